@@ -0,0 +1,58 @@
+//! Compare the fuzzy scorers on the paths of a 10 000-file tree
+//!
+//! Run with `cargo bench --features nucleo` to include the nucleo scorer.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gthr::fuzzy::matcher::{FuzzyScorer, FuzzySearch};
+use std::hint::black_box;
+
+const FILE_COUNT: usize = 10_000;
+
+const QUERIES: &[&str] = &["main", "srcapirs", "config toml", "test_util", "README"];
+
+/// Relative paths shaped like a real project: nested modules with mixed extensions
+fn tree_paths() -> Vec<String> {
+    let top_levels = ["src", "tests", "docs", "crates/core/src", "web/components"];
+    let modules = ["api", "config", "directory", "output", "ui", "util", "fuzzy", "auth"];
+    let extensions = ["rs", "toml", "md", "ts", "json"];
+
+    (0..FILE_COUNT)
+        .map(|i| {
+            format!(
+                "{}/{}/{}_{}/file_{}.{}",
+                top_levels[i % top_levels.len()],
+                modules[(i / 7) % modules.len()],
+                modules[(i / 3) % modules.len()],
+                i / 100,
+                i,
+                extensions[i % extensions.len()]
+            )
+        })
+        .collect()
+}
+
+fn bench_scorers(c: &mut Criterion) {
+    let paths = tree_paths();
+    let mut scorers = vec![FuzzyScorer::Skim];
+    if cfg!(feature = "nucleo") {
+        scorers.push(FuzzyScorer::Nucleo);
+    }
+
+    for query in QUERIES {
+        let mut group = c.benchmark_group(format!("fuzzy/{}", query));
+        for &scorer in &scorers {
+            let fuzzy_search = FuzzySearch::new().with_scorer(scorer);
+            group.bench_with_input(
+                BenchmarkId::from_parameter(format!("{:?}", scorer)),
+                query,
+                |b, query| {
+                    b.iter(|| fuzzy_search.search_items(&paths, black_box(query), |path| path))
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_scorers);
+criterion_main!(benches);
@@ -1,18 +1,29 @@
-use crate::constants::DEFAULT_MAX_FILE_SIZE;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use crate::constants::{DEFAULT_MAX_FILE_SIZE, DEFAULT_MIN_FILE_SIZE};
+use crate::directory::git::GitSelection;
+use crate::error::EXIT_CODES_HELP;
+use crate::output::budget::TokenPriority;
+use crate::output::formatter::{FrontMatterFormat, OutputFormat, PathStyle, TreeStyle};
+use crate::output::writer::OverwriteMode;
+use crate::util::parse_byte_size;
+use crate::verbosity::Verbosity;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+use clap_complete::Shell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 #[derive(Parser)]
 #[command(name = "gthr")]
 #[command(about = "A CLI tool for directory text ingestion")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
+#[command(after_help = EXIT_CODES_HELP)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
 
-    /// Root directory to process
-    #[arg(short, long, default_value = ".")]
-    pub root: PathBuf,
+    /// Root directory to process (repeat to combine several directories)
+    #[arg(short, long, default_value = ".", value_hint = ValueHint::DirPath)]
+    pub root: Vec<PathBuf>,
 
     /// Pre-include all files and directories
     #[arg(short = 'I', long = "include-all", conflicts_with = "exclude_all")]
@@ -30,8 +41,28 @@ pub struct Cli {
     #[arg(short = 'e', long = "exclude")]
     pub exclude: Vec<String>,
 
+    /// Include files with these extensions, e.g. rs,toml,md (case-insensitive)
+    #[arg(
+        short = 'x',
+        long,
+        visible_alias = "include-extensions",
+        value_name = "EXTS",
+        value_delimiter = ','
+    )]
+    pub ext: Vec<String>,
+
+    /// Exclude files with these extensions (case-insensitive)
+    #[arg(
+        short = 'X',
+        long = "not-ext",
+        visible_alias = "exclude-extensions",
+        value_name = "EXTS",
+        value_delimiter = ','
+    )]
+    pub not_ext: Vec<String>,
+
     /// Output file path
-    #[arg(short, long)]
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
     pub output: Option<PathBuf>,
 
     /// Respect .gitignore files
@@ -45,6 +76,223 @@ pub struct Cli {
     /// Maximum file size to include (in bytes)
     #[arg(long, default_value_t = DEFAULT_MAX_FILE_SIZE)]
     pub max_file_size: u64,
+
+    /// Minimum file size to include (in bytes); files of exactly this size are kept
+    #[arg(long, default_value_t = DEFAULT_MIN_FILE_SIZE)]
+    pub min_file_size: u64,
+
+    /// Fail instead of exporting when the output is larger than this (e.g. 100KB, 2MB)
+    #[arg(long = "max-total-size", value_name = "SIZE", value_parser = parse_byte_size)]
+    pub max_total_size: Option<u64>,
+
+    /// Leave out included files until the output fits in this many estimated tokens
+    #[arg(long = "token-limit", value_name = "TOKENS")]
+    pub token_limit: Option<usize>,
+
+    /// Which files --token-limit keeps: small ones (size-asc), large ones (size-desc) or
+    /// the first paths (name)
+    #[arg(
+        long = "token-priority",
+        value_enum,
+        default_value_t = TokenPriority::SizeAsc,
+        requires = "token_limit"
+    )]
+    pub token_priority: TokenPriority,
+
+    /// Maximum directory depth to traverse (0 = only the root's direct children)
+    #[arg(long = "max-depth")]
+    pub max_depth: Option<usize>,
+
+    /// Only include files modified since a duration ago (30m, 2h, 3d, 1w) or a date (2024-06-01)
+    #[arg(long = "modified-since", value_name = "WHEN", value_parser = parse_modified_since)]
+    pub modified_since: Option<SystemTime>,
+
+    /// Don't apply .gthrignore files or the global gthr ignore file
+    #[arg(long = "no-gthrignore")]
+    pub no_gthrignore: bool,
+
+    /// Detect text files without reading or writing the .gthr_cache of earlier runs
+    #[arg(long = "no-cache")]
+    pub no_cache: bool,
+
+    /// Never include files matching this gitignore-style file, repeatable
+    #[arg(long = "ignore-file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub ignore_files: Vec<PathBuf>,
+
+    /// Follow symbolic links while traversing
+    #[arg(long = "follow-symlinks")]
+    pub follow_symlinks: bool,
+
+    /// Strip comments from source files to reduce token count
+    #[arg(long = "strip-comments")]
+    pub strip_comments: bool,
+
+    /// Skip empty and whitespace-only files in the output
+    #[arg(long = "skip-empty")]
+    pub skip_empty: bool,
+
+    /// Append to the -o file after a `---` separator instead of overwriting it
+    #[arg(long)]
+    pub append: bool,
+
+    /// Overwrite existing output files without asking
+    #[arg(long, conflicts_with = "backup")]
+    pub force: bool,
+
+    /// Rename an existing output file to <name>.bak before writing
+    #[arg(long, conflicts_with = "append")]
+    pub backup: bool,
+
+    /// In direct mode, keep running and rewrite the -o file whenever the files change
+    #[arg(long, requires = "output", conflicts_with = "append")]
+    pub watch: bool,
+
+    /// Poll for changes every N seconds instead of using file system notifications
+    #[arg(long, value_name = "SECONDS", requires = "watch")]
+    pub watch_interval: Option<u64>,
+
+    /// Open the output file once it is written (`--open=editor` uses $EDITOR)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WITH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "default"
+    )]
+    pub open: Option<OpenWith>,
+
+    /// Open the interactive mode with this search already typed
+    #[arg(short = 'q', long, value_name = "TEXT")]
+    pub query: Option<String>,
+
+    /// Session file used to save and restore interactive selections
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub session: Option<PathBuf>,
+
+    /// Before exporting, list files added, removed or modified since a saved session
+    #[arg(long = "diff-against", value_name = "SESSION", value_hint = ValueHint::FilePath)]
+    pub diff_against: Option<PathBuf>,
+
+    /// Write the included file paths to this file when exporting, one per line
+    #[arg(long = "save-selection", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub save_selection: Option<PathBuf>,
+
+    /// After the output, also write the included file paths to this plain text file
+    ///
+    /// One path per line in --path-style, without headers. In interactive mode Alt+P
+    /// writes the file.
+    #[arg(long = "export-paths", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub export_paths: Option<PathBuf>,
+
+    /// Start from the files listed in a --save-selection file (combines with -i and -e)
+    #[arg(
+        long = "load-selection",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = ["git_diff", "staged", "untracked"]
+    )]
+    pub load_selection: Option<PathBuf>,
+
+    /// Prepend YAML or TOML front matter to Markdown output (overrides config)
+    #[arg(long = "front-matter", value_enum, value_name = "FORMAT")]
+    pub front_matter: Option<FrontMatterFormat>,
+
+    /// Tag listed in the front matter, repeatable
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Include the file tree in the output, also in Markdown and for a single file
+    #[arg(long, overrides_with = "no_tree")]
+    pub tree: bool,
+
+    /// Leave the file tree out of the output
+    #[arg(long = "no-tree", overrides_with = "tree")]
+    pub no_tree: bool,
+
+    /// Output only the file tree and statistics, without file contents
+    #[arg(long = "summary-only", conflicts_with_all = ["template", "format"])]
+    pub summary_only: bool,
+
+    /// Output only the tree of every traversed file, included or not, like tree(1)
+    #[arg(long = "tree-only", conflicts_with_all = ["summary_only", "template", "format"])]
+    pub tree_only: bool,
+
+    /// How file paths are written in the output (overrides config)
+    #[arg(
+        long = "path-style",
+        value_enum,
+        conflicts_with_all = ["absolute_paths", "relative_paths"]
+    )]
+    pub path_style: Option<PathStyle>,
+
+    /// Write absolute file paths, short for --path-style absolute
+    #[arg(long = "absolute-paths", overrides_with = "relative_paths")]
+    pub absolute_paths: bool,
+
+    /// Write file paths relative to the root (the default), short for --path-style relative
+    #[arg(long = "relative-paths", overrides_with = "absolute_paths")]
+    pub relative_paths: bool,
+
+    /// How the file tree is drawn
+    #[arg(long = "tree-style", value_enum, default_value_t = TreeStyle::Unicode)]
+    pub tree_style: TreeStyle,
+
+    /// Copy the list of included file paths instead of their contents
+    #[arg(long = "copy-path")]
+    pub copy_path: bool,
+
+    /// Copy to the clipboard, failing instead of falling back to a file prompt
+    #[arg(long, conflicts_with_all = ["output", "pipe", "print0"])]
+    pub clipboard: bool,
+
+    /// With --copy-path, list paths relative to the root instead of absolute
+    #[arg(long, requires = "copy_path")]
+    pub relative: bool,
+
+    /// Pipe the output to a shell command instead of the clipboard or a file
+    #[arg(long, value_name = "COMMAND", conflicts_with = "output")]
+    pub pipe: Option<String>,
+
+    /// Pre-include only files changed since a git ref (combines with -i and -e)
+    #[arg(long = "git-diff", visible_alias = "since-commit", value_name = "REF")]
+    pub git_diff: Option<String>,
+
+    /// Pre-include only files staged in the git index (combines with -i and -e)
+    #[arg(long, visible_alias = "git-staged", conflicts_with_all = ["git_diff", "untracked"])]
+    pub staged: bool,
+
+    /// Pre-include only files not yet tracked by git (combines with -i and -e)
+    #[arg(long, conflicts_with = "git_diff")]
+    pub untracked: bool,
+
+    /// Apply the `[profiles.NAME]` section of the config over the other settings
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Handlebars template used to render the output (overrides config)
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub template: Option<PathBuf>,
+
+    /// Output format of the gathered text
+    #[arg(long, value_enum, default_value_t = OutputFormat::Markdown, conflicts_with = "template")]
+    pub format: OutputFormat,
+
+    /// Log skipped and unreadable entries to stderr; -vv also logs every included file
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only report errors: no warnings, --verbose logs or success messages
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Exit with code 2 when nothing is included, also for `list`, `stats`, `tokens` and --print0
+    #[arg(long)]
+    pub fail_on_empty: bool,
+
+    /// Separate printed file paths with NUL (`list`, and `--copy-path`, which then prints)
+    #[arg(long)]
+    pub print0: bool,
 }
 
 #[derive(Subcommand)]
@@ -53,21 +301,337 @@ pub enum Commands {
     Interactive,
     /// Generate text ingest directly without interaction
     Direct,
+    /// Print a language breakdown and statistics of the included files
+    Stats {
+        /// Output format of the statistics
+        #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+        format: StatsFormat,
+    },
+    /// Print estimated token counts of the included files, largest first
+    Tokens {
+        /// Only list the N files with the most tokens (the total still covers all)
+        #[arg(long, value_name = "N")]
+        top: Option<usize>,
+    },
+    /// Print the relative paths of the included files without generating output
+    List {
+        /// Add size (in bytes) and language columns
+        #[arg(long)]
+        long: bool,
+        /// Separate entries with NUL instead of newlines, for `xargs -0`
+        #[arg(short = '0', long = "null")]
+        null: bool,
+    },
+    /// Create or inspect .gthr.toml configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Write a commented default config to the project root
+    Init {
+        /// Write the global config instead of the project config
+        #[arg(long)]
+        global: bool,
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the effective settings and where each value came from
+    Show {
+        /// Output format of the settings
+        #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+        format: StatsFormat,
+        /// Only show settings that differ from the defaults
+        #[arg(long)]
+        diff: bool,
+    },
+    /// Print the global and project config paths
+    Path,
+    /// Manage the `[profiles.NAME]` presets used with `--profile`
+    Profiles {
+        #[command(subcommand)]
+        action: ProfilesAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProfilesAction {
+    /// List the profiles defined in the global and project configs
+    List,
+}
+
+/// How `--open` launches the written output file
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OpenWith {
+    /// The platform's default application (`open`, `xdg-open` or `start`)
+    Default,
+    /// `$EDITOR`, falling back to the default application when it is unset
+    Editor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
+impl Cli {
+    /// Reporting level from -v and --quiet
+    pub fn verbosity(&self) -> Verbosity {
+        Verbosity::from_flags(self.verbose, self.quiet)
+    }
+
+    /// Git-driven selection requested by --git-diff, --staged or --untracked
+    pub fn git_selection(&self) -> Option<GitSelection> {
+        if let Some(base_ref) = &self.git_diff {
+            Some(GitSelection::Diff(base_ref.clone()))
+        } else if self.staged {
+            Some(GitSelection::Staged)
+        } else if self.untracked {
+            Some(GitSelection::Untracked)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the command line picks the initial selection, which then wins over the
+    /// interactive session saved with Ctrl+S
+    pub fn sets_selection(&self) -> bool {
+        self.include_all
+            || self.exclude_all
+            || !self.include.is_empty()
+            || !self.exclude.is_empty()
+            || !self.ext.is_empty()
+            || !self.not_ext.is_empty()
+            || self.git_selection().is_some()
+            || self.load_selection.is_some()
+    }
+
+    /// --path-style, --absolute-paths or --relative-paths, if given
+    pub fn path_style(&self) -> Option<PathStyle> {
+        if self.absolute_paths {
+            Some(PathStyle::Absolute)
+        } else if self.relative_paths {
+            Some(PathStyle::Relative)
+        } else {
+            self.path_style
+        }
+    }
+
+    /// --tree or --no-tree, whichever was given last
+    pub fn show_tree(&self) -> Option<bool> {
+        if self.tree {
+            Some(true)
+        } else if self.no_tree {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// How existing output files are treated, from --force and --backup
+    pub fn overwrite_mode(&self) -> OverwriteMode {
+        if self.force {
+            OverwriteMode::Force
+        } else if self.backup {
+            OverwriteMode::Backup
+        } else {
+            OverwriteMode::Refuse
+        }
+    }
+
+    /// First root directory, used to locate the project config
+    pub fn project_root(&self) -> &Path {
+        self.root.first().map_or(Path::new("."), PathBuf::as_path)
+    }
+}
+
+/// Write the completion script for `shell` to `out`
+pub fn generate_completions(shell: Shell, out: &mut dyn Write) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, out);
 }
 
 impl Default for Cli {
     fn default() -> Self {
         Self {
             command: Some(Commands::Interactive),
-            root: PathBuf::from("."),
+            root: vec![PathBuf::from(".")],
             include_all: false,
             exclude_all: false,
             include: Vec::new(),
             exclude: Vec::new(),
+            ext: Vec::new(),
+            not_ext: Vec::new(),
             output: None,
             respect_gitignore: None,
             show_hidden: None,
             max_file_size: DEFAULT_MAX_FILE_SIZE,
+            min_file_size: DEFAULT_MIN_FILE_SIZE,
+            max_total_size: None,
+            token_limit: None,
+            token_priority: TokenPriority::SizeAsc,
+            max_depth: None,
+            modified_since: None,
+            no_gthrignore: false,
+            no_cache: false,
+            ignore_files: Vec::new(),
+            follow_symlinks: false,
+            strip_comments: false,
+            skip_empty: false,
+            append: false,
+            force: false,
+            backup: false,
+            watch: false,
+            watch_interval: None,
+            open: None,
+            query: None,
+            session: None,
+            diff_against: None,
+            save_selection: None,
+            export_paths: None,
+            load_selection: None,
+            front_matter: None,
+            tags: Vec::new(),
+            tree: false,
+            no_tree: false,
+            summary_only: false,
+            tree_only: false,
+            path_style: None,
+            absolute_paths: false,
+            relative_paths: false,
+            tree_style: TreeStyle::Unicode,
+            copy_path: false,
+            clipboard: false,
+            relative: false,
+            pipe: None,
+            git_diff: None,
+            staged: false,
+            untracked: false,
+            profile: None,
+            template: None,
+            format: OutputFormat::Markdown,
+            verbose: 0,
+            quiet: false,
+            fail_on_empty: false,
+            print0: false,
+        }
+    }
+}
+
+/// Parse `--modified-since` as a duration before now or a local date
+fn parse_modified_since(value: &str) -> Result<SystemTime, String> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let midnight = date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+            .ok_or_else(|| format!("{} has no local midnight", value))?;
+        return Ok(midnight.into());
+    }
+
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|&index| index > 0)
+        .ok_or_else(|| {
+            format!(
+                "expected a duration like 2h or 3d, or a date like 2024-06-01, got '{}'",
+                value
+            )
+        })?;
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("duration '{}' is too large", value))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown duration unit '{}', expected s, m, h, d or w",
+                unit
+            ));
+        }
+    };
+
+    amount
+        .checked_mul(unit_secs)
+        .and_then(|secs| SystemTime::now().checked_sub(Duration::from_secs(secs)))
+        .ok_or_else(|| format!("duration '{}' is too large", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_completions() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let mut script = Vec::new();
+            generate_completions(shell, &mut script);
+            let script = String::from_utf8(script).unwrap();
+
+            assert!(!script.is_empty(), "{} completions are empty", shell);
+            assert!(
+                script.contains("gthr"),
+                "{} completions miss the program name",
+                shell
+            );
         }
     }
+
+    #[test]
+    fn test_sets_selection() {
+        let sets_selection = |args: &[&str]| {
+            Cli::try_parse_from(std::iter::once("gthr").chain(args.iter().copied()))
+                .unwrap()
+                .sets_selection()
+        };
+        assert!(!sets_selection(&[]));
+        assert!(!sets_selection(&["--max-depth", "2", "--session", "s"]));
+        assert!(sets_selection(&["-i", "src/**"]));
+        assert!(sets_selection(&["-e", "*.lock"]));
+        assert!(sets_selection(&["--ext", "rs"]));
+        assert!(sets_selection(&["-I"]));
+        assert!(sets_selection(&["--staged"]));
+        assert!(sets_selection(&["--git-diff", "main"]));
+        assert!(sets_selection(&["--load-selection", ".gthr-selection"]));
+    }
+
+    #[test]
+    fn test_parse_modified_since() {
+        let hours_ago = |value| {
+            let cutoff = parse_modified_since(value).unwrap();
+            SystemTime::now().duration_since(cutoff).unwrap().as_secs() / 3600
+        };
+        assert_eq!(hours_ago("2h"), 2);
+        assert_eq!(hours_ago("3d"), 72);
+        assert_eq!(hours_ago("1w"), 168);
+        assert!(parse_modified_since("2024-06-01").unwrap() < SystemTime::now());
+
+        assert!(
+            parse_modified_since("3x")
+                .unwrap_err()
+                .contains("unknown duration unit")
+        );
+        assert!(
+            parse_modified_since("h")
+                .unwrap_err()
+                .contains("expected a duration")
+        );
+        assert!(parse_modified_since("2024-13-01").is_err());
+    }
 }
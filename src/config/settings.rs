@@ -1,43 +1,254 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use anyhow::Result;
-use crate::constants::DEFAULT_MAX_FILE_SIZE;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result, bail};
+use crate::output::encoding::DEFAULT_ENCODING_FALLBACKS;
+use crate::output::formatter::{FrontMatterFormat, PathStyle};
+use crate::output::writer::DEFAULT_CLIPBOARD_BACKENDS;
+use crate::constants::{
+    DEFAULT_FILENAME_MATCH_BOOST, DEFAULT_MAX_CLIPBOARD_SIZE, DEFAULT_MAX_FILE_SIZE,
+    DEFAULT_MIN_FILE_SIZE,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     #[serde(default = "default_max_file_size")]
     pub max_file_size: u64,
+    #[serde(default = "default_min_file_size")]
+    pub min_file_size: u64,
     #[serde(default = "default_max_clipboard_size")]
     pub max_clipboard_size: usize,
+    #[serde(default = "default_clipboard_backends")]
+    pub clipboard_backends: Vec<String>,
     #[serde(default = "default_respect_gitignore")]
     pub respect_gitignore: bool,
     #[serde(default = "default_show_hidden")]
     pub show_hidden: bool,
-    #[serde(default = "default_include_metadata")]
-    pub include_metadata: bool,
+    #[serde(default)]
+    pub include_metadata: Option<bool>,
     #[serde(default = "default_include_line_numbers")]
     pub include_line_numbers: bool,
     #[serde(default)]
     pub default_output_dir: Option<PathBuf>,
+    #[serde(default = "default_strip_comments")]
+    pub strip_comments: bool,
+    #[serde(default = "default_skip_empty_files")]
+    pub skip_empty_files: bool,
+    #[serde(default = "default_hash_files")]
+    pub hash_files: bool,
+    #[serde(default = "default_encoding_fallbacks")]
+    pub encoding_fallbacks: Vec<String>,
+    #[serde(default)]
+    pub template_path: Option<PathBuf>,
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub front_matter: Option<FrontMatterFormat>,
+    #[serde(default)]
+    pub path_style: PathStyle,
+    #[serde(default)]
+    pub max_total_size_warning: Option<u64>,
+    #[serde(default)]
+    pub token_warn_threshold: Option<usize>,
+    #[serde(default)]
+    pub show_tree: Option<bool>,
+    #[serde(default)]
+    pub ignore_files: Vec<PathBuf>,
+    #[serde(default)]
+    pub cache_path: Option<PathBuf>,
+    #[serde(default = "default_fuzzy_scorer")]
+    pub fuzzy_scorer: String,
+    #[serde(default = "default_score_boost_filename_match")]
+    pub score_boost_filename_match: f64,
+    #[serde(default = "default_use_multi_query")]
+    pub use_multi_query: bool,
+    #[serde(default = "default_multi_query_delimiter")]
+    pub multi_query_delimiter: char,
+    /// `[profiles.NAME]` sections of settings applied with `--profile NAME`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+/// Descriptions written above each setting by `gthr config init`
+const SETTING_DOCS: &[(&str, &str)] = &[
+    ("max_file_size", "Maximum file size to include when traversing directories (in bytes)"),
+    (
+        "min_file_size",
+        "Minimum file size to include when traversing directories (in bytes)\n\
+         Files of exactly this size are kept",
+    ),
+    (
+        "max_clipboard_size",
+        "Maximum size for clipboard operations (in bytes)\n\
+         When output exceeds this size, you'll be prompted to save to a file instead",
+    ),
+    (
+        "clipboard_backends",
+        "Clipboard backends tried in order: \"arboard\", \"xclip\", \"xsel\" or \"wl-copy\"\n\
+         Command line tools that are not installed are skipped",
+    ),
+    ("respect_gitignore", "Whether to respect .gitignore files by default"),
+    ("show_hidden", "Whether to show hidden files and directories (starting with .)"),
+    (
+        "include_metadata",
+        "Whether to include a report header and per-file metadata\n\
+         Unset: left out of exported output",
+    ),
+    ("include_line_numbers", "Whether to prefix each line of code with its line number"),
+    (
+        "default_output_dir",
+        "Directory where saved output and session files are stored\n\
+         Relative paths resolve against the project root",
+    ),
+    ("strip_comments", "Whether to strip comments from source files before export"),
+    ("skip_empty_files", "Whether to leave empty and whitespace-only files out of the output"),
+    (
+        "hash_files",
+        "Whether to add SHA-256 hashes of included files to the output\n\
+         Each file gets a hash line, and the report header lists short hashes",
+    ),
+    (
+        "encoding_fallbacks",
+        "Encodings tried, in order, for files that are neither UTF-8 nor UTF-16\n\
+         Any WHATWG encoding label works, e.g. \"shift_jis\" or \"windows-1251\"",
+    ),
+    (
+        "template_path",
+        "Handlebars template used to render the output instead of the built-in Markdown",
+    ),
+    ("max_depth", "Maximum directory depth to traverse (0 = only the root's direct children)"),
+    ("front_matter", "Front matter prepended to Markdown output: \"yaml\" or \"toml\""),
+    (
+        "path_style",
+        "How file paths are written in the output: \"relative\" (src/main.rs), \"absolute\" or\n\
+         \"root-relative\" (project/src/main.rs)",
+    ),
+    (
+        "max_total_size_warning",
+        "Size of the included files (in bytes) above which the interactive status bar warns",
+    ),
+    (
+        "token_warn_threshold",
+        "Estimated tokens above which the status bar token count turns red (yellow from 80%)",
+    ),
+    (
+        "show_tree",
+        "Whether to include the file tree in the output\n\
+         Unset: in XML and templates when more than one file is included, never in Markdown",
+    ),
+    (
+        "ignore_files",
+        "Gitignore-style files whose matches are never included, like --ignore-file\n\
+         Relative paths resolve against the project root",
+    ),
+    (
+        "cache_path",
+        "File caching which files are text, instead of .gthr_cache in each root\n\
+         Relative paths resolve against the project root",
+    ),
+    (
+        "fuzzy_scorer",
+        "Fuzzy matcher for the interactive search: \"skim\" or \"nucleo\"\n\
+         \"nucleo\" needs gthr built with the `nucleo` feature",
+    ),
+    (
+        "score_boost_filename_match",
+        "Multiplier for fuzzy search scores when the whole match is in the file name\n\
+         1.0 ranks file name and directory matches alike",
+    ),
+    (
+        "use_multi_query",
+        "Whether fuzzy search queries are split into terms that must all match",
+    ),
+    (
+        "multi_query_delimiter",
+        "Character separating multi-query search terms\n\
+         Change it (e.g. to \"|\") to search for literal spaces",
+    ),
+];
+
+/// Config file an effective setting was taken from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    Global,
+    Project,
+    /// The profile selected with `--profile`
+    Profile,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::Global => write!(f, "global"),
+            ConfigSource::Project => write!(f, "project"),
+            ConfigSource::Profile => write!(f, "profile"),
+        }
+    }
+}
+
+/// A setting's effective value and the file it came from; unset options have no value
+#[derive(Debug, Clone)]
+pub struct EffectiveSetting {
+    pub key: &'static str,
+    pub value: Option<toml::Value>,
+    pub source: ConfigSource,
+    /// Whether the value equals the built-in default, wherever it came from
+    pub is_default: bool,
 }
 
 fn default_max_file_size() -> u64 { DEFAULT_MAX_FILE_SIZE }
-fn default_max_clipboard_size() -> usize { 2 * 1024 * 1024 }
+fn default_min_file_size() -> u64 { DEFAULT_MIN_FILE_SIZE }
+fn default_max_clipboard_size() -> usize { DEFAULT_MAX_CLIPBOARD_SIZE }
+fn default_clipboard_backends() -> Vec<String> {
+    DEFAULT_CLIPBOARD_BACKENDS.iter().map(|name| name.to_string()).collect()
+}
 fn default_respect_gitignore() -> bool { true }
 fn default_show_hidden() -> bool { false }
-fn default_include_metadata() -> bool { true }
 fn default_include_line_numbers() -> bool { false }
+fn default_strip_comments() -> bool { false }
+fn default_skip_empty_files() -> bool { false }
+fn default_hash_files() -> bool { false }
+fn default_encoding_fallbacks() -> Vec<String> {
+    DEFAULT_ENCODING_FALLBACKS.iter().map(|label| label.to_string()).collect()
+}
+fn default_fuzzy_scorer() -> String { "skim".to_string() }
+fn default_score_boost_filename_match() -> f64 { DEFAULT_FILENAME_MATCH_BOOST }
+fn default_use_multi_query() -> bool { true }
+fn default_multi_query_delimiter() -> char { ' ' }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             max_file_size: default_max_file_size(),
+            min_file_size: default_min_file_size(),
             max_clipboard_size: default_max_clipboard_size(),
+            clipboard_backends: default_clipboard_backends(),
             respect_gitignore: default_respect_gitignore(),
             show_hidden: default_show_hidden(),
-            include_metadata: default_include_metadata(),
+            include_metadata: None,
             include_line_numbers: default_include_line_numbers(),
             default_output_dir: None,
+            strip_comments: default_strip_comments(),
+            skip_empty_files: default_skip_empty_files(),
+            hash_files: default_hash_files(),
+            encoding_fallbacks: default_encoding_fallbacks(),
+            template_path: None,
+            max_depth: None,
+            front_matter: None,
+            path_style: PathStyle::Relative,
+            max_total_size_warning: None,
+            token_warn_threshold: None,
+            show_tree: None,
+            ignore_files: Vec::new(),
+            cache_path: None,
+            fuzzy_scorer: default_fuzzy_scorer(),
+            score_boost_filename_match: default_score_boost_filename_match(),
+            use_multi_query: default_use_multi_query(),
+            multi_query_delimiter: default_multi_query_delimiter(),
+            profiles: BTreeMap::new(),
         }
     }
 }
@@ -68,6 +279,11 @@ impl Settings {
         }
     }
 
+    /// Ignore file applied to every project, in gitignore syntax
+    pub fn get_global_ignore_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|config_dir| config_dir.join("gthr").join("ignore"))
+    }
+
     pub fn get_project_config_path(project_root: &std::path::Path) -> PathBuf {
         project_root.join(".gthr.toml")
     }
@@ -101,16 +317,22 @@ impl Settings {
         if project.max_file_size != default_max_file_size() {
             global.max_file_size = project.max_file_size;
         }
+        if project.min_file_size != default_min_file_size() {
+            global.min_file_size = project.min_file_size;
+        }
         if project.max_clipboard_size != default_max_clipboard_size() {
             global.max_clipboard_size = project.max_clipboard_size;
         }
+        if project.clipboard_backends != default_clipboard_backends() {
+            global.clipboard_backends = project.clipboard_backends;
+        }
         if project.respect_gitignore != default_respect_gitignore() {
             global.respect_gitignore = project.respect_gitignore;
         }
         if project.show_hidden != default_show_hidden() {
             global.show_hidden = project.show_hidden;
         }
-        if project.include_metadata != default_include_metadata() {
+        if project.include_metadata.is_some() {
             global.include_metadata = project.include_metadata;
         }
         if project.include_line_numbers != default_include_line_numbers() {
@@ -119,9 +341,304 @@ impl Settings {
         if project.default_output_dir.is_some() {
             global.default_output_dir = project.default_output_dir;
         }
+        if project.strip_comments != default_strip_comments() {
+            global.strip_comments = project.strip_comments;
+        }
+        if project.skip_empty_files != default_skip_empty_files() {
+            global.skip_empty_files = project.skip_empty_files;
+        }
+        if project.hash_files != default_hash_files() {
+            global.hash_files = project.hash_files;
+        }
+        if project.encoding_fallbacks != default_encoding_fallbacks() {
+            global.encoding_fallbacks = project.encoding_fallbacks;
+        }
+        if project.template_path.is_some() {
+            global.template_path = project.template_path;
+        }
+        if project.max_depth.is_some() {
+            global.max_depth = project.max_depth;
+        }
+        if project.front_matter.is_some() {
+            global.front_matter = project.front_matter;
+        }
+        if project.path_style != PathStyle::default() {
+            global.path_style = project.path_style;
+        }
+        if project.max_total_size_warning.is_some() {
+            global.max_total_size_warning = project.max_total_size_warning;
+        }
+        if project.token_warn_threshold.is_some() {
+            global.token_warn_threshold = project.token_warn_threshold;
+        }
+        if project.show_tree.is_some() {
+            global.show_tree = project.show_tree;
+        }
+        if project.fuzzy_scorer != default_fuzzy_scorer() {
+            global.fuzzy_scorer = project.fuzzy_scorer;
+        }
+        if project.score_boost_filename_match != default_score_boost_filename_match() {
+            global.score_boost_filename_match = project.score_boost_filename_match;
+        }
+        if project.use_multi_query != default_use_multi_query() {
+            global.use_multi_query = project.use_multi_query;
+        }
+        if project.multi_query_delimiter != default_multi_query_delimiter() {
+            global.multi_query_delimiter = project.multi_query_delimiter;
+        }
+        if project.cache_path.is_some() {
+            global.cache_path = project.cache_path;
+        }
+        // Ignore files only ever add protection, so both configs apply
+        global.ignore_files.extend(project.ignore_files);
+        // A project profile replaces a global one of the same name
+        global.profiles.extend(project.profiles);
         global
     }
 
+    /// Names of the defined profiles, each with the profile it `extends`
+    pub fn profile_names(&self) -> Vec<(&str, Option<&str>)> {
+        self.profiles
+            .iter()
+            .map(|(name, profile)| {
+                let extends = profile.get("extends").and_then(toml::Value::as_str);
+                (name.as_str(), extends)
+            })
+            .collect()
+    }
+
+    /// Apply the `[profiles.NAME]` section, after the profiles it `extends`
+    ///
+    /// Profile values override these settings the way the project config overrides the
+    /// global one, so values equal to the defaults leave them unchanged.
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        for name in self.profile_chain(name)?.iter().rev() {
+            let mut table = self.profiles[name].clone();
+            table.remove("extends");
+            for key in table.keys() {
+                if !SETTING_DOCS.iter().any(|(doc_key, _)| doc_key == key) {
+                    crate::warn!("Unknown setting {} in profile \"{}\"", key, name);
+                }
+            }
+            let profile: Settings = toml::Value::Table(table)
+                .try_into()
+                .with_context(|| format!("Invalid profile \"{}\"", name))?;
+            *self = Self::merge_settings(self.clone(), profile);
+        }
+        Ok(())
+    }
+
+    /// Names from profile `name` up to the profile that extends no other
+    fn profile_chain(&self, name: &str) -> Result<Vec<String>> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut next = Some(name.to_string());
+        while let Some(name) = next {
+            if chain.contains(&name) {
+                chain.push(name);
+                bail!("Profiles extend each other in a cycle: {}", chain.join(" -> "));
+            }
+            let Some(profile) = self.profiles.get(&name) else {
+                let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                bail!("Unknown profile \"{}\" (defined: {})", name, known.join(", "));
+            };
+            next = match profile.get("extends") {
+                Some(toml::Value::String(parent)) => Some(parent.clone()),
+                Some(_) => bail!("extends in profile \"{}\" must be a profile name", name),
+                None => None,
+            };
+            chain.push(name);
+        }
+        Ok(chain)
+    }
+
+    /// Serialize as TOML with a descriptive comment above every setting
+    pub fn to_commented_toml(&self) -> Result<String> {
+        let table = toml::Table::try_from(self)?;
+        let mut output = String::from(
+            "# gthr configuration\n#\n# Only specify the settings you want to change from defaults.\n",
+        );
+
+        for (key, doc) in SETTING_DOCS {
+            output.push('\n');
+            for line in doc.lines() {
+                output.push_str(&format!("# {}\n", line.trim()));
+            }
+            match table.get(*key) {
+                Some(value) => output.push_str(&format!("{} = {}\n", key, value)),
+                // Unset options are left commented out
+                None => output.push_str(&format!("# {} = \"\"\n", key)),
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Write a commented default config to `path`, refusing to overwrite unless `force` is set
+    pub fn write_default_config(path: &Path, force: bool) -> Result<()> {
+        if path.exists() && !force {
+            bail!("{} already exists, use --force to overwrite it", path.display());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, Self::default().to_commented_toml()?)?;
+        Ok(())
+    }
+
+    /// Effective value of every setting, annotated with the config file it came from
+    ///
+    /// `profile` is the profile these settings were loaded with, if any.
+    pub fn effective_settings(
+        &self,
+        project_root: &Path,
+        profile: Option<&str>,
+    ) -> Result<Vec<EffectiveSetting>> {
+        self.effective_settings_from(
+            &Self::get_global_config_path(),
+            &Self::get_project_config_path(project_root),
+            profile,
+        )
+    }
+
+    fn effective_settings_from(
+        &self,
+        global_path: &Path,
+        project_path: &Path,
+        profile: Option<&str>,
+    ) -> Result<Vec<EffectiveSetting>> {
+        let effective = toml::Table::try_from(self)?;
+        let defaults = toml::Table::try_from(Self::default())?;
+        let read_table = |path: &Path| {
+            std::fs::read_to_string(path)
+                .ok()
+                .and_then(|content| content.parse::<toml::Table>().ok())
+                .unwrap_or_default()
+        };
+        let global = read_table(global_path);
+        let project = read_table(project_path);
+        // Values of the profile, over those of the profiles it extends
+        let mut profile_table = toml::Table::new();
+        if let Some(profile) = profile {
+            for name in self.profile_chain(profile)?.iter().rev() {
+                profile_table.extend(self.profiles[name].clone());
+            }
+        }
+
+        let settings = SETTING_DOCS
+            .iter()
+            .map(|(key, _)| {
+                let value = effective.get(*key).cloned();
+                // Project values only win when they differ from the default, so compare
+                // against the effective value rather than checking for presence
+                let source = match &value {
+                    Some(value) if profile_table.get(*key) == Some(value) => ConfigSource::Profile,
+                    Some(value) if project.get(*key) == Some(value) => ConfigSource::Project,
+                    Some(value) if global.get(*key) == Some(value) => ConfigSource::Global,
+                    _ => ConfigSource::Default,
+                };
+                let is_default = value.as_ref() == defaults.get(*key);
+                EffectiveSetting {
+                    key,
+                    value,
+                    source,
+                    is_default,
+                }
+            })
+            .collect();
+
+        Ok(settings)
+    }
+
+    /// Documented settings in config file order, as `(key, description)`
+    pub fn setting_docs() -> &'static [(&'static str, &'static str)] {
+        SETTING_DOCS
+    }
+
+    /// Current value of `key`; unset options have no value
+    pub fn value(&self, key: &str) -> Result<Option<toml::Value>> {
+        Ok(toml::Table::try_from(self)?.remove(key))
+    }
+
+    /// Set `key` to `value`, or back to its default with `None`
+    pub fn set_value(&mut self, key: &str, value: Option<toml::Value>) -> Result<()> {
+        if !SETTING_DOCS.iter().any(|(doc_key, _)| *doc_key == key) {
+            bail!("Unknown setting {}", key);
+        }
+        let mut table = toml::Table::try_from(&*self)?;
+        match value {
+            Some(value) => table.insert(key.to_string(), value),
+            None => table.remove(key),
+        };
+        *self = toml::Value::Table(table)
+            .try_into()
+            .with_context(|| format!("Invalid value for {}", key))?;
+        Ok(())
+    }
+
+    /// Parse `input` as a TOML value, taking it as a plain string when it isn't one
+    pub fn parse_value(input: &str) -> toml::Value {
+        format!("value = {}", input)
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(input.to_string()))
+    }
+
+    /// Write the current values of `keys` to the config file at `path`
+    ///
+    /// Other settings and comments in the file are kept; unset options are removed.
+    pub fn save_keys(&self, path: &Path, keys: &[&str]) -> Result<()> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut document: toml_edit::DocumentMut = content
+            .parse()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let table = toml::Table::try_from(self)?;
+        for key in keys {
+            match table.get(*key) {
+                Some(value) => {
+                    let value: toml_edit::Value = value.to_string().parse()?;
+                    document[*key] = toml_edit::value(value);
+                }
+                None => {
+                    document.remove(key);
+                }
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, document.to_string())?;
+        Ok(())
+    }
+
+    /// `default_output_dir`, with a relative path resolved against the project root
+    pub fn output_dir(&self, project_root: &Path) -> Option<PathBuf> {
+        self.default_output_dir
+            .as_ref()
+            .map(|output_dir| project_root.join(output_dir))
+    }
+
+    /// `ignore_files`, with relative paths resolved against the project root
+    pub fn ignore_files(&self, project_root: &Path) -> Vec<PathBuf> {
+        self.ignore_files
+            .iter()
+            .map(|ignore_file| project_root.join(ignore_file))
+            .collect()
+    }
+
+    /// `cache_path`, with a relative path resolved against the project root
+    pub fn cache_path(&self, project_root: &Path) -> Option<PathBuf> {
+        self.cache_path
+            .as_ref()
+            .map(|cache_path| project_root.join(cache_path))
+    }
+
     /// Format clipboard size for user-facing messages
     pub fn format_clipboard_size(&self) -> String {
         let size = self.max_clipboard_size;
@@ -154,4 +671,151 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_output_dir_resolves_against_project_root() {
+        let project_root = Path::new("/projects/app");
+        let mut settings = Settings::default();
+        assert_eq!(settings.output_dir(project_root), None);
+
+        settings.default_output_dir = Some(PathBuf::from("exports"));
+        assert_eq!(
+            settings.output_dir(project_root),
+            Some(PathBuf::from("/projects/app/exports"))
+        );
+
+        settings.default_output_dir = Some(PathBuf::from("/tmp/gthr"));
+        assert_eq!(
+            settings.output_dir(project_root),
+            Some(PathBuf::from("/tmp/gthr"))
+        );
+    }
+
+    #[test]
+    fn test_commented_default_config() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_path = temp_dir.path().join(".gthr.toml");
+
+        Settings::write_default_config(&config_path, false)?;
+        let content = std::fs::read_to_string(&config_path)?;
+        assert!(content.contains("# Whether to respect .gitignore files by default\nrespect_gitignore = true"));
+
+        // Every serialized setting must be documented
+        let table = toml::Table::try_from(Settings::default())?;
+        for key in table.keys() {
+            assert!(SETTING_DOCS.iter().any(|(doc_key, _)| doc_key == key), "{} is undocumented", key);
+        }
+
+        let loaded = Settings::load_from_file(&config_path)?;
+        assert_eq!(loaded.max_file_size, default_max_file_size());
+
+        assert!(Settings::write_default_config(&config_path, false).is_err());
+        Settings::write_default_config(&config_path, true)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_effective_setting_sources() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let global_path = temp_dir.path().join("global.toml");
+        let project_path = temp_dir.path().join("project.toml");
+        std::fs::write(&global_path, "show_hidden = true\nstrip_comments = true\n")?;
+        std::fs::write(&project_path, "strip_comments = true\nhash_files = true\n")?;
+
+        let settings = Settings::merge_settings(
+            Settings::load_from_file(&global_path)?,
+            Settings::load_from_file(&project_path)?,
+        );
+        let effective = settings.effective_settings_from(&global_path, &project_path, None)?;
+        let source_of = |key: &str| effective.iter().find(|setting| setting.key == key).unwrap().source;
+
+        assert_eq!(source_of("show_hidden"), ConfigSource::Global);
+        assert_eq!(source_of("strip_comments"), ConfigSource::Project);
+        assert_eq!(source_of("hash_files"), ConfigSource::Project);
+        assert_eq!(source_of("max_file_size"), ConfigSource::Default);
+        assert_eq!(source_of("template_path"), ConfigSource::Default);
+
+        let is_default = |key: &str| effective.iter().find(|setting| setting.key == key).unwrap().is_default;
+        assert!(!is_default("show_hidden"));
+        assert!(is_default("max_file_size"));
+        assert!(is_default("template_path"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_and_save_values() -> Result<()> {
+        let mut settings = Settings::default();
+        settings.set_value("max_file_size", Some(Settings::parse_value("2048")))?;
+        assert_eq!(settings.max_file_size, 2048);
+        settings.set_value("template_path", Some(Settings::parse_value("gthr.hbs")))?;
+        assert_eq!(settings.template_path, Some(PathBuf::from("gthr.hbs")));
+        assert_eq!(settings.value("template_path")?, Some(toml::Value::from("gthr.hbs")));
+
+        // Type errors and unknown keys leave the settings untouched
+        assert!(settings.set_value("max_file_size", Some(Settings::parse_value("big"))).is_err());
+        assert!(settings.set_value("theme", Some(Settings::parse_value("dark"))).is_err());
+        assert_eq!(settings.max_file_size, 2048);
+
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join(".gthr.toml");
+        std::fs::write(&path, "# Project limits\nmax_file_size = 10\nshow_hidden = true\n")?;
+        settings.save_keys(&path, &["max_file_size", "template_path"])?;
+        settings.set_value("template_path", None)?;
+        assert_eq!(settings.template_path, None);
+
+        let saved = std::fs::read_to_string(&path)?;
+        assert!(saved.starts_with("# Project limits\nmax_file_size = 2048\nshow_hidden = true\n"));
+        assert!(saved.contains("template_path = \"gthr.hbs\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_profile() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let global_path = temp_dir.path().join("global.toml");
+        let project_path = temp_dir.path().join("project.toml");
+        std::fs::write(&global_path, "[profiles.base]\nshow_hidden = true\nmax_depth = 2\n")?;
+        std::fs::write(
+            &project_path,
+            "max_depth = 4\n\n[profiles.review]\nextends = \"base\"\ninclude_line_numbers = true\n\
+             max_depth = 3\n\n[profiles.loop]\nextends = \"loop\"\n",
+        )?;
+        let settings = Settings::merge_settings(
+            Settings::load_from_file(&global_path)?,
+            Settings::load_from_file(&project_path)?,
+        );
+        assert_eq!(
+            settings.profile_names(),
+            vec![("base", None), ("loop", Some("loop")), ("review", Some("base"))]
+        );
+        assert_eq!(settings.max_depth, Some(4));
+
+        // The profile wins over the profile it extends, which wins over the base settings
+        let mut review = settings.clone();
+        review.apply_profile("review")?;
+        assert!(review.include_line_numbers);
+        assert!(review.show_hidden);
+        assert_eq!(review.max_depth, Some(3));
+        let effective =
+            review.effective_settings_from(&global_path, &project_path, Some("review"))?;
+        let source_of = |key: &str| effective.iter().find(|setting| setting.key == key).unwrap().source;
+        assert_eq!(source_of("show_hidden"), ConfigSource::Profile);
+        assert_eq!(source_of("max_depth"), ConfigSource::Profile);
+        assert_eq!(source_of("max_file_size"), ConfigSource::Default);
+
+        let mut other = settings.clone();
+        let error = other.apply_profile("docs").unwrap_err();
+        assert!(error.to_string().contains("base, loop, review"));
+        assert!(other.apply_profile("loop").unwrap_err().to_string().contains("loop -> loop"));
+
+        // Profiles survive a save
+        let saved_path = temp_dir.path().join("saved.toml");
+        settings.save_to_file(&saved_path)?;
+        assert_eq!(Settings::load_from_file(&saved_path)?.profiles, settings.profiles);
+
+        Ok(())
+    }
 }
\ No newline at end of file
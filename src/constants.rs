@@ -3,3 +3,39 @@ pub const DEFAULT_MAX_CLIPBOARD_SIZE: usize = 2 * 1024 * 1024;
 
 /// Default maximum file size to include (2MB) - can be overridden in config
 pub const DEFAULT_MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Default minimum file size to include (no minimum) - can be overridden in config
+pub const DEFAULT_MIN_FILE_SIZE: u64 = 0;
+
+/// Maximum number of selection changes kept for undo in the interactive mode
+pub const MAX_UNDO_HISTORY: usize = 50;
+
+/// How long transient status bar messages stay visible (in milliseconds)
+pub const STATUS_MESSAGE_DURATION_MS: u64 = 2000;
+
+/// Average number of characters per token used for token estimates
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Maximum number of matching lines collected by the interactive content search
+pub const MAX_CONTENT_MATCHES: usize = 1000;
+
+/// Default multiplier for fuzzy scores whose match lies entirely in the file name
+pub const DEFAULT_FILENAME_MATCH_BOOST: f64 = 1.5;
+
+/// How long `--watch` waits for changes to settle before regenerating (in milliseconds)
+pub const WATCH_DEBOUNCE_MS: u64 = 500;
+
+/// Lines of a file shown in the interactive preview pane
+pub const PREVIEW_MAX_LINES: usize = 200;
+
+/// Bytes read from a file for the interactive preview pane, so large files load quickly
+pub const PREVIEW_MAX_BYTES: u64 = 64 * 1024;
+
+/// Longest gap between two clicks on a row that toggles it as a double-click (in milliseconds)
+pub const DOUBLE_CLICK_MS: u64 = 400;
+
+/// Rows the interactive list scrolls per mouse wheel step
+pub const MOUSE_SCROLL_ROWS: usize = 3;
+
+/// Selection file written by Alt+S in the project root, read back with `--load-selection`
+pub const SELECTION_FILENAME: &str = ".gthr-selection";
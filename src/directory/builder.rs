@@ -0,0 +1,231 @@
+use super::git::GitSelection;
+use super::patterns::{
+    apply_patterns_and_extensions, set_extension_state, set_matching_state,
+};
+use super::state::SelectionState;
+use super::traversal::{DecisionCallback, DirectoryTraverser, TraversalDecision};
+use super::tree::DirectoryTree;
+use crate::cli::Cli;
+use crate::config::settings::Settings;
+use crate::constants::{DEFAULT_MAX_FILE_SIZE, DEFAULT_MIN_FILE_SIZE};
+use crate::output::formatter::format_file_size;
+use crate::verbosity::Verbosity;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Build the directory tree with common logic for all modes
+pub fn build_directory_tree(cli: &Cli, settings: &Settings) -> Result<DirectoryTree> {
+    build_directory_tree_with_gitignore(cli, settings, respect_gitignore(cli, settings))
+}
+
+/// Whether `.gitignore` files are respected: `-g` when given, else the settings
+pub fn respect_gitignore(cli: &Cli, settings: &Settings) -> bool {
+    cli.respect_gitignore.unwrap_or(settings.respect_gitignore)
+}
+
+/// `build_directory_tree` with `.gitignore` handling decided by the caller, for rescans
+/// from the TUI
+pub fn build_directory_tree_with_gitignore(
+    cli: &Cli,
+    settings: &Settings,
+    respect_gitignore: bool,
+) -> Result<DirectoryTree> {
+    let max_file_size = if cli.max_file_size == DEFAULT_MAX_FILE_SIZE {
+        // If using default CLI value
+        settings.max_file_size // Use config file value
+    } else {
+        cli.max_file_size // Use explicitly set CLI value
+    };
+    let min_file_size = if cli.min_file_size == DEFAULT_MIN_FILE_SIZE {
+        settings.min_file_size
+    } else {
+        cli.min_file_size
+    };
+    let show_hidden = cli.show_hidden.unwrap_or(settings.show_hidden);
+    // -I pre-includes everything; -E (and the default) pre-excludes everything
+    let initial_state = if cli.include_all {
+        SelectionState::Included
+    } else {
+        SelectionState::Excluded
+    };
+    let verbosity = cli.verbosity();
+    let skipped_too_small = Arc::new(AtomicUsize::new(0));
+    let traverser = DirectoryTraverser::new(
+        respect_gitignore,
+        show_hidden,
+        max_file_size,
+        initial_state,
+    )
+    .with_min_file_size(min_file_size)
+    .with_follow_symlinks(cli.follow_symlinks)
+    .with_modified_since(cli.modified_since)
+    .with_max_depth(cli.max_depth.or(settings.max_depth))
+    .with_gthrignore(!cli.no_gthrignore)
+    .with_text_cache(!cli.no_cache)
+    .with_cache_path(settings.cache_path(cli.project_root()))
+    .with_global_ignore_file(Settings::get_global_ignore_path())
+    .with_ignore_files(
+        cli.ignore_files
+            .iter()
+            .cloned()
+            .chain(settings.ignore_files(cli.project_root()))
+            .collect(),
+    )
+    .with_decision_callback(
+        (verbosity >= Verbosity::Verbose)
+            .then(|| log_traversal_decisions(Arc::clone(&skipped_too_small))),
+    );
+    let mut tree = match cli.root.as_slice() {
+        [root] => traverser.traverse(root)?,
+        roots => {
+            // Canonicalize so each root is labelled by its directory name
+            let mut seen = HashSet::new();
+            let mut trees = Vec::new();
+            for root in roots {
+                let root = root
+                    .canonicalize()
+                    .with_context(|| format!("Failed to resolve root {}", root.display()))?;
+                if seen.insert(root.clone()) {
+                    trees.push(traverser.traverse(&root)?);
+                }
+            }
+            DirectoryTree::merge_roots(trees)
+        }
+    };
+
+    if let Some(selection) = cli.git_selection() {
+        select_git_files(&mut tree, &selection)?;
+        adjust_selection(&mut tree, cli)?;
+    } else if let Some(path) = &cli.load_selection {
+        load_selection(&mut tree, path)?;
+        adjust_selection(&mut tree, cli)?;
+    } else if !cli.include.is_empty()
+        || !cli.exclude.is_empty()
+        || !cli.ext.is_empty()
+        || !cli.not_ext.is_empty()
+    {
+        // Apply include/exclude patterns if provided
+        apply_patterns_and_extensions(
+            &mut tree,
+            &cli.include,
+            &cli.exclude,
+            &cli.ext,
+            &cli.not_ext,
+        )?;
+    }
+
+    let skipped_too_small = skipped_too_small.load(Ordering::Relaxed);
+    if skipped_too_small > 0 {
+        eprintln!(
+            "Skipped {} {} smaller than {}",
+            skipped_too_small,
+            if skipped_too_small == 1 { "file" } else { "files" },
+            format_file_size(min_file_size)
+        );
+    }
+    if verbosity >= Verbosity::Trace {
+        log_selection(&tree);
+    }
+
+    Ok(tree)
+}
+
+/// `-v`: print entries the traverser leaves out as they are found
+///
+/// Files it keeps are logged at `-vv` by `log_selection` once patterns have been applied.
+/// Files under `--min-file-size` are also counted in `skipped_too_small` for a summary.
+fn log_traversal_decisions(skipped_too_small: Arc<AtomicUsize>) -> DecisionCallback {
+    Box::new(move |path, decision| {
+        match decision {
+            TraversalDecision::Include(_) => return,
+            TraversalDecision::TooSmall(_) => {
+                skipped_too_small.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        match decision.reason() {
+            Some(reason) => eprintln!("{} {} ({})", decision.label(), path.display(), reason),
+            None => eprintln!("{} {}", decision.label(), path.display()),
+        }
+    })
+}
+
+/// `-vv`: print whether each text file ended up selected
+fn log_selection(tree: &DirectoryTree) {
+    for node in &tree.nodes {
+        if node.is_directory || !node.is_text_file {
+            continue;
+        }
+        let path = tree.relative_path(&node.path);
+        if node.state.is_included() {
+            let size = node.size.unwrap_or(0);
+            eprintln!("INCLUDE {} ({})", path.display(), format_file_size(size));
+        } else {
+            eprintln!("EXCLUDE {} (not selected)", path.display());
+        }
+    }
+}
+
+/// Let patterns add to or remove from a git or loaded selection instead of replacing it
+fn adjust_selection(tree: &mut DirectoryTree, cli: &Cli) -> Result<()> {
+    set_matching_state(tree, &cli.include, SelectionState::Included)?;
+    set_extension_state(tree, &cli.ext, SelectionState::Included);
+    set_matching_state(tree, &cli.exclude, SelectionState::Excluded)?;
+    set_extension_state(tree, &cli.not_ext, SelectionState::Excluded);
+    Ok(())
+}
+
+/// Include exactly the files listed in a `--save-selection` file
+///
+/// Blank lines and lines starting with `#` are ignored.
+pub fn load_selection(tree: &mut DirectoryTree, path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read selection {}", path.display()))?;
+    let paths: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+
+    for missing in tree.apply_selection(&paths) {
+        crate::warn!("Skipping {}: no longer exists", missing);
+    }
+    Ok(())
+}
+
+/// Include exactly the files git selects in each root's repository
+fn select_git_files(tree: &mut DirectoryTree, selection: &GitSelection) -> Result<()> {
+    let root = &tree.nodes[tree.root_index];
+    let roots: Vec<_> = if tree.is_multi_root() {
+        root.children.iter().map(|&index| tree.nodes[index].path.clone()).collect()
+    } else {
+        vec![root.path.clone()]
+    };
+
+    tree.set_state(tree.root_index, SelectionState::Excluded);
+    let mut selected_any = false;
+    for root in roots {
+        for path in selection.deleted_files(&root)? {
+            crate::warn!("Skipping {}: deleted", path.display());
+        }
+        let files = selection.files(&root)?;
+        selected_any |= !files.is_empty();
+        for path in files {
+            // Files that were filtered out during traversal are skipped
+            if let Some(&index) = tree.path_to_index.get(&path) {
+                tree.set_state(index, SelectionState::Included);
+            }
+        }
+    }
+    // Not being in a repository is an error; an empty selection only warrants a warning
+    if !selected_any {
+        crate::warn!("{}", selection.empty_warning());
+    }
+
+    Ok(())
+}
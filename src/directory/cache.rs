@@ -0,0 +1,172 @@
+use super::tree::{is_text_by_content, is_text_by_extension};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// File name of the text detection cache kept in each traversed root
+pub const CACHE_FILENAME: &str = ".gthr_cache";
+
+/// Bumped when the cache layout or the detection rules change, discarding older caches
+const CACHE_VERSION: u32 = 1;
+
+/// Whether a file is text, valid while its size and modification time are unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct CacheEntry {
+    modified: Option<SystemTime>,
+    size: u64,
+    is_text: bool,
+}
+
+/// On-disk layout; `version` comes first so it can be checked on its own
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+/// Text detection results of earlier traversals, so unchanged files are not read again
+///
+/// Only files whose extension does not decide it are cached, as only those are sampled.
+pub struct TextDetectionCache {
+    path: PathBuf,
+    entries: HashMap<PathBuf, CacheEntry>,
+    /// Files looked up during this traversal; the others are dropped on save
+    seen: HashSet<PathBuf>,
+    dirty: bool,
+}
+
+impl TextDetectionCache {
+    /// Load the cache at `path`; a missing, corrupt or outdated file starts it empty
+    pub fn load(path: &Path) -> Self {
+        let entries = fs::read(path)
+            .ok()
+            .and_then(|bytes| {
+                let version: u32 = bincode::deserialize(&bytes).ok()?;
+                if version != CACHE_VERSION {
+                    return None;
+                }
+                bincode::deserialize::<CacheFile>(&bytes).ok()
+            })
+            .map(|file| file.entries)
+            .unwrap_or_default();
+
+        Self {
+            path: path.to_path_buf(),
+            entries,
+            seen: HashSet::new(),
+            dirty: false,
+        }
+    }
+
+    /// Whether the file is text, sampling its content only on a missing or stale entry
+    pub fn is_text(&mut self, path: &Path, metadata: &fs::Metadata) -> bool {
+        if is_text_by_extension(path) {
+            return true;
+        }
+
+        self.seen.insert(path.to_path_buf());
+        let modified = metadata.modified().ok();
+        let size = metadata.len();
+        if let Some(entry) = self.entries.get(path)
+            && entry.modified == modified
+            && entry.size == size
+        {
+            return entry.is_text;
+        }
+
+        let is_text = is_text_by_content(path);
+        self.entries.insert(
+            path.to_path_buf(),
+            CacheEntry {
+                modified,
+                size,
+                is_text,
+            },
+        );
+        self.dirty = true;
+        is_text
+    }
+
+    /// Write the cache if it changed, forgetting files under `root` that are gone
+    ///
+    /// Entries of other roots sharing a configured `cache_path` are kept. The cache is
+    /// written to a temporary file renamed over the old one, so it is never half-written.
+    pub fn save(mut self, root: &Path) -> Result<()> {
+        let count = self.entries.len();
+        self.entries
+            .retain(|path, _| self.seen.contains(path) || !path.starts_with(root));
+        if !self.dirty && self.entries.len() == count {
+            return Ok(());
+        }
+
+        let bytes = bincode::serialize(&CacheFile {
+            version: CACHE_VERSION,
+            entries: self.entries,
+        })?;
+        let temp_path = temp_path(&self.path);
+        fs::write(&temp_path, bytes)
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Cache file used for `root`: the configured `cache_path`, else `.gthr_cache` in the root
+pub fn cache_file(root: &Path, cache_path: Option<&Path>) -> PathBuf {
+    cache_path.map_or_else(|| root.join(CACHE_FILENAME), Path::to_path_buf)
+}
+
+/// Where the cache is written before it replaces `path`
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cache_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        let cache_path = root.join(CACHE_FILENAME);
+        let script = root.join("run");
+        let blob = root.join("blob");
+        fs::write(&script, "make all\n")?;
+        fs::write(&blob, [0u8, 1, 2, 3])?;
+
+        let mut cache = TextDetectionCache::load(&cache_path);
+        assert!(cache.is_text(&script, &fs::metadata(&script)?));
+        assert!(!cache.is_text(&blob, &fs::metadata(&blob)?));
+        cache.save(root)?;
+        assert!(!temp_path(&cache_path).exists());
+
+        // A cached result is used as long as the size and modification time match
+        let mut cache = TextDetectionCache::load(&cache_path);
+        assert_eq!(cache.entries.len(), 2);
+        cache.entries.get_mut(&script).unwrap().is_text = false;
+        assert!(!cache.is_text(&script, &fs::metadata(&script)?));
+
+        fs::write(&script, "make all install\n")?;
+        assert!(cache.is_text(&script, &fs::metadata(&script)?));
+
+        // Files not seen again are dropped
+        cache.save(root)?;
+        assert_eq!(TextDetectionCache::load(&cache_path).entries.len(), 1);
+
+        // Caches of another version start empty
+        let mut bytes = fs::read(&cache_path)?;
+        bytes[..4].copy_from_slice(&(CACHE_VERSION + 1).to_le_bytes());
+        fs::write(&cache_path, bytes)?;
+        assert!(TextDetectionCache::load(&cache_path).entries.is_empty());
+
+        Ok(())
+    }
+}
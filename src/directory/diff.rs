@@ -0,0 +1,129 @@
+use super::tree::{DirectoryTree, FileNode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Files that differ between two trees, by path relative to each tree's root
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TreeDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+}
+
+/// Size and content hash of a file, enough to tell whether it changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub size: u64,
+    /// BLAKE3 hash of the contents, or `None` if the file could not be read
+    pub hash: Option<String>,
+}
+
+impl FileFingerprint {
+    /// Fingerprint of a file node, or `None` for directories
+    pub fn of(node: &FileNode) -> Option<Self> {
+        if node.is_directory {
+            return None;
+        }
+
+        let bytes = fs::read(&node.path).ok();
+        Some(Self {
+            size: node
+                .size
+                .or_else(|| bytes.as_ref().map(|bytes| bytes.len() as u64))
+                .unwrap_or(0),
+            hash: bytes.map(|bytes| blake3::hash(&bytes).to_hex().to_string()),
+        })
+    }
+
+    /// Whether the contents differ; files whose hash is unknown only compare by size
+    fn differs_from(&self, other: &Self) -> bool {
+        match (&self.hash, &other.hash) {
+            (Some(hash), Some(other_hash)) => hash != other_hash,
+            _ => self.size != other.size,
+        }
+    }
+}
+
+impl TreeDiff {
+    /// Compare file fingerprints from before and after, keyed by relative path
+    pub fn between(
+        before: &BTreeMap<PathBuf, FileFingerprint>,
+        after: &BTreeMap<PathBuf, FileFingerprint>,
+    ) -> Self {
+        let mut diff = Self::default();
+        for (path, fingerprint) in after {
+            match before.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(previous) if previous.differs_from(fingerprint) => {
+                    diff.modified.push(path.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = before
+            .keys()
+            .filter(|path| !after.contains_key(*path))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl DirectoryTree {
+    /// Fingerprint of every file in the tree, keyed by relative path
+    pub fn fingerprints(&self) -> BTreeMap<PathBuf, FileFingerprint> {
+        self.nodes
+            .iter()
+            .filter_map(|node| {
+                let fingerprint = FileFingerprint::of(node)?;
+                Some((self.relative_path(&node.path), fingerprint))
+            })
+            .collect()
+    }
+
+    /// Files added, removed or modified in `other` compared to this tree
+    pub fn diff(&self, other: &DirectoryTree) -> TreeDiff {
+        TreeDiff::between(&self.fingerprints(), &other.fingerprints())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::state::SelectionState;
+    use crate::directory::traversal::DirectoryTraverser;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_diff() -> Result<()> {
+        let before_dir = TempDir::new()?;
+        let after_dir = TempDir::new()?;
+        for dir in [&before_dir, &after_dir] {
+            fs::write(dir.path().join("main.rs"), "fn main() {}")?;
+        }
+        fs::write(before_dir.path().join("lib.rs"), "pub fn a() {}")?;
+        fs::write(after_dir.path().join("lib.rs"), "pub fn b() {}")?;
+        fs::write(before_dir.path().join("old.rs"), "")?;
+        fs::write(after_dir.path().join("new.rs"), "")?;
+
+        let traverser = DirectoryTraverser::new(true, false, 1024, SelectionState::Excluded);
+        let before = traverser.traverse(before_dir.path())?;
+        let after = traverser.traverse(after_dir.path())?;
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added, vec![PathBuf::from("new.rs")]);
+        assert_eq!(diff.removed, vec![PathBuf::from("old.rs")]);
+        // Same size, different contents
+        assert_eq!(diff.modified, vec![PathBuf::from("lib.rs")]);
+        assert!(before.diff(&before).is_empty());
+
+        Ok(())
+    }
+}
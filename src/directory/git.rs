@@ -0,0 +1,225 @@
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Which files git should pre-select
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitSelection {
+    /// Files that differ from a ref
+    Diff(String),
+    /// Files in the index
+    Staged,
+    /// Files git doesn't track yet, honouring .gitignore
+    Untracked,
+}
+
+impl GitSelection {
+    /// Files under `root` in this selection, as paths joined onto `root`
+    ///
+    /// Deleted files are left out and renamed files are reported under their new path.
+    /// `root` may be a subdirectory of the repository.
+    pub fn files(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        ensure_repository(root)?;
+
+        let stdout = match self {
+            GitSelection::Diff(_) | GitSelection::Staged => self.diff(root, "--diff-filter=d")?,
+            GitSelection::Untracked => {
+                git(root, &["ls-files", "--others", "--exclude-standard", "-z"])?
+            }
+        };
+
+        Ok(parse_paths(root, &stdout))
+    }
+
+    /// Files under `root` deleted in this selection, which no longer exist to be included
+    pub fn deleted_files(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        ensure_repository(root)?;
+
+        match self {
+            GitSelection::Diff(_) | GitSelection::Staged => {
+                Ok(parse_paths(root, &self.diff(root, "--diff-filter=D")?))
+            }
+            GitSelection::Untracked => Ok(Vec::new()),
+        }
+    }
+
+    /// Warning shown when the selection has no files, which leaves the output empty
+    pub fn empty_warning(&self) -> String {
+        match self {
+            GitSelection::Diff(base_ref) => format!("No files changed since {}", base_ref),
+            GitSelection::Staged => "No files staged in the git index".to_string(),
+            GitSelection::Untracked => "No untracked files".to_string(),
+        }
+    }
+
+    /// `git diff --name-only` restricted to `root`, covering staged and unstaged changes
+    fn diff(&self, root: &Path, diff_filter: &str) -> Result<Vec<u8>> {
+        let mut args = vec!["diff", "--name-only", "--relative", diff_filter, "-z"];
+        match self {
+            GitSelection::Diff(base_ref) => args.push(base_ref),
+            GitSelection::Staged => args.push("--cached"),
+            GitSelection::Untracked => {}
+        }
+        args.push("--");
+        git(root, &args)
+    }
+}
+
+/// Fail with a clear message when `root` is not inside a git work tree
+fn ensure_repository(root: &Path) -> Result<()> {
+    // Outside a repository `git diff` falls back to `--no-index` and prints its usage
+    if git(root, &["rev-parse", "--is-inside-work-tree"]).is_err() {
+        bail!("{} is not inside a git repository", root.display());
+    }
+    Ok(())
+}
+
+/// Split NUL-separated paths printed by git relative to `root`
+fn parse_paths(root: &Path, stdout: &[u8]) -> Vec<PathBuf> {
+    stdout
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| root.join(String::from_utf8_lossy(path).as_ref()))
+        .collect()
+}
+
+/// Run git in `root` and return its stdout, failing with git's error message
+fn git(root: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .context("Failed to run git; is it installed?")?;
+
+    if !output.status.success() {
+        bail!(
+            "git {} failed in {}: {}",
+            args.join(" "),
+            root.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn run_git(root: &Path, args: &[&str]) -> Result<()> {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["-c", "user.name=gthr", "-c", "user.email=gthr@example.com"])
+            .args(args)
+            .output()?
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+        Ok(())
+    }
+
+    fn init_repo(root_path: &Path) -> Result<()> {
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("old.rs"), "pub fn old() {}")?;
+        fs::write(root_path.join("gone.rs"), "pub fn gone() {}")?;
+        fs::write(root_path.join("same.rs"), "pub fn same() {}")?;
+        fs::write(root_path.join(".gitignore"), "*.log\n")?;
+        run_git(root_path, &["init", "-q"])?;
+        run_git(root_path, &["add", "."])?;
+        run_git(root_path, &["commit", "-q", "-m", "initial"])?;
+        Ok(())
+    }
+
+    fn sorted_files(selection: GitSelection, root_path: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = selection.files(root_path)?;
+        files.sort();
+        Ok(files)
+    }
+
+    #[test]
+    fn test_diff_selection() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        init_repo(root_path)?;
+
+        fs::write(root_path.join("main.rs"), "fn main() { run(); }")?;
+        run_git(root_path, &["mv", "old.rs", "new.rs"])?;
+        run_git(root_path, &["rm", "-q", "gone.rs"])?;
+
+        let selection = GitSelection::Diff("HEAD".to_string());
+        assert_eq!(
+            sorted_files(selection.clone(), root_path)?,
+            vec![root_path.join("main.rs"), root_path.join("new.rs")]
+        );
+        assert_eq!(
+            selection.deleted_files(root_path)?,
+            vec![root_path.join("gone.rs")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_selection_in_subdirectory() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::create_dir(root_path.join("src"))?;
+        fs::write(root_path.join("src").join("lib.rs"), "pub fn lib() {}")?;
+        init_repo(root_path)?;
+        run_git(root_path, &["tag", "v1.0.0"])?;
+
+        fs::write(root_path.join("main.rs"), "fn main() { run(); }")?;
+        fs::write(
+            root_path.join("src").join("lib.rs"),
+            "pub fn lib() { run(); }",
+        )?;
+
+        // Only changes under the subdirectory root are reported, relative to it
+        let src = root_path.join("src");
+        assert_eq!(
+            GitSelection::Diff("v1.0.0".to_string()).files(&src)?,
+            vec![src.join("lib.rs")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_staged_and_untracked_selection() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        init_repo(root_path)?;
+
+        fs::create_dir(root_path.join("src"))?;
+        fs::write(root_path.join("src").join("new.rs"), "pub fn new() {}")?;
+        fs::write(root_path.join("debug.log"), "ignored")?;
+        fs::write(root_path.join("main.rs"), "fn main() { run(); }")?;
+        fs::write(root_path.join("same.rs"), "pub fn changed() {}")?;
+        run_git(root_path, &["add", "main.rs"])?;
+
+        // Unstaged changes to same.rs are neither staged nor untracked
+        assert_eq!(
+            sorted_files(GitSelection::Staged, root_path)?,
+            vec![root_path.join("main.rs")]
+        );
+        assert_eq!(
+            sorted_files(GitSelection::Untracked, root_path)?,
+            vec![root_path.join("src").join("new.rs")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_selection_outside_repository() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        let error = GitSelection::Staged.files(temp_dir.path()).unwrap_err();
+        assert!(error.to_string().contains("is not inside a git repository"));
+
+        Ok(())
+    }
+}
@@ -1,4 +1,9 @@
+pub mod builder;
+pub mod cache;
+pub mod diff;
+pub mod git;
+pub mod patterns;
 pub mod tree;
 pub mod traversal;
 pub mod state;
-
+pub mod stats;
@@ -0,0 +1,320 @@
+use super::state::SelectionState;
+use super::tree::DirectoryTree;
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Set the selection state of every node from include/exclude glob patterns
+pub fn apply_patterns(
+    tree: &mut DirectoryTree,
+    include: &[String],
+    exclude: &[String],
+) -> Result<()> {
+    apply_patterns_and_extensions(tree, include, exclude, &[], &[])
+}
+
+/// Like `apply_patterns`, also including or excluding files by extension
+///
+/// Include patterns and extensions are combined, so a file matching either is included.
+/// Within each pattern list a leading `!` negates a pattern and, as in gitignore, the
+/// last pattern matching a path decides: `-i "src/**" -i "!src/gen/**" -i "src/gen/api.rs"`
+/// includes `src` except `src/gen`, but keeps `src/gen/api.rs`. A list of only negated
+/// include patterns starts from everything included.
+pub fn apply_patterns_and_extensions(
+    tree: &mut DirectoryTree,
+    include: &[String],
+    exclude: &[String],
+    include_extensions: &[String],
+    exclude_extensions: &[String],
+) -> Result<()> {
+    // If no include patterns are specified, include everything by default
+    let include_all =
+        !include.iter().any(|pattern| !pattern.starts_with('!')) && include_extensions.is_empty();
+    let included = last_matches(tree, include)?;
+    let excluded = last_matches(tree, exclude)?;
+
+    // Patterns decide files (and empty directories); other directories follow their contents
+    for i in 0..tree.nodes.len() {
+        if !tree.nodes[i].children.is_empty() {
+            continue;
+        }
+
+        let mut should_include = match included[i] {
+            Some(included) => included,
+            None => include_all || node_has_extension(tree, i, include_extensions),
+        };
+
+        // Exclude patterns override includes
+        if excluded[i] == Some(true) || node_has_extension(tree, i, exclude_extensions) {
+            should_include = false;
+        }
+
+        tree.nodes[i].state = if should_include {
+            SelectionState::Included
+        } else {
+            SelectionState::Excluded
+        };
+    }
+    tree.recompute_directory_states();
+
+    Ok(())
+}
+
+/// Set nodes matching `patterns` to `state`, leaving the others untouched
+///
+/// Nodes whose last matching pattern is negated with `!` are left untouched too.
+pub fn set_matching_state(
+    tree: &mut DirectoryTree,
+    patterns: &[String],
+    state: SelectionState,
+) -> Result<()> {
+    let matched = last_matches(tree, patterns)?;
+    for (i, matched) in matched.into_iter().enumerate() {
+        if matched == Some(true) && tree.nodes[i].children.is_empty() {
+            tree.nodes[i].state = state;
+        }
+    }
+    tree.recompute_directory_states();
+    Ok(())
+}
+
+/// For each node, whether the last pattern matching it was positive (`Some(true)`) or
+/// negated with a leading `!` (`Some(false)`); `None` if no pattern matched
+fn last_matches(tree: &DirectoryTree, patterns: &[String]) -> Result<Vec<Option<bool>>> {
+    let mut result = vec![None; tree.nodes.len()];
+    for pattern in patterns {
+        let (positive, glob) = match pattern.strip_prefix('!') {
+            Some(glob) => (false, glob),
+            None => (true, pattern.as_str()),
+        };
+        let matched = matching_nodes(tree, &build_glob_set(&[glob.to_string()])?);
+        for (i, matched) in matched.into_iter().enumerate() {
+            if matched {
+                result[i] = Some(positive);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Compile glob patterns in which `*` and `?` stay within one path component and `**`
+/// spans any number of directories
+pub fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid pattern '{}'", pattern))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+/// Set files with any of `extensions` to `state`, leaving the others untouched
+pub fn set_extension_state(tree: &mut DirectoryTree, extensions: &[String], state: SelectionState) {
+    for i in 0..tree.nodes.len() {
+        if node_has_extension(tree, i, extensions) {
+            tree.set_state(i, state);
+        }
+    }
+}
+
+/// Whether a file's extension is one of `extensions`, ignoring case and a leading `.`
+///
+/// Extensionless files such as `Makefile` match their whole name.
+pub fn file_has_extension(name: &str, extensions: &[String]) -> bool {
+    let extension = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => extension,
+        _ => name,
+    };
+    extensions.iter().any(|wanted| {
+        wanted
+            .trim_start_matches('.')
+            .eq_ignore_ascii_case(extension)
+    })
+}
+
+fn node_has_extension(tree: &DirectoryTree, index: usize, extensions: &[String]) -> bool {
+    !extensions.is_empty()
+        && tree
+            .nodes
+            .get(index)
+            .is_some_and(|node| !node.is_directory && file_has_extension(&node.name, extensions))
+}
+
+/// Whether each node, or a directory containing it, matches `glob_set`
+fn matching_nodes(tree: &DirectoryTree, glob_set: &GlobSet) -> Vec<bool> {
+    let mut matched = vec![false; tree.nodes.len()];
+    if glob_set.is_empty() {
+        return matched;
+    }
+
+    // Parents are always added before their children
+    for (i, node) in tree.nodes.iter().enumerate() {
+        let parent_matched = node.parent.is_some_and(|parent| matched[parent]);
+        matched[i] = parent_matched || (i != tree.root_index && node_matches(tree, i, glob_set));
+    }
+    matched
+}
+
+fn node_matches(tree: &DirectoryTree, index: usize, glob_set: &GlobSet) -> bool {
+    let node = &tree.nodes[index];
+
+    // Use relative path from the root for pattern matching
+    let relative_path = tree.relative_path(&node.path);
+    // In multi-root trees, also match relative to the node's own root so that
+    // `src/**` selects `src` under every root
+    if tree.is_multi_root() {
+        let root_relative_path: std::path::PathBuf = relative_path.components().skip(1).collect();
+        if !root_relative_path.as_os_str().is_empty() && glob_set.is_match(&root_relative_path) {
+            return true;
+        }
+    }
+
+    // Patterns without a `/`, like `*.rs`, also match the name at any depth
+    glob_set.is_match(&relative_path) || glob_set.is_match(&node.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_patterns() -> Result<()> {
+        let cases = [
+            ("**/*.rs", "main.rs", true),
+            ("**/*.rs", "src/ui/app.rs", true),
+            ("**/*.rs", "src/main.rson", false),
+            ("src/**/*.rs", "src/main.rs", true),
+            ("src/**/*.rs", "src/ui/app.rs", true),
+            ("src/**/*.rs", "tests/main.rs", false),
+            ("src/*", "src/main.rs", true),
+            ("src/*", "src/ui/app.rs", false),
+            ("*.{yml,yaml}", "ci.yml", true),
+            ("*.{yml,yaml}", "ci.yaml", true),
+            ("*.{yml,yaml}", "ci.json", false),
+            ("file?.txt", "file1.txt", true),
+            ("file?.txt", "file10.txt", false),
+            ("src/?.rs", "src/a.rs", true),
+            ("?.rs", "src/a.rs", false),
+            ("[ab]?.rs", "b1.rs", true),
+            ("[ab]?.rs", "c1.rs", false),
+            ("*.rs", "foo.rson", false),
+            ("*.rs", "src/main.rs", false),
+        ];
+
+        for (pattern, path, expected) in cases {
+            let glob_set = build_glob_set(&[pattern.to_string()])?;
+            assert_eq!(
+                glob_set.is_match(path),
+                expected,
+                "{} against {}",
+                pattern,
+                path
+            );
+        }
+
+        assert!(build_glob_set(&["src/[".to_string()]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patterns_apply_to_directory_contents() -> Result<()> {
+        let root = std::path::PathBuf::from("root");
+        let mut tree = DirectoryTree::new(root.clone());
+        let target = root.join("target");
+        let debug = target.join("debug");
+        tree.add_node(target.clone(), true, &root);
+        tree.add_node(debug.clone(), true, &target);
+        tree.add_node(debug.join("app.rs"), false, &debug);
+        tree.add_node(root.join("main.rs"), false, &root);
+
+        apply_patterns(&mut tree, &[], &["target/*".to_string()])?;
+        let state_of = |path: &std::path::Path| tree.nodes[tree.path_to_index[path]].state;
+        // Files inside an excluded directory are excluded too
+        assert_eq!(state_of(&debug.join("app.rs")), SelectionState::Excluded);
+        assert_eq!(state_of(&root.join("main.rs")), SelectionState::Included);
+
+        // Patterns without a separator match names at any depth
+        apply_patterns(&mut tree, &["*.rs".to_string()], &[])?;
+        assert_eq!(tree.get_all_included_files().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_negated_patterns_last_match_wins() -> Result<()> {
+        let root = std::path::PathBuf::from("root");
+        let mut tree = DirectoryTree::new(root.clone());
+        let src = root.join("src");
+        let generated = src.join("generated");
+        tree.add_node(src.clone(), true, &root);
+        tree.add_node(src.join("main.rs"), false, &src);
+        tree.add_node(generated.clone(), true, &src);
+        tree.add_node(generated.join("api.rs"), false, &generated);
+        tree.add_node(generated.join("models.rs"), false, &generated);
+        tree.add_node(root.join("README.md"), false, &root);
+
+        let included = |tree: &DirectoryTree| -> Vec<String> {
+            tree.get_all_included_files()
+                .iter()
+                .map(|node| tree.relative_path(&node.path).display().to_string())
+                .collect()
+        };
+        let patterns = |patterns: &[&str]| -> Vec<String> {
+            patterns.iter().map(|pattern| pattern.to_string()).collect()
+        };
+
+        // Included, then negated, then re-included
+        apply_patterns(
+            &mut tree,
+            &patterns(&["src/**", "!src/generated/**", "src/generated/api.rs"]),
+            &[],
+        )?;
+        assert_eq!(included(&tree), vec!["src/main.rs", "src/generated/api.rs"]);
+
+        // A later pattern only wins where it matches
+        apply_patterns(
+            &mut tree,
+            &patterns(&["src/generated/api.rs", "!src/**"]),
+            &[],
+        )?;
+        assert!(included(&tree).is_empty());
+
+        // Only negated includes start from everything included
+        apply_patterns(&mut tree, &patterns(&["!src/**"]), &[])?;
+        assert_eq!(included(&tree), vec!["README.md"]);
+
+        // Negated excludes carve exceptions out of earlier excludes
+        apply_patterns(
+            &mut tree,
+            &[],
+            &patterns(&["src/generated/**", "!src/generated/api.rs"]),
+        )?;
+        assert_eq!(
+            included(&tree),
+            vec!["src/main.rs", "src/generated/api.rs", "README.md"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_has_extension() {
+        let extensions = vec![
+            "rs".to_string(),
+            ".TOML".to_string(),
+            "makefile".to_string(),
+        ];
+
+        assert!(file_has_extension("main.rs", &extensions));
+        assert!(file_has_extension("Cargo.toml", &extensions));
+        assert!(file_has_extension("MAIN.RS", &extensions));
+        assert!(file_has_extension("Makefile", &extensions));
+        assert!(!file_has_extension("main.rs.bak", &extensions));
+        assert!(!file_has_extension("README.md", &extensions));
+        // Dotfiles have no extension; their name is compared as a whole
+        assert!(!file_has_extension(".rs", &["md".to_string()]));
+    }
+}
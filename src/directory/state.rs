@@ -1,16 +1,14 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SelectionState {
     Included,
+    #[default]
     Excluded,
     Partial, // Some children included, some excluded
 }
 
-impl Default for SelectionState {
-    fn default() -> Self {
-        SelectionState::Excluded
-    }
-}
-
 impl SelectionState {
     pub fn is_included(&self) -> bool {
         matches!(self, SelectionState::Included | SelectionState::Partial)
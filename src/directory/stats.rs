@@ -0,0 +1,276 @@
+use super::tree::{DirectoryTree, FileNode};
+use crate::output::formatter::estimate_tokens;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::SystemTime;
+
+/// Number of largest files kept in `TreeStats`
+const LARGEST_FILES_COUNT: usize = 10;
+
+/// Aggregate statistics of the included files
+#[derive(Debug, Clone, Default)]
+pub struct TreeStats {
+    pub total_files: usize,
+    pub total_size: u64,
+    /// Newline count of every file, plus one for files not ending in a newline
+    pub total_lines: usize,
+    /// File count and bytes per language; files of unknown language count as `other`
+    pub languages: HashMap<&'static str, (usize, u64)>,
+    /// Relative paths and sizes of the largest files, largest first
+    pub largest_files: Vec<(PathBuf, u64)>,
+    pub estimated_tokens: usize,
+}
+
+impl DirectoryTree {
+    /// Statistics of the included files; line counts read the files in parallel
+    pub fn stats(&self) -> TreeStats {
+        let included_files = self.get_all_included_files();
+        let total_size: u64 = included_files.iter().filter_map(|node| node.size).sum();
+
+        let mut languages: HashMap<&'static str, (usize, u64)> = HashMap::new();
+        for node in &included_files {
+            let language = match node.language() {
+                "" => "other",
+                language => language,
+            };
+            let entry = languages.entry(language).or_default();
+            entry.0 += 1;
+            entry.1 += node.size.unwrap_or(0);
+        }
+
+        let mut largest_files: Vec<(PathBuf, u64)> = included_files
+            .iter()
+            .map(|node| (self.relative_path(&node.path), node.size.unwrap_or(0)))
+            .collect();
+        largest_files.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        largest_files.truncate(LARGEST_FILES_COUNT);
+
+        TreeStats {
+            total_files: included_files.len(),
+            total_size,
+            total_lines: included_files
+                .par_iter()
+                .map(|node| count_lines(&node.path))
+                .sum(),
+            languages,
+            largest_files,
+            estimated_tokens: estimate_tokens(total_size),
+        }
+    }
+}
+
+/// Token and line estimate of a file, valid while its size and modification time match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileEstimate {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub tokens: usize,
+    /// `None` until a `LineCounter` read the file
+    pub lines: Option<usize>,
+}
+
+impl FileEstimate {
+    /// Whether both estimates were made for the same version of a file
+    pub fn same_file(&self, other: &FileEstimate) -> bool {
+        self.size == other.size && self.modified == other.modified
+    }
+}
+
+impl FileNode {
+    /// The cached estimate of this file, started over when its size or modification time changed
+    pub fn estimate(&mut self) -> FileEstimate {
+        let size = self.size.unwrap_or(0);
+        match self.estimate {
+            Some(estimate) if estimate.size == size && estimate.modified == self.modified => {
+                estimate
+            }
+            _ => {
+                let estimate = FileEstimate {
+                    size,
+                    modified: self.modified,
+                    tokens: estimate_tokens(size),
+                    lines: None,
+                };
+                self.estimate = Some(estimate);
+                estimate
+            }
+        }
+    }
+}
+
+/// Counts lines of files on a background thread, so files are never read while drawing
+pub struct LineCounter {
+    sender: Sender<(usize, PathBuf, FileEstimate)>,
+    receiver: Receiver<(usize, FileEstimate)>,
+    cancelled: Arc<AtomicBool>,
+    /// Files requested but not drained yet
+    pending: usize,
+}
+
+impl LineCounter {
+    pub fn spawn() -> Self {
+        let (sender, requests) = mpsc::channel::<(usize, PathBuf, FileEstimate)>();
+        let (results, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = Arc::clone(&cancelled);
+
+        // Ends once the counter is dropped and the request channel closes
+        thread::spawn(move || {
+            for (index, path, mut estimate) in requests {
+                if thread_cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                estimate.lines = Some(count_lines(&path));
+                if results.send((index, estimate)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            sender,
+            receiver,
+            cancelled,
+            pending: 0,
+        }
+    }
+
+    /// Count the lines of the file at `index`, sent back with `estimate`
+    pub fn request(&mut self, index: usize, path: PathBuf, estimate: FileEstimate) {
+        if self.sender.send((index, path, estimate)).is_ok() {
+            self.pending += 1;
+        }
+    }
+
+    /// Estimates counted since the last call, by node index
+    pub fn drain(&mut self) -> Vec<(usize, FileEstimate)> {
+        let counted: Vec<_> = self.receiver.try_iter().collect();
+        self.pending -= counted.len();
+        counted
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.pending == 0
+    }
+}
+
+impl Drop for LineCounter {
+    fn drop(&mut self) {
+        // Skip the queued files once the tree they belong to is replaced
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Count lines by scanning for `\n` in fixed-size chunks; unreadable files count as 0
+fn count_lines(path: &Path) -> usize {
+    let Ok(mut file) = File::open(path) else {
+        return 0;
+    };
+
+    let mut buffer = [0u8; 64 * 1024];
+    let mut lines = 0;
+    let mut last_byte = None;
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => {
+                lines += memchr::memchr_iter(b'\n', &buffer[..read]).count();
+                last_byte = Some(buffer[read - 1]);
+            }
+        }
+    }
+
+    match last_byte {
+        Some(byte) if byte != b'\n' => lines + 1,
+        _ => lines,
+    }
+}
+
+/// Lines in already loaded bytes, counted like `TreeStats::total_lines`
+pub fn count_lines_in(bytes: &[u8]) -> usize {
+    let lines = memchr::memchr_iter(b'\n', bytes).count();
+    match bytes.last() {
+        Some(&byte) if byte != b'\n' => lines + 1,
+        _ => lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::state::SelectionState;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_stats() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        let files = [
+            ("main.rs", "fn main() {\n    run();\n}\n"),
+            ("lib.rs", "pub fn run() {}"),
+            ("notes.txt", "a\nb\n\nc"),
+            ("LICENSE", "MIT"),
+            ("skipped.rs", "fn skipped() {}\n"),
+        ];
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        for (name, content) in files {
+            let path = root_path.join(name);
+            fs::write(&path, content)?;
+            let index = tree.add_node(path, false, root_path).unwrap();
+            tree.get_node_mut(index).unwrap().size = Some(content.len() as u64);
+        }
+        tree.set_state(tree.root_index, SelectionState::Included);
+        tree.set_state(tree.path_to_index[&root_path.join("skipped.rs")], SelectionState::Excluded);
+
+        let stats = tree.stats();
+
+        assert_eq!(stats.total_files, 4);
+        assert_eq!(stats.total_size, 25 + 15 + 6 + 3);
+        assert_eq!(stats.total_lines, 3 + 1 + 4 + 1);
+        assert_eq!(stats.estimated_tokens, 13);
+        assert_eq!(stats.languages["rust"], (2, 40));
+        assert_eq!(stats.languages["text"], (1, 6));
+        assert_eq!(stats.languages["other"], (1, 3));
+        assert_eq!(stats.largest_files[0], (PathBuf::from("main.rs"), 25));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_counter() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("main.rs");
+        fs::write(&path, "fn main() {\n}\n")?;
+        let mut node = FileNode::new(path.clone(), false, None);
+        node.size = Some(14);
+
+        let mut counter = LineCounter::spawn();
+        counter.request(0, path, node.estimate());
+        let counted = loop {
+            if let Some((_, estimate)) = counter.drain().pop() {
+                break estimate;
+            }
+            thread::yield_now();
+        };
+        assert!(counter.is_idle());
+        assert_eq!((counted.tokens, counted.lines), (4, Some(2)));
+        node.estimate = Some(counted);
+
+        // An unchanged file keeps its count, a changed one is counted again
+        assert_eq!(node.estimate(), counted);
+        node.size = Some(13);
+        assert_eq!((node.estimate().tokens, node.estimate().lines), (4, None));
+
+        Ok(())
+    }
+}
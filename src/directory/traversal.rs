@@ -1,6 +1,11 @@
-use std::path::Path;
-use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use anyhow::{Context, Result, bail};
 use ignore::WalkBuilder;
+use ignore::gitignore::GitignoreBuilder;
+use super::cache::{TextDetectionCache, cache_file, temp_path};
 use super::tree::DirectoryTree;
 use super::state::SelectionState;
 
@@ -8,16 +13,189 @@ pub struct DirectoryTraverser {
     respect_gitignore: bool,
     show_hidden: bool,
     max_file_size: u64,
-    include_all: bool,
+    min_file_size: u64,
+    initial_state: SelectionState,
+    follow_symlinks: bool,
+    max_depth: Option<usize>,
+    use_gthrignore: bool,
+    global_ignore_file: Option<PathBuf>,
+    ignore_files: Vec<PathBuf>,
+    modified_since: Option<SystemTime>,
+    text_cache: bool,
+    cache_path: Option<PathBuf>,
+    decision_callback: Option<DecisionCallback>,
 }
 
+/// Called with every entry the traverser includes or leaves out, relative to its root
+pub type DecisionCallback = Box<dyn Fn(&Path, TraversalDecision) + Send + Sync>;
+
+/// What the traverser did with an entry, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraversalDecision {
+    /// Added to the tree, with the file's size
+    Include(Option<u64>),
+    /// Added to the tree, but never part of the output because it is not text
+    Binary(u64),
+    /// Larger than the maximum file size
+    TooLarge(u64),
+    /// Smaller than the minimum file size
+    TooSmall(u64),
+    /// Last modified before `--modified-since`
+    NotModified,
+    /// Already visited through another symlink
+    SymlinkCycle,
+    /// Hidden file or directory
+    Hidden,
+    /// Matched by a `.gitignore`, `.gthrignore` or the global ignore file
+    Ignored,
+    /// Could not be read, with the error
+    Unreadable(String),
+}
+
+impl TraversalDecision {
+    /// `INCLUDE`, `SKIP` or `IGNORE`
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Include(_) => "INCLUDE",
+            Self::Binary(_)
+            | Self::TooLarge(_)
+            | Self::TooSmall(_)
+            | Self::NotModified
+            | Self::SymlinkCycle
+            | Self::Unreadable(_) => "SKIP",
+            Self::Hidden | Self::Ignored => "IGNORE",
+        }
+    }
+
+    /// Why the entry got its label, such as `binary, 4.1 MB`
+    pub fn reason(&self) -> Option<String> {
+        use crate::output::formatter::format_file_size;
+
+        match self {
+            Self::Include(size) => size.map(format_file_size),
+            Self::Binary(size) => Some(format!("binary, {}", format_file_size(*size))),
+            Self::TooLarge(size) => Some(format!("too large, {}", format_file_size(*size))),
+            Self::TooSmall(size) => Some(format!("too small, {}", format_file_size(*size))),
+            Self::NotModified => Some("not modified since".to_string()),
+            Self::SymlinkCycle => Some("symlink cycle".to_string()),
+            Self::Hidden => Some("hidden".to_string()),
+            Self::Ignored => Some("ignore file".to_string()),
+            Self::Unreadable(error) => Some(format!("unreadable, {}", error)),
+        }
+    }
+}
+
+/// Path a walk error is about, if it names one, and the underlying error message
+fn describe_walk_error(error: &ignore::Error) -> (Option<&Path>, String) {
+    match error {
+        ignore::Error::WithPath { path, err } => (Some(path), err.to_string()),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            describe_walk_error(err)
+        }
+        ignore::Error::Loop { child, .. } => (Some(child), error.to_string()),
+        _ => (None, error.to_string()),
+    }
+}
+
+/// Per-directory ignore file using gitignore syntax, like ripgrep's `.rgignore`
+pub const GTHRIGNORE_FILENAME: &str = ".gthrignore";
+
 impl DirectoryTraverser {
-    pub fn new(respect_gitignore: bool, show_hidden: bool, max_file_size: u64, include_all: bool) -> Self {
+    pub fn new(
+        respect_gitignore: bool,
+        show_hidden: bool,
+        max_file_size: u64,
+        initial_state: SelectionState,
+    ) -> Self {
         Self {
             respect_gitignore,
             show_hidden,
             max_file_size,
-            include_all,
+            min_file_size: 0,
+            initial_state,
+            follow_symlinks: false,
+            max_depth: None,
+            use_gthrignore: true,
+            global_ignore_file: None,
+            ignore_files: Vec::new(),
+            modified_since: None,
+            text_cache: false,
+            cache_path: None,
+            decision_callback: None,
+        }
+    }
+
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Skip files smaller than `min_file_size` bytes; files of exactly that size are kept
+    pub fn with_min_file_size(mut self, min_file_size: u64) -> Self {
+        self.min_file_size = min_file_size;
+        self
+    }
+
+    /// Limit traversal depth; 0 keeps only the root's direct children.
+    ///
+    /// Directories at the cutoff stay in the tree as empty leaves.
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Apply `.gthrignore` files (and the global ignore file) found while traversing
+    pub fn with_gthrignore(mut self, use_gthrignore: bool) -> Self {
+        self.use_gthrignore = use_gthrignore;
+        self
+    }
+
+    /// Ignore file applied to every project, with lower priority than `.gthrignore`
+    pub fn with_global_ignore_file(mut self, global_ignore_file: Option<PathBuf>) -> Self {
+        self.global_ignore_file = global_ignore_file;
+        self
+    }
+
+    /// Ignore files in gitignore syntax whose matches never enter the tree
+    ///
+    /// Unlike `.gitignore` rules they cannot be overridden by other ignore files, and
+    /// patterns are anchored at the traversed root.
+    pub fn with_ignore_files(mut self, ignore_files: Vec<PathBuf>) -> Self {
+        self.ignore_files = ignore_files;
+        self
+    }
+
+    /// Skip files last modified before `modified_since`
+    pub fn with_modified_since(mut self, modified_since: Option<SystemTime>) -> Self {
+        self.modified_since = modified_since;
+        self
+    }
+
+    /// Remember which files without a known text extension are text, so later traversals
+    /// only read the files that changed
+    pub fn with_text_cache(mut self, text_cache: bool) -> Self {
+        self.text_cache = text_cache;
+        self
+    }
+
+    /// File the text cache is kept in instead of `.gthr_cache` in each root
+    pub fn with_cache_path(mut self, cache_path: Option<PathBuf>) -> Self {
+        self.cache_path = cache_path;
+        self
+    }
+
+    /// Report every include/skip/ignore decision, e.g. for `--verbose`
+    ///
+    /// Entries pruned by ignore files are only found with an extra walk, which
+    /// runs only while a callback is set.
+    pub fn with_decision_callback(mut self, callback: Option<DecisionCallback>) -> Self {
+        self.decision_callback = callback;
+        self
+    }
+
+    fn report(&self, root_path: &Path, path: &Path, decision: TraversalDecision) {
+        if let Some(callback) = &self.decision_callback {
+            callback(path.strip_prefix(root_path).unwrap_or(path), decision);
         }
     }
 
@@ -25,14 +203,12 @@ impl DirectoryTraverser {
         let mut tree = DirectoryTree::new(root_path.to_path_buf());
 
         // Set initial state for root
-        let initial_state = if self.include_all {
-            SelectionState::Included
-        } else {
-            SelectionState::Excluded
-        };
+        let initial_state = self.initial_state;
         tree.set_state(tree.root_index, initial_state);
 
         let mut builder = WalkBuilder::new(root_path);
+        // Entries the walker yielded, used to find the ones it pruned
+        let mut seen = HashSet::new();
 
         // Configure the walker based on our settings
         if !self.respect_gitignore {
@@ -43,6 +219,47 @@ impl DirectoryTraverser {
 
         // Configure hidden files visibility
         builder.hidden(!self.show_hidden);
+        builder.follow_links(self.follow_symlinks);
+        // The walker counts the root itself as depth 0
+        builder.max_depth(self.max_depth.map(|depth| depth + 1));
+
+        if self.use_gthrignore {
+            builder.add_custom_ignore_filename(GTHRIGNORE_FILENAME);
+            if let Some(global_ignore_file) = &self.global_ignore_file
+                && global_ignore_file.is_file()
+                && let Some(e) = builder.add_ignore(global_ignore_file)
+            {
+                crate::warn!("Failed to read {}: {}", global_ignore_file.display(), e);
+            }
+        }
+
+        if !self.ignore_files.is_empty() {
+            let mut ignore_builder = GitignoreBuilder::new(root_path);
+            for ignore_file in &self.ignore_files {
+                if !ignore_file.is_file() {
+                    bail!("Ignore file {} does not exist", ignore_file.display());
+                }
+                if let Some(e) = ignore_builder.add(ignore_file) {
+                    return Err(e).with_context(|| {
+                        format!("Failed to read ignore file {}", ignore_file.display())
+                    });
+                }
+            }
+            let ignore = ignore_builder.build()?;
+            builder.filter_entry(move |entry| {
+                let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+                entry.depth() == 0 || !ignore.matched(entry.path(), is_dir).is_ignore()
+            });
+        }
+
+        // Identities of visited entries, used to break symlink cycles
+        let mut visited = HashSet::new();
+        if self.follow_symlinks && let Some(id) = file_identity(root_path) {
+            visited.insert(id);
+        }
+
+        let cache_path = cache_file(root_path, self.cache_path.as_deref());
+        let mut cache = self.text_cache.then(|| TextDetectionCache::load(&cache_path));
 
         // Build the walker and iterate
         let walker = builder.build();
@@ -50,7 +267,13 @@ impl DirectoryTraverser {
         for result in walker {
             let entry = match result {
                 Ok(entry) => entry,
-                Err(_) => continue, // Skip entries we can't read
+                Err(e) => {
+                    // Skip entries we can't read
+                    let (path, error) = describe_walk_error(&e);
+                    let path = path.unwrap_or(root_path);
+                    self.report(root_path, path, TraversalDecision::Unreadable(error));
+                    continue;
+                }
             };
 
             let path = entry.path();
@@ -58,56 +281,158 @@ impl DirectoryTraverser {
             if path == root_path {
                 continue; // Skip root as it's already added
             }
+            if cache.is_some() && (path == cache_path || path == temp_path(&cache_path)) {
+                continue;
+            }
+
+            if self.decision_callback.is_some() {
+                seen.insert(path.to_path_buf());
+            }
 
             // Apply our custom filtering
             if !self.should_include_entry_by_path(path) {
+                self.report(root_path, path, TraversalDecision::Hidden);
                 continue;
             }
 
-            let is_directory = entry.file_type().map_or(false, |ft| ft.is_dir());
+            if self.follow_symlinks
+                && let Some(id) = file_identity(path)
+                && !visited.insert(id)
+            {
+                crate::warn!(
+                    "Skipping {}: already visited through another link",
+                    path.display()
+                );
+                self.report(root_path, path, TraversalDecision::SymlinkCycle);
+                continue;
+            }
+
+            let is_directory = entry.file_type().is_some_and(|ft| ft.is_dir());
             let parent_path = path.parent().unwrap_or(root_path);
 
-            // Check file size before adding to tree
-            if !is_directory {
-                if let Ok(metadata) = std::fs::metadata(path) {
-                    if metadata.len() > self.max_file_size {
-                        // Skip files that are too large
+            let metadata = if is_directory {
+                None
+            } else {
+                match std::fs::metadata(path) {
+                    Ok(metadata) => Some(metadata),
+                    Err(e) => {
+                        // Broken symlinks and entries removed mid-walk
+                        self.report(root_path, path, TraversalDecision::Unreadable(e.to_string()));
                         continue;
                     }
                 }
+            };
+            let modified = metadata.as_ref().and_then(|metadata| metadata.modified().ok());
+
+            // Check file size before adding to tree
+            if let Some(metadata) = &metadata {
+                // Skip files that are too large or too small
+                if metadata.len() > self.max_file_size {
+                    self.report(root_path, path, TraversalDecision::TooLarge(metadata.len()));
+                    continue;
+                }
+                if metadata.len() < self.min_file_size {
+                    self.report(root_path, path, TraversalDecision::TooSmall(metadata.len()));
+                    continue;
+                }
             }
 
-            if let Some(node_index) = tree.add_node(path.to_path_buf(), is_directory, parent_path) {
-                // Set file size for files
-                if !is_directory {
-                    if let Ok(metadata) = std::fs::metadata(path) {
-                        if let Some(node) = tree.get_node_mut(node_index) {
-                            node.size = Some(metadata.len());
-                        }
-                    }
+            if let (Some(modified_since), Some(modified)) = (self.modified_since, modified)
+                && modified < modified_since
+            {
+                // Skip files that haven't changed recently
+                self.report(root_path, path, TraversalDecision::NotModified);
+                continue;
+            }
+
+            let is_text = |path: &Path| match (&mut cache, &metadata) {
+                (Some(cache), Some(metadata)) => cache.is_text(path, metadata),
+                _ => super::tree::is_text_file(path),
+            };
+            if let Some(node_index) =
+                tree.add_node_with(path.to_path_buf(), is_directory, parent_path, is_text)
+            {
+                // Set file size and modification time for files
+                if let Some(metadata) = &metadata
+                    && let Some(node) = tree.get_node_mut(node_index)
+                {
+                    node.size = Some(metadata.len());
+                    node.modified = modified;
                 }
 
                 // Set initial state
                 tree.set_state(node_index, initial_state);
+
+                if !is_directory && let Some(node) = tree.get_node(node_index) {
+                    let decision = match node.size {
+                        Some(size) if !node.is_text_file => TraversalDecision::Binary(size),
+                        size => TraversalDecision::Include(size),
+                    };
+                    self.report(root_path, path, decision);
+                }
             }
         }
 
+        if self.decision_callback.is_some() {
+            self.report_pruned(root_path, seen);
+        }
+
+        if let Some(cache) = cache
+            && let Err(e) = cache.save(root_path)
+        {
+            crate::warn!("Failed to save the text detection cache: {:#}", e);
+        }
+
         Ok(tree)
     }
 
+    /// Report the topmost entries the walker pruned through ignore files or hidden names
+    fn report_pruned(&self, root_path: &Path, seen: HashSet<PathBuf>) {
+        let seen = Arc::new(seen);
+        let walked = Arc::clone(&seen);
+        let root = root_path.to_path_buf();
+
+        let mut builder = WalkBuilder::new(root_path);
+        builder
+            .standard_filters(false)
+            .follow_links(self.follow_symlinks)
+            .max_depth(self.max_depth.map(|depth| depth + 1))
+            // Only look inside directories the main walk entered
+            .filter_entry(move |entry| {
+                entry.path() == root
+                    || entry.path().parent().is_some_and(|parent| {
+                        parent == root || walked.contains(parent)
+                    })
+            });
+
+        for entry in builder.build().flatten() {
+            let path = entry.path();
+            if path == root_path || seen.contains(path) || path.file_name() == Some(".git".as_ref()) {
+                continue;
+            }
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let decision = if hidden && !self.show_hidden {
+                TraversalDecision::Hidden
+            } else {
+                TraversalDecision::Ignored
+            };
+            self.report(root_path, path, decision);
+        }
+    }
+
     fn should_include_entry_by_path(&self, path: &Path) -> bool {
         // Skip hidden files and directories unless show_hidden is enabled
-        if !self.show_hidden {
-            if let Some(name) = path.file_name() {
-                let name_str = name.to_string_lossy();
-                if name_str.starts_with('.') && name_str != "." && name_str != ".." {
-                    // Allow some common config files
-                    if !matches!(
-                        name_str.as_ref(),
-                        ".gitignore" | ".gitattributes" | ".editorconfig" | ".env" | ".env.example"
-                    ) {
-                        return false;
-                    }
+        if !self.show_hidden
+            && let Some(name) = path.file_name()
+        {
+            let name_str = name.to_string_lossy();
+            if name_str.starts_with('.') && name_str != "." && name_str != ".." {
+                // Allow some common config files
+                if !matches!(
+                    name_str.as_ref(),
+                    ".gitignore" | ".gitattributes" | ".editorconfig" | ".env" | ".env.example"
+                ) {
+                    return false;
                 }
             }
         }
@@ -119,6 +444,25 @@ impl DirectoryTraverser {
     }
 }
 
+/// Identity of the file a path resolves to (inode on Unix)
+#[cfg(unix)]
+fn file_identity(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|metadata| metadata.ino())
+}
+
+/// Identity of the file a path resolves to (hash of the canonical path elsewhere)
+#[cfg(not(unix))]
+fn file_identity(path: &Path) -> Option<u64> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let canonical = std::fs::canonicalize(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,11 +481,284 @@ mod tests {
         fs::create_dir(root_path.join("target"))?;
         fs::write(root_path.join("target").join("debug"), "binary")?;
 
-        let traverser = DirectoryTraverser::new(true, false, 1024 * 1024, false);
+        let traverser = DirectoryTraverser::new(true, false, 1024 * 1024, SelectionState::Excluded);
         let tree = traverser.traverse(root_path)?;
 
         assert!(tree.nodes.len() >= 3); // root, src, main.rs, README.md
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path().join("root");
+        let outside_path = temp_dir.path().join("outside");
+        fs::create_dir_all(root_path.join("src"))?;
+        fs::create_dir(&outside_path)?;
+        fs::write(root_path.join("src").join("main.rs"), "fn main() {}")?;
+        fs::write(outside_path.join("shared.rs"), "pub fn shared() {}")?;
+        symlink(&outside_path, root_path.join("shared"))?;
+        symlink(&root_path, root_path.join("src").join("loop"))?;
+
+        let traverser = DirectoryTraverser::new(true, false, 1024 * 1024, SelectionState::Excluded);
+        let tree = traverser.traverse(&root_path)?;
+        assert!(!tree.path_to_index.contains_key(&root_path.join("shared").join("shared.rs")));
+
+        let tree = traverser.with_follow_symlinks(true).traverse(&root_path)?;
+        assert!(tree.path_to_index.contains_key(&root_path.join("shared").join("shared.rs")));
+        assert!(!tree.path_to_index.contains_key(&root_path.join("src").join("loop")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_depth() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+
+        // root/{a.rs, one/{b.rs, two/{c.rs, three/d.rs}}}
+        let deepest = root_path.join("one").join("two").join("three");
+        fs::create_dir_all(&deepest)?;
+        fs::write(root_path.join("a.rs"), "")?;
+        fs::write(root_path.join("one").join("b.rs"), "")?;
+        fs::write(root_path.join("one").join("two").join("c.rs"), "")?;
+        fs::write(deepest.join("d.rs"), "")?;
+
+        let node_count = |max_depth| -> Result<usize> {
+            let tree = DirectoryTraverser::new(true, false, 1024 * 1024, SelectionState::Excluded)
+                .with_max_depth(max_depth)
+                .traverse(root_path)?;
+            Ok(tree.nodes.len())
+        };
+
+        assert_eq!(node_count(Some(0))?, 3); // root, a.rs, one
+        assert_eq!(node_count(Some(1))?, 5); // + b.rs, two
+        assert_eq!(node_count(Some(2))?, 7); // + c.rs, three
+        assert_eq!(node_count(None)?, 8); // + d.rs
+
+        // Directories at the cutoff are kept as leaves
+        let tree = DirectoryTraverser::new(true, false, 1024 * 1024, SelectionState::Excluded)
+            .with_max_depth(Some(0))
+            .traverse(root_path)?;
+        let one = &tree.nodes[tree.path_to_index[&root_path.join("one")]];
+        assert!(one.is_directory);
+        assert!(one.children.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_file_size() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("empty.ts"), "")?;
+        fs::write(root_path.join("short.ts"), "abc")?;
+        fs::write(root_path.join("exact.ts"), "abcd")?;
+        fs::write(root_path.join("long.ts"), "abcde")?;
+
+        let tree = DirectoryTraverser::new(true, false, 1024 * 1024, SelectionState::Excluded)
+            .with_min_file_size(4)
+            .traverse(root_path)?;
+
+        // The minimum is inclusive: a file of exactly 4 bytes is kept
+        assert!(!tree.path_to_index.contains_key(&root_path.join("empty.ts")));
+        assert!(!tree.path_to_index.contains_key(&root_path.join("short.ts")));
+        assert!(tree.path_to_index.contains_key(&root_path.join("exact.ts")));
+        assert!(tree.path_to_index.contains_key(&root_path.join("long.ts")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_since() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("old.rs"), "")?;
+        fs::write(root_path.join("new.rs"), "")?;
+        let day = std::time::Duration::from_secs(24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(root_path.join("old.rs"))?
+            .set_modified(SystemTime::now() - 3 * day)?;
+
+        let tree = DirectoryTraverser::new(true, false, 1024 * 1024, SelectionState::Excluded)
+            .with_modified_since(Some(SystemTime::now() - day))
+            .traverse(root_path)?;
+
+        assert!(!tree.path_to_index.contains_key(&root_path.join("old.rs")));
+        let new = &tree.nodes[tree.path_to_index[&root_path.join("new.rs")]];
+        assert!(new.modified.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gthrignore() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path().join("root");
+        fs::create_dir_all(root_path.join("fixtures"))?;
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("notes.log"), "debug")?;
+        fs::write(root_path.join("keep.log"), "keep")?;
+        fs::write(root_path.join("fixtures").join("big.json"), "{}")?;
+        let global_ignore = temp_dir.path().join("ignore");
+        fs::write(&global_ignore, "*.log\n")?;
+        // Project rules override the global file
+        fs::write(root_path.join(GTHRIGNORE_FILENAME), "fixtures/\n!keep.log\n")?;
+
+        let traverser = DirectoryTraverser::new(true, false, 1024 * 1024, SelectionState::Excluded)
+            .with_global_ignore_file(Some(global_ignore));
+        let tree = traverser.traverse(&root_path)?;
+        assert!(tree.path_to_index.contains_key(&root_path.join("main.rs")));
+        assert!(tree.path_to_index.contains_key(&root_path.join("keep.log")));
+        assert!(!tree.path_to_index.contains_key(&root_path.join("notes.log")));
+        assert!(!tree.path_to_index.contains_key(&root_path.join("fixtures")));
+
+        let tree = traverser.with_gthrignore(false).traverse(&root_path)?;
+        assert!(tree.path_to_index.contains_key(&root_path.join("notes.log")));
+        assert!(tree.path_to_index.contains_key(&root_path.join("fixtures").join("big.json")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decision_callback() -> Result<()> {
+        use std::sync::Mutex;
+
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::create_dir_all(root_path.join("fixtures").join("nested"))?;
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("logo.png"), [0u8, 159, 146, 150])?;
+        fs::write(root_path.join("huge.txt"), "a".repeat(2048))?;
+        fs::write(root_path.join(".env.local"), "SECRET=1")?;
+        fs::write(root_path.join("fixtures").join("nested").join("big.json"), "{}")?;
+        fs::write(root_path.join(GTHRIGNORE_FILENAME), "fixtures/\n")?;
+
+        let decisions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&decisions);
+        DirectoryTraverser::new(true, false, 1024, SelectionState::Excluded)
+            .with_decision_callback(Some(Box::new(move |path, decision| {
+                recorded.lock().unwrap().push((path.to_string_lossy().to_string(), decision));
+            })))
+            .traverse(root_path)?;
+
+        let mut decisions = decisions.lock().unwrap().clone();
+        decisions.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            decisions,
+            vec![
+                (".env.local".to_string(), TraversalDecision::Hidden),
+                (".gthrignore".to_string(), TraversalDecision::Hidden),
+                ("fixtures".to_string(), TraversalDecision::Ignored),
+                ("huge.txt".to_string(), TraversalDecision::TooLarge(2048)),
+                ("logo.png".to_string(), TraversalDecision::Binary(4)),
+                ("main.rs".to_string(), TraversalDecision::Include(Some(12))),
+            ]
+        );
+        assert_eq!(TraversalDecision::Binary(4).label(), "SKIP");
+        assert_eq!(TraversalDecision::Unreadable("denied".to_string()).label(), "SKIP");
+        assert_eq!(TraversalDecision::Binary(4).reason().as_deref(), Some("binary, 4 B"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_entries_are_reported() -> Result<()> {
+        use std::os::unix::fs::symlink;
+        use std::sync::Mutex;
+
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        symlink(root_path.join("missing.rs"), root_path.join("dangling.rs"))?;
+
+        let decisions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&decisions);
+        let tree = DirectoryTraverser::new(true, false, 1024, SelectionState::Excluded)
+            .with_decision_callback(Some(Box::new(move |path, decision| {
+                recorded.lock().unwrap().push((path.to_path_buf(), decision));
+            })))
+            .traverse(root_path)?;
+
+        // The broken link is reported instead of silently vanishing or entering the tree
+        assert!(!tree.path_to_index.contains_key(&root_path.join("dangling.rs")));
+        let decisions = decisions.lock().unwrap();
+        let (_, decision) = decisions
+            .iter()
+            .find(|(path, _)| path == Path::new("dangling.rs"))
+            .expect("dangling.rs was not reported");
+        assert!(matches!(decision, TraversalDecision::Unreadable(_)));
+        assert!(decision.reason().unwrap().starts_with("unreadable, "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path().join("root");
+        fs::create_dir_all(root_path.join("fixtures").join("customers"))?;
+        fs::create_dir_all(root_path.join("src").join("schema"))?;
+        fs::write(root_path.join("fixtures").join("customers").join("a.json"), "{}")?;
+        fs::write(root_path.join("fixtures").join("empty.json"), "{}")?;
+        fs::write(root_path.join("src").join("schema").join("api.rs"), "")?;
+        fs::write(root_path.join("schema"), "generated")?;
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        // Not overridden by a negation in .gitignore
+        fs::write(root_path.join(".gitignore"), "!fixtures/customers/\n")?;
+        let ignore_file = temp_dir.path().join(".aiignore");
+        fs::write(&ignore_file, "customers/\n/schema\n")?;
+
+        let traverser = DirectoryTraverser::new(true, false, 1024, SelectionState::Excluded);
+        let tree = traverser
+            .with_ignore_files(vec![ignore_file])
+            .traverse(&root_path)?;
+        assert!(!tree.path_to_index.contains_key(&root_path.join("fixtures").join("customers")));
+        assert!(tree.path_to_index.contains_key(&root_path.join("fixtures").join("empty.json")));
+        // Anchored at the root: only the top-level `schema` is ignored
+        assert!(!tree.path_to_index.contains_key(&root_path.join("schema")));
+        assert!(tree.path_to_index.contains_key(&root_path.join("src").join("schema")));
+
+        let missing = DirectoryTraverser::new(true, false, 1024, SelectionState::Excluded)
+            .with_ignore_files(vec![temp_dir.path().join("missing")])
+            .traverse(&root_path);
+        assert!(missing.unwrap_err().to_string().contains("does not exist"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_cache() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("run"), "make all\n")?;
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+
+        let traverser = DirectoryTraverser::new(true, true, 1024, SelectionState::Excluded)
+            .with_text_cache(true);
+        traverser.traverse(root_path)?;
+        let cache_path = root_path.join(super::super::cache::CACHE_FILENAME);
+        assert!(cache_path.exists());
+
+        // The cache never lists itself, even with hidden files shown
+        let tree = traverser.traverse(root_path)?;
+        assert!(!tree.path_to_index.contains_key(&cache_path));
+        assert!(tree.nodes[tree.path_to_index[&root_path.join("run")]].is_text_file);
+
+        let elsewhere = temp_dir.path().join("cache").join("text");
+        fs::create_dir(temp_dir.path().join("cache"))?;
+        DirectoryTraverser::new(true, true, 1024, SelectionState::Excluded)
+            .with_text_cache(true)
+            .with_cache_path(Some(elsewhere.clone()))
+            .traverse(root_path)?;
+        assert!(elsewhere.exists());
+
+        Ok(())
+    }
+}
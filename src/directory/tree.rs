@@ -1,8 +1,12 @@
 use super::state::SelectionState;
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use super::stats::FileEstimate;
+use anyhow::{Context, Result, bail};
+use phf::phf_map;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
 use std::fs;
 use std::io::Read;
+use std::time::SystemTime;
 
 #[derive(Debug, Clone)]
 pub struct FileNode {
@@ -10,10 +14,13 @@ pub struct FileNode {
     pub name: String,
     pub is_directory: bool,
     pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
     pub children: Vec<usize>, // Indices into the tree's nodes vector
     pub parent: Option<usize>,
     pub state: SelectionState,
     pub is_text_file: bool,
+    /// Token and line estimate, filled the first time the file is included
+    pub estimate: Option<FileEstimate>,
 }
 
 impl FileNode {
@@ -29,16 +36,180 @@ impl FileNode {
             name,
             is_directory,
             size: None,
+            modified: None,
             children: Vec::new(),
             parent,
             state: SelectionState::default(),
             is_text_file: false,
+            estimate: None,
         }
     }
 
     pub fn add_child(&mut self, child_index: usize) {
         self.children.push(child_index);
     }
+
+    /// Code fence language name for this file, or an empty string if unknown
+    pub fn language(&self) -> &'static str {
+        language_for_path(&self.path)
+    }
+}
+
+/// Code fence language names by lowercase extension, or by file name for files without one
+static LANGUAGES: phf::Map<&'static str, &'static str> = phf_map! {
+    // Programming languages
+    "rs" => "rust",
+    "py" => "python",
+    "js" => "javascript",
+    "ts" => "typescript",
+    "jsx" => "jsx",
+    "tsx" => "tsx",
+    "java" => "java",
+    "c" => "c",
+    "cpp" => "cpp",
+    "cc" => "cpp",
+    "cxx" => "cpp",
+    "h" => "cpp",
+    "hpp" => "cpp",
+    "hxx" => "cpp",
+    "go" => "go",
+    "rb" => "ruby",
+    "php" => "php",
+    "swift" => "swift",
+    "kt" => "kotlin",
+    "kts" => "kotlin",
+    "scala" => "scala",
+    "dart" => "dart",
+    "lua" => "lua",
+    "perl" => "perl",
+    "pl" => "perl",
+    "pm" => "perl",
+    "r" => "r",
+    "jl" => "julia",
+    "hs" => "haskell",
+    "elm" => "elm",
+    "clj" => "clojure",
+    "cljs" => "clojure",
+    "ex" => "elixir",
+    "exs" => "elixir",
+    "erl" => "erlang",
+    "hrl" => "erlang",
+    "ml" => "ocaml",
+    "mli" => "ocaml",
+    "fs" => "fsharp",
+    "fsi" => "fsharp",
+    "fsx" => "fsharp",
+    "fsscript" => "fsharp",
+    "pas" => "pascal",
+    "pp" => "pascal",
+    "inc" => "pascal",
+    "asm" => "asm",
+    "s" => "asm",
+    "nim" => "nim",
+    "zig" => "zig",
+    "v" => "v",
+    "gleam" => "gleam",
+    "d" => "d",
+    "adb" => "ada",
+    "ads" => "ada",
+    "cob" => "cobol",
+    "cbl" => "cobol",
+    "f" => "fortran",
+    "for" => "fortran",
+    "f90" => "fortran",
+    "f95" => "fortran",
+    "f03" => "fortran",
+    "pro" => "prolog",
+    // Web technologies
+    "html" => "html",
+    "htm" => "html",
+    "css" => "css",
+    "scss" => "scss",
+    "sass" => "scss",
+    "less" => "less",
+    "vue" => "vue",
+    "svelte" => "svelte",
+    // Data formats
+    "json" => "json",
+    "ipynb" => "json",
+    "yaml" => "yaml",
+    "yml" => "yaml",
+    "toml" => "toml",
+    "xml" => "xml",
+    "csv" => "csv",
+    "tsv" => "tsv",
+    "ini" => "ini",
+    "conf" => "ini",
+    "config" => "ini",
+    "properties" => "properties",
+    "env" => "dotenv",
+    // Documentation
+    "md" => "markdown",
+    "txt" => "text",
+    "rst" => "rst",
+    "adoc" => "asciidoc",
+    "tex" => "latex",
+    "org" => "org",
+    "typ" => "typst",
+    "typst" => "typst",
+    // Scripts
+    "sh" => "bash",
+    "bash" => "bash",
+    "zsh" => "zsh",
+    "fish" => "fish",
+    "ps1" => "powershell",
+    "bat" => "batch",
+    "cmd" => "batch",
+    // Configuration files
+    "gitignore" => "gitignore",
+    "gitattributes" => "gitattributes",
+    "dockerignore" => "gitignore",
+    "editorconfig" => "editorconfig",
+    "eslintrc" => "json",
+    "prettierrc" => "json",
+    "babelrc" => "json",
+    "npmrc" => "ini",
+    "yarnrc" => "yaml",
+    // Build files
+    "dockerfile" => "dockerfile",
+    "makefile" => "makefile",
+    "justfile" => "just",
+    "cmake" => "cmake",
+    "gradle" => "groovy",
+    "gemfile" => "ruby",
+    "podfile" => "ruby",
+    "rakefile" => "ruby",
+    "vagrantfile" => "ruby",
+    "pipfile" => "toml",
+    // Misc text formats
+    "log" => "log",
+    "nix" => "nix",
+    "vim" => "vim",
+    "vimrc" => "vim",
+    "el" => "elisp",
+    "emacs" => "elisp",
+    "lisp" => "lisp",
+    "scm" => "scheme",
+    "rkt" => "racket",
+    "sql" => "sql",
+    "proto" => "protobuf",
+    "graphql" => "graphql",
+    "gql" => "graphql",
+};
+
+/// Code fence language name for a path, or an empty string if unknown
+pub fn language_for_path(path: &Path) -> &'static str {
+    let key = match path.extension() {
+        Some(extension) => extension,
+        None => match path.file_name() {
+            Some(name) => name,
+            None => return "",
+        },
+    };
+    LANGUAGES
+        .get(key.to_string_lossy().to_lowercase().as_str())
+        .copied()
+        .unwrap_or("")
 }
 
 #[derive(Debug)]
@@ -69,6 +240,17 @@ impl DirectoryTree {
         path: PathBuf,
         is_directory: bool,
         parent_path: &Path,
+    ) -> Option<usize> {
+        self.add_node_with(path, is_directory, parent_path, is_text_file)
+    }
+
+    /// `add_node` with the text detection of files left to `is_text`, such as a cache
+    pub fn add_node_with(
+        &mut self,
+        path: PathBuf,
+        is_directory: bool,
+        parent_path: &Path,
+        is_text: impl FnOnce(&Path) -> bool,
     ) -> Option<usize> {
         if self.path_to_index.contains_key(&path) {
             return self.path_to_index.get(&path).copied();
@@ -81,7 +263,7 @@ impl DirectoryTree {
 
         // Determine if it's a text file
         if !is_directory {
-            node.is_text_file = is_text_file(&path);
+            node.is_text_file = is_text(&path);
         }
 
         self.nodes.push(node);
@@ -93,6 +275,190 @@ impl DirectoryTree {
         Some(node_index)
     }
 
+    /// Build a tree from an explicit list of paths, such as the output of `git ls-files`
+    ///
+    /// Relative paths are resolved against `root`; absolute paths must lie inside it.
+    /// Missing parent directories are added, listed paths start included and directory
+    /// states follow from their contents. Paths that no longer exist are skipped.
+    pub fn from_path_list(root: PathBuf, paths: &[PathBuf]) -> Result<Self> {
+        let mut tree = Self::new(root.clone());
+
+        for path in paths {
+            let relative = if path.is_absolute() {
+                path.strip_prefix(&root)
+                    .with_context(|| format!("{} is outside {}", path.display(), root.display()))?
+            } else {
+                path.as_path()
+            };
+
+            let mut full_path = root.clone();
+            let mut components = Vec::new();
+            for component in relative.components() {
+                match component {
+                    Component::Normal(name) => components.push(name),
+                    Component::CurDir => {}
+                    _ => bail!("{} is outside {}", path.display(), root.display()),
+                }
+            }
+            let Some((name, parents)) = components.split_last() else {
+                continue;
+            };
+
+            let Ok(metadata) = fs::metadata(root.join(relative)) else {
+                crate::warn!("Skipping {}: not found", path.display());
+                continue;
+            };
+
+            for parent in parents {
+                let parent_path = full_path.clone();
+                full_path.push(parent);
+                tree.add_node(full_path.clone(), true, &parent_path);
+            }
+            let parent_path = full_path.clone();
+            full_path.push(name);
+
+            if let Some(index) = tree.add_node(full_path, metadata.is_dir(), &parent_path) {
+                let node = &mut tree.nodes[index];
+                if !metadata.is_dir() {
+                    node.size = Some(metadata.len());
+                    node.modified = metadata.modified().ok();
+                }
+                node.state = SelectionState::Included;
+            }
+        }
+
+        tree.recompute_directory_states();
+        Ok(tree)
+    }
+
+    /// Include exactly the files at `relative_paths`, as listed by `format_file_list`
+    ///
+    /// Returns the paths that are not in the tree, which are skipped.
+    pub fn apply_selection(&mut self, relative_paths: &[String]) -> Vec<String> {
+        let index_by_path: HashMap<PathBuf, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (self.relative_path(&node.path), index))
+            .collect();
+
+        self.set_state(self.root_index, SelectionState::Excluded);
+        let mut missing = Vec::new();
+        for relative_path in relative_paths {
+            match index_by_path.get(Path::new(relative_path)) {
+                Some(&index) => self.set_state(index, SelectionState::Included),
+                None => missing.push(relative_path.clone()),
+            }
+        }
+        missing
+    }
+
+    /// Copy of the node at `index` and its descendants, with that node as the root
+    ///
+    /// Selection states are kept. Only the subtree is visited, so this is cheap even for
+    /// large trees. Panics if `index` is out of bounds.
+    pub fn subtree(&self, index: usize) -> DirectoryTree {
+        let mut root = self.nodes[index].clone();
+        root.parent = None;
+        root.children.clear();
+        let mut subtree = Self {
+            nodes: vec![root],
+            root_index: 0,
+            path_to_index: HashMap::from([(self.nodes[index].path.clone(), 0)]),
+        };
+
+        // (index in self, index of its copy in subtree)
+        let mut stack = vec![(index, 0)];
+        while let Some((old_index, new_index)) = stack.pop() {
+            for &child in &self.nodes[old_index].children {
+                let mut node = self.nodes[child].clone();
+                node.parent = Some(new_index);
+                node.children.clear();
+
+                let child_index = subtree.nodes.len();
+                subtree.path_to_index.insert(node.path.clone(), child_index);
+                subtree.nodes.push(node);
+                subtree.nodes[new_index].add_child(child_index);
+                stack.push((child, child_index));
+            }
+        }
+
+        subtree
+    }
+
+    /// Combine separately traversed trees under a synthetic root whose children are their roots
+    ///
+    /// Roots sharing a directory name get a numeric suffix (`app`, `app-2`) so that
+    /// relative paths stay unique.
+    pub fn merge_roots(trees: Vec<DirectoryTree>) -> Self {
+        let mut merged = Self::new(PathBuf::new());
+        merged.nodes[merged.root_index].name = ".".to_string();
+        let mut root_names = HashSet::new();
+
+        for mut tree in trees {
+            let root_name = &mut tree.nodes[tree.root_index].name;
+            let base_name = root_name.clone();
+            let mut suffix = 2;
+            while !root_names.insert(root_name.clone()) {
+                *root_name = format!("{}-{}", base_name, suffix);
+                suffix += 1;
+            }
+
+            let offset = merged.nodes.len();
+            merged.nodes[merged.root_index].add_child(offset + tree.root_index);
+
+            for mut node in tree.nodes {
+                node.parent = Some(node.parent.map_or(merged.root_index, |parent| parent + offset));
+                for child in &mut node.children {
+                    *child += offset;
+                }
+                merged.path_to_index.insert(node.path.clone(), merged.nodes.len());
+                merged.nodes.push(node);
+            }
+        }
+
+        merged.update_parent_state(merged.root_index);
+        merged
+    }
+
+    /// Whether this tree was built from several roots by `merge_roots`
+    pub fn is_multi_root(&self) -> bool {
+        self.nodes[self.root_index].path.as_os_str().is_empty()
+    }
+
+    /// Path relative to the root; in multi-root trees it starts with the name of its root
+    pub fn relative_path(&self, path: &Path) -> PathBuf {
+        let root = &self.nodes[self.root_index];
+        if self.is_multi_root() {
+            for &child in &root.children {
+                let sub_root = &self.nodes[child];
+                if let Ok(rest) = path.strip_prefix(&sub_root.path) {
+                    let mut relative_path = PathBuf::from(&sub_root.name);
+                    if !rest.as_os_str().is_empty() {
+                        relative_path.push(rest);
+                    }
+                    return relative_path;
+                }
+            }
+        }
+
+        path.strip_prefix(&root.path).unwrap_or(path).to_path_buf()
+    }
+
+    /// Root directory for display, listing every root of a multi-root tree
+    pub fn root_label(&self) -> String {
+        let root = &self.nodes[self.root_index];
+        if self.is_multi_root() {
+            root.children
+                .iter()
+                .map(|&child| self.nodes[child].path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        } else {
+            root.path.display().to_string()
+        }
+    }
+
     pub fn get_node(&self, index: usize) -> Option<&FileNode> {
         self.nodes.get(index)
     }
@@ -135,17 +501,69 @@ impl DirectoryTree {
     }
 
     fn update_parent_state(&mut self, parent_index: usize) {
-        let children: Vec<usize> = self.nodes[parent_index].children.clone();
+        let Some(new_state) = self.state_from_children(parent_index) else {
+            return;
+        };
+
+        if let Some(parent) = self.nodes.get_mut(parent_index) {
+            parent.state = new_state;
+        }
 
+        // Recursively update grandparent
+        if let Some(grandparent_index) = self.nodes[parent_index].parent {
+            self.update_parent_state(grandparent_index);
+        }
+    }
+
+    /// Derive every directory's state from its children, after leaf states were set directly
+    pub fn recompute_directory_states(&mut self) {
+        // Children always come after their parent, so walking backwards visits them first
+        for index in (0..self.nodes.len()).rev() {
+            if let Some(state) = self.state_from_children(index) {
+                self.nodes[index].state = state;
+            }
+        }
+    }
+
+    /// Take over the selection of `previous`, an earlier scan of the same roots
+    ///
+    /// Files and empty directories found in both keep their state, new ones keep the state
+    /// they were traversed with, and directory states follow from their contents. A renamed
+    /// file counts as removed and added. Returns the numbers of files added and removed.
+    pub fn keep_selection_from(&mut self, previous: &DirectoryTree) -> (usize, usize) {
+        let mut added = 0;
+        for node in &mut self.nodes {
+            match previous.path_to_index.get(&node.path) {
+                Some(&index) if node.children.is_empty() => {
+                    node.state = previous.nodes[index].state;
+                }
+                Some(_) => {}
+                None if !node.is_directory => added += 1,
+                None => {}
+            }
+        }
+        let removed = previous
+            .nodes
+            .iter()
+            .filter(|node| !node.is_directory && !self.path_to_index.contains_key(&node.path))
+            .count();
+
+        self.recompute_directory_states();
+        (added, removed)
+    }
+
+    /// State implied by a directory's children, or `None` if it has none
+    fn state_from_children(&self, index: usize) -> Option<SelectionState> {
+        let children = &self.nodes.get(index)?.children;
         if children.is_empty() {
-            return;
+            return None;
         }
 
         let mut included_count = 0;
         let mut excluded_count = 0;
         let mut partial_count = 0;
 
-        for child_index in &children {
+        for child_index in children {
             if let Some(child) = self.nodes.get(*child_index) {
                 match child.state {
                     SelectionState::Included => included_count += 1,
@@ -155,22 +573,15 @@ impl DirectoryTree {
             }
         }
 
-        let new_state = if partial_count > 0 || (included_count > 0 && excluded_count > 0) {
-            SelectionState::Partial
-        } else if included_count > 0 {
-            SelectionState::Included
-        } else {
-            SelectionState::Excluded
-        };
-
-        if let Some(parent) = self.nodes.get_mut(parent_index) {
-            parent.state = new_state;
-        }
-
-        // Recursively update grandparent
-        if let Some(grandparent_index) = self.nodes[parent_index].parent {
-            self.update_parent_state(grandparent_index);
-        }
+        Some(
+            if partial_count > 0 || (included_count > 0 && excluded_count > 0) {
+                SelectionState::Partial
+            } else if included_count > 0 {
+                SelectionState::Included
+            } else {
+                SelectionState::Excluded
+            },
+        )
     }
 
     pub fn toggle_state(&mut self, index: usize) {
@@ -180,6 +591,22 @@ impl DirectoryTree {
         }
     }
 
+    /// Indices of the text files at or below `index`, whatever their state
+    pub fn file_indices(&self, index: usize) -> Vec<usize> {
+        let mut files = Vec::new();
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            let Some(node) = self.nodes.get(index) else {
+                continue;
+            };
+            if !node.is_directory && node.is_text_file {
+                files.push(index);
+            }
+            stack.extend(&node.children);
+        }
+        files
+    }
+
     pub fn get_all_included_files(&self) -> Vec<&FileNode> {
         let mut included_files = Vec::new();
         self.collect_included_files(self.root_index, &mut included_files);
@@ -200,7 +627,7 @@ impl DirectoryTree {
     }
 }
 
-fn is_text_file(path: &Path) -> bool {
+pub(crate) fn is_text_file(path: &Path) -> bool {
     // Quick extension-based check for common text file extensions
     if is_text_by_extension(path) {
         return true;
@@ -211,7 +638,7 @@ fn is_text_file(path: &Path) -> bool {
     is_text_by_content(path)
 }
 
-fn is_text_by_extension(path: &Path) -> bool {
+pub(crate) fn is_text_by_extension(path: &Path) -> bool {
     if let Some(extension) = path.extension() {
         let ext = extension.to_string_lossy().to_lowercase();
         matches!(
@@ -221,11 +648,14 @@ fn is_text_by_extension(path: &Path) -> bool {
             | "h" | "hpp" | "hxx" | "go" | "rb" | "php" | "swift" | "kt" | "kts" | "scala"
             | "dart" | "lua" | "perl" | "r" | "jl" | "hs" | "elm" | "clj" | "cljs"
             | "ex" | "exs" | "erl" | "hrl" | "ml" | "mli" | "fs" | "fsi" | "fsx" | "fsscript"
-            | "pas" | "pp" | "inc" | "asm" | "s"
+            | "pas" | "pp" | "inc" | "asm" | "s" | "pl" | "pm" | "nim" | "zig" | "v"
+            | "gleam" | "d" | "adb" | "ads" | "cob" | "cbl" | "f" | "for" | "f90" | "f95"
+            | "f03" | "pro"
             // Web technologies
             | "html" | "htm" | "css" | "scss" | "sass" | "less" | "vue" | "svelte"
             // Data formats
             | "json" | "yaml" | "yml" | "toml" | "xml" | "csv" | "tsv" | "ini" | "conf"
+            | "ipynb"
             | "config" | "properties" | "env"
             // Documentation
             | "md" | "txt" | "rst" | "adoc" | "tex" | "org"
@@ -261,7 +691,7 @@ fn is_text_by_extension(path: &Path) -> bool {
     }
 }
 
-fn is_text_by_content(path: &Path) -> bool {
+pub(crate) fn is_text_by_content(path: &Path) -> bool {
     // Read first few KB to determine if file is text or binary
     const SAMPLE_SIZE: usize = 8192; // 8KB sample
 
@@ -306,7 +736,7 @@ fn is_likely_text(buffer: &[u8]) -> bool {
         let byte = buffer[i];
 
         // ASCII printable characters and common whitespace
-        if (byte >= 32 && byte <= 126) || byte == b'\n' || byte == b'\r' || byte == b'\t' {
+        if (32..=126).contains(&byte) || byte == b'\n' || byte == b'\r' || byte == b'\t' {
             printable_count += 1;
             i += 1;
         }
@@ -351,3 +781,117 @@ fn get_utf8_char_length(first_byte: u8) -> Option<usize> {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language() {
+        let language = |path: &str| FileNode::new(PathBuf::from(path), false, None).language();
+
+        assert_eq!(language("src/main.rs"), "rust");
+        assert_eq!(language("lib/app.EX"), "elixir");
+        assert_eq!(language("Main.hs"), "haskell");
+        assert_eq!(language("build.zig"), "zig");
+        assert_eq!(language("solver.f90"), "fortran");
+        assert_eq!(language("rules.pro"), "prolog");
+        assert_eq!(language("Dockerfile"), "dockerfile");
+        assert_eq!(language("Makefile"), "makefile");
+        assert_eq!(language("data.bin"), "");
+        assert_eq!(language("LICENSE"), "");
+    }
+
+    #[test]
+    fn test_subtree() {
+        let root = PathBuf::from("/project");
+        let mut tree = DirectoryTree::new(root.clone());
+        let src = root.join("src");
+        let ui = src.join("ui");
+        tree.add_node(root.join("README.md"), false, &root);
+        tree.add_node(src.clone(), true, &root);
+        tree.add_node(src.join("main.rs"), false, &src);
+        tree.add_node(ui.clone(), true, &src);
+        let app = tree.add_node(ui.join("app.rs"), false, &ui).unwrap();
+        tree.set_state(app, SelectionState::Included);
+
+        let subtree = tree.subtree(tree.path_to_index[&src]);
+
+        assert_eq!(subtree.nodes.len(), 4);
+        assert_eq!(subtree.nodes[subtree.root_index].path, src);
+        assert_eq!(subtree.nodes[subtree.root_index].parent, None);
+        assert!(!subtree.path_to_index.contains_key(&root.join("README.md")));
+        let app = &subtree.nodes[subtree.path_to_index[&ui.join("app.rs")]];
+        assert_eq!(app.state, SelectionState::Included);
+        assert_eq!(subtree.nodes[app.parent.unwrap()].path, ui);
+        assert_eq!(subtree.nodes[subtree.root_index].state, SelectionState::Partial);
+        assert_eq!(subtree.relative_path(&app.path), PathBuf::from("ui/app.rs"));
+    }
+
+    #[test]
+    fn test_keep_selection_from() {
+        let root = PathBuf::from("/project");
+        let src = root.join("src");
+        let scan = |files: &[&str]| {
+            let mut tree = DirectoryTree::new(root.clone());
+            tree.add_node(src.clone(), true, &root);
+            for file in files {
+                tree.add_node(src.join(file), false, &src);
+            }
+            tree
+        };
+        let mut previous = scan(&["main.rs", "old.rs", "lib.rs"]);
+        for file in ["main.rs", "old.rs"] {
+            previous.set_state(previous.path_to_index[&src.join(file)], SelectionState::Included);
+        }
+
+        // old.rs was renamed to new.rs, lib.rs deleted and extra.rs created
+        let mut tree = scan(&["main.rs", "new.rs", "extra.rs"]);
+        let (added, removed) = tree.keep_selection_from(&previous);
+        assert_eq!((added, removed), (2, 2));
+
+        let state = |file: &str| tree.nodes[tree.path_to_index[&src.join(file)]].state;
+        assert_eq!(state("main.rs"), SelectionState::Included);
+        // The renamed file starts out like any new file
+        assert_eq!(state("new.rs"), SelectionState::Excluded);
+        assert_eq!(state("extra.rs"), SelectionState::Excluded);
+        assert_eq!(tree.nodes[tree.path_to_index[&src]].state, SelectionState::Partial);
+        assert_eq!(tree.get_all_included_files().len(), 1);
+    }
+
+    #[test]
+    fn test_from_path_list() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let root = temp_dir.path().to_path_buf();
+        fs::create_dir_all(root.join("src").join("ui"))?;
+        fs::write(root.join("src").join("main.rs"), "fn main() {}")?;
+        fs::write(root.join("src").join("ui").join("app.rs"), "pub struct App;")?;
+        fs::write(root.join("src").join("ui").join("view.rs"), "")?;
+        fs::write(root.join("logo.png"), [0u8, 159, 146, 150])?;
+
+        let tree = DirectoryTree::from_path_list(
+            root.clone(),
+            &[
+                PathBuf::from("./src/main.rs"),
+                root.join("src").join("ui").join("app.rs"),
+                PathBuf::from("logo.png"),
+                PathBuf::from("deleted.rs"),
+            ],
+        )?;
+        let node = |relative: &str| &tree.nodes[tree.path_to_index[&root.join(relative)]];
+
+        // Parent directories are created once, and files not listed are left out
+        assert_eq!(tree.nodes.len(), 6);
+        assert!(node("src/ui").is_directory);
+        assert_eq!(node("src/main.rs").size, Some(12));
+        assert!(node("src/main.rs").is_text_file);
+        assert!(!node("logo.png").is_text_file);
+        assert_eq!(node("src/ui").state, SelectionState::Included);
+        assert_eq!(tree.get_all_included_files().len(), 2);
+
+        let outside = DirectoryTree::from_path_list(root, &[PathBuf::from("../secret.rs")]);
+        assert!(outside.unwrap_err().to_string().contains("is outside"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Exit code for errors without a more specific one
+pub const EXIT_FAILURE: i32 = 1;
+
+/// `--help` section listing the exit codes of `GthrError`
+pub const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  Success
+  1  Error
+  2  No files included in the output
+  3  Output destination not writable
+  4  Clipboard unavailable with --clipboard
+  5  Interactive mode quit without exporting";
+
+/// Failures scripts may want to tell apart, each with its own exit code
+#[derive(Debug)]
+pub enum GthrError {
+    /// The selection produced no content to export
+    NoFilesIncluded,
+    /// The output file could not be written, with the reason
+    OutputUnwritable(PathBuf, String),
+    /// `--clipboard` was given but the clipboard could not be used, with the reason
+    ClipboardUnavailable(String),
+    /// The interactive mode was quit without exporting anything
+    Cancelled,
+}
+
+impl GthrError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GthrError::NoFilesIncluded => 2,
+            GthrError::OutputUnwritable(..) => 3,
+            GthrError::ClipboardUnavailable(_) => 4,
+            GthrError::Cancelled => 5,
+        }
+    }
+
+    /// Exit code for any error, using the first `GthrError` in its chain
+    pub fn exit_code_of(error: &anyhow::Error) -> i32 {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<GthrError>())
+            .map_or(EXIT_FAILURE, GthrError::exit_code)
+    }
+}
+
+impl fmt::Display for GthrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GthrError::NoFilesIncluded => {
+                write!(f, "No content included. Please include at least one file.")
+            }
+            GthrError::OutputUnwritable(path, reason) => {
+                write!(f, "Failed to write {}: {}", path.display(), reason)
+            }
+            GthrError::ClipboardUnavailable(reason) => {
+                write!(f, "Clipboard unavailable: {}", reason)
+            }
+            GthrError::Cancelled => write!(f, "Cancelled without exporting"),
+        }
+    }
+}
+
+impl std::error::Error for GthrError {}
+
+/// Whether stdout was closed by its reader, like `head`, which is not a failure
+///
+/// On Unix the ignored `SIGPIPE` surfaces as this error; Windows reports closed pipes the same way.
+pub fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_of() {
+        let error = anyhow::Error::new(GthrError::NoFilesIncluded);
+        assert_eq!(GthrError::exit_code_of(&error), 2);
+
+        // Context added on the way up keeps the code
+        let error = Err::<(), _>(GthrError::ClipboardUnavailable("no display".to_string()))
+            .context("Export failed")
+            .unwrap_err();
+        assert_eq!(GthrError::exit_code_of(&error), 4);
+        assert_eq!(GthrError::exit_code_of(&GthrError::Cancelled.into()), 5);
+
+        assert_eq!(
+            GthrError::exit_code_of(&anyhow::anyhow!("bad pattern")),
+            EXIT_FAILURE
+        );
+    }
+
+    #[test]
+    fn test_is_broken_pipe() {
+        let error = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+            .context("Failed to write the file list")
+            .unwrap_err();
+        assert!(is_broken_pipe(&error));
+        assert!(!is_broken_pipe(&std::io::Error::other("disk full").into()));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::constants::MAX_CONTENT_MATCHES;
+use crate::directory::tree::DirectoryTree;
+use anyhow::Result;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use grep_searcher::sinks::Lossy;
+use grep_searcher::{BinaryDetection, SearcherBuilder};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use tokio::task::JoinHandle;
+
+/// A line inside a file that matched the content search query
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentMatch {
+    pub node_index: usize,
+    pub line_number: u64,
+    pub line: String,
+}
+
+/// Build a smart-case matcher for `query`, taken literally
+pub fn content_matcher(query: &str) -> Result<RegexMatcher> {
+    Ok(RegexMatcherBuilder::new()
+        .case_smart(true)
+        .fixed_strings(true)
+        .build(query)?)
+}
+
+/// Call `on_match` with the line number and text of every matching line until it returns false
+pub fn search_file<F>(matcher: &RegexMatcher, path: &Path, mut on_match: F) -> Result<()>
+where
+    F: FnMut(u64, &str) -> bool,
+{
+    let mut searcher = SearcherBuilder::new()
+        .binary_detection(BinaryDetection::quit(b'\x00'))
+        .line_number(true)
+        .build();
+
+    searcher.search_path(
+        matcher,
+        path,
+        Lossy(|line_number, line| Ok(on_match(line_number, line.trim_end()))),
+    )?;
+    Ok(())
+}
+
+/// A content search running on the blocking thread pool, streaming matches as they are found
+pub struct ContentSearch {
+    receiver: Receiver<ContentMatch>,
+    cancelled: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl ContentSearch {
+    /// Search every text file in `tree` for `query`; must be called inside a Tokio runtime
+    pub fn spawn(tree: &DirectoryTree, query: &str) -> Result<Self> {
+        let matcher = content_matcher(query)?;
+        let files: Vec<(usize, PathBuf)> = tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| !node.is_directory && node.is_text_file)
+            .map(|(index, node)| (index, node.path.clone()))
+            .collect();
+
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task_cancelled = Arc::clone(&cancelled);
+
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut found = 0;
+            for (node_index, path) in files {
+                if task_cancelled.load(Ordering::Relaxed) || found >= MAX_CONTENT_MATCHES {
+                    return;
+                }
+
+                // Unreadable files are skipped, as in the path search
+                let _ = search_file(&matcher, &path, |line_number, line| {
+                    found += 1;
+                    let sent = sender.send(ContentMatch {
+                        node_index,
+                        line_number,
+                        line: line.to_string(),
+                    });
+                    sent.is_ok() && found < MAX_CONTENT_MATCHES
+                });
+            }
+        });
+
+        Ok(Self {
+            receiver,
+            cancelled,
+            handle,
+        })
+    }
+
+    /// Matches found since the last call
+    pub fn drain(&self) -> Vec<ContentMatch> {
+        self.receiver.try_iter().collect()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+impl Drop for ContentSearch {
+    fn drop(&mut self) {
+        // Stop scanning files once a newer query replaces this search
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_search_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(
+            &path,
+            "use std::fs;\n\nfn parse_config() {}\nfn main() { parse_config(); }\n",
+        )?;
+
+        let mut matches = Vec::new();
+        search_file(
+            &content_matcher("parse_config")?,
+            &path,
+            |line_number, line| {
+                matches.push((line_number, line.to_string()));
+                true
+            },
+        )?;
+
+        assert_eq!(
+            matches,
+            vec![
+                (3, "fn parse_config() {}".to_string()),
+                (4, "fn main() { parse_config(); }".to_string()),
+            ]
+        );
+
+        // Queries with uppercase letters are case-sensitive, and are never parsed as regexes
+        let mut count = 0;
+        search_file(&content_matcher("PARSE_CONFIG(")?, &path, |_, _| {
+            count += 1;
+            true
+        })?;
+        assert_eq!(count, 0);
+        search_file(&content_matcher("fn main(")?, &path, |_, _| {
+            count += 1;
+            true
+        })?;
+        assert_eq!(count, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_content_search_streams_matches() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("a.rs"), "fn needle() {}\n")?;
+        fs::write(root_path.join("b.rs"), "fn haystack() {}\n")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("a.rs"), false, root_path);
+        tree.add_node(root_path.join("b.rs"), false, root_path);
+
+        let search = ContentSearch::spawn(&tree, "needle")?;
+        while !search.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        let matches = search.drain();
+
+        assert_eq!(
+            matches,
+            vec![ContentMatch {
+                node_index: tree.path_to_index[&root_path.join("a.rs")],
+                line_number: 1,
+                line: "fn needle() {}".to_string(),
+            }]
+        );
+
+        Ok(())
+    }
+}
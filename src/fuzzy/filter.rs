@@ -1,9 +1,128 @@
-use super::matcher::{search_items, MatchResult};
+use super::content::ContentMatch;
+use super::matcher::{FuzzySearch, MatchResult, search_items_exact, search_items_regex};
+use crate::directory::state::SelectionState;
 use crate::directory::tree::{DirectoryTree, FileNode};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
 
+/// How the search query is matched against relative paths
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SearchMode {
+    #[default]
+    Fuzzy,
+    /// Case-insensitive substring of the relative path
+    Substring,
+    Regex,
+    Exact,
+}
+
+impl SearchMode {
+    /// The mode that follows this one when cycling with Ctrl+R
+    pub fn next(self) -> Self {
+        match self {
+            SearchMode::Fuzzy => SearchMode::Substring,
+            SearchMode::Substring => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Exact,
+            SearchMode::Exact => SearchMode::Fuzzy,
+        }
+    }
+
+    /// Search bar label, or `None` for the default fuzzy mode
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            SearchMode::Fuzzy => None,
+            SearchMode::Substring => Some("[substring]"),
+            SearchMode::Regex => Some("[regex]"),
+            SearchMode::Exact => Some("[exact]"),
+        }
+    }
+}
+
+/// Order of the flat file list, cycled with `s` in list focus
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortOrder {
+    /// Best match first, or tree order without a query
+    #[default]
+    Relevance,
+    Path,
+    /// Largest first
+    Size,
+    /// Most recently modified first
+    Modified,
+}
+
+impl SortOrder {
+    pub fn next(self) -> Self {
+        match self {
+            SortOrder::Relevance => SortOrder::Path,
+            SortOrder::Path => SortOrder::Size,
+            SortOrder::Size => SortOrder::Modified,
+            SortOrder::Modified => SortOrder::Relevance,
+        }
+    }
+
+    /// List title label, or `None` for the default relevance order
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            SortOrder::Relevance => None,
+            SortOrder::Path => Some("[sort: path]"),
+            SortOrder::Size => Some("[sort: size]"),
+            SortOrder::Modified => Some("[sort: modified]"),
+        }
+    }
+}
+
+/// Which items the file list shows by selection state, cycled with `f` in list focus
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StateFilter {
+    #[default]
+    All,
+    Included,
+    Excluded,
+}
+
+impl StateFilter {
+    pub fn next(self) -> Self {
+        match self {
+            StateFilter::All => StateFilter::Included,
+            StateFilter::Included => StateFilter::Excluded,
+            StateFilter::Excluded => StateFilter::All,
+        }
+    }
+
+    /// List title label, or `None` when everything is shown
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            StateFilter::All => None,
+            StateFilter::Included => Some("[included only]"),
+            StateFilter::Excluded => Some("[excluded only]"),
+        }
+    }
+
+    /// Partially included directories are shown by both filters
+    pub fn shows(self, state: SelectionState) -> bool {
+        match self {
+            StateFilter::All => true,
+            StateFilter::Included => state != SelectionState::Excluded,
+            StateFilter::Excluded => state != SelectionState::Included,
+        }
+    }
+}
+
+/// Search results; `matches[i]` is the match that produced the row `visible_items[i]`
 pub struct FilteredResults {
     pub matches: Vec<MatchResult>,
     pub visible_items: Vec<usize>, // Indices into the original tree
+    pub search_mode: SearchMode,
+    pub error: Option<String>, // Set when a regex query does not compile
+    pub content_matches: Vec<ContentMatch>, // Parallel to visible_items during content search
+    pub exclusions: Vec<String>, // `!term`s of a fuzzy query, shown as badges
+}
+
+impl Default for FilteredResults {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FilteredResults {
@@ -11,7 +130,43 @@ impl FilteredResults {
         Self {
             matches: Vec::new(),
             visible_items: Vec::new(),
+            search_mode: SearchMode::default(),
+            error: None,
+            content_matches: Vec::new(),
+            exclusions: Vec::new(),
+        }
+    }
+
+    /// Append a streamed content search match as a new row
+    pub fn push_content_match(&mut self, content_match: ContentMatch) {
+        self.matches
+            .push(MatchResult::new(1, Vec::new(), self.visible_items.len()));
+        self.visible_items.push(content_match.node_index);
+        self.content_matches.push(content_match);
+    }
+
+    /// Matched character positions in the display path of `row`, empty without a query
+    pub fn match_indices(&self, row: usize) -> &[usize] {
+        self.matches
+            .get(row)
+            .map_or(&[], |match_result| match_result.indices.as_slice())
+    }
+
+    /// Runs of matched characters in the display path of `row`, as `(start, end)` character
+    /// ranges with `end` exclusive, for highlighting
+    pub fn highlighted_spans(&self, row: usize) -> Vec<(usize, usize)> {
+        let mut indices = self.match_indices(row).to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let mut spans: Vec<(usize, usize)> = Vec::new();
+        for index in indices {
+            match spans.last_mut() {
+                Some((_, end)) if *end == index => *end += 1,
+                _ => spans.push((index, index + 1)),
+            }
         }
+        spans
     }
 
     pub fn is_empty(&self) -> bool {
@@ -23,7 +178,12 @@ impl FilteredResults {
     }
 }
 
-pub fn filter_tree_nodes(tree: &DirectoryTree, query: &str) -> FilteredResults {
+pub fn filter_tree_nodes(
+    tree: &DirectoryTree,
+    query: &str,
+    search_mode: SearchMode,
+    fuzzy_search: &FuzzySearch,
+) -> FilteredResults {
     // Collect all nodes that should be searchable
     let searchable_nodes: Vec<(usize, &FileNode)> = tree
         .nodes
@@ -38,18 +198,52 @@ pub fn filter_tree_nodes(tree: &DirectoryTree, query: &str) -> FilteredResults {
     // Extract text for fuzzy matching (use relative path from root)
     let node_texts: Vec<String> = searchable_nodes
         .iter()
-        .map(|(_, node)| {
-            // Create a display path relative to the root
-            if let Ok(relative_path) = node.path.strip_prefix(&tree.nodes[tree.root_index].path) {
-                relative_path.to_string_lossy().to_string()
-            } else {
-                node.name.clone()
-            }
-        })
+        .map(|(_, node)| tree.relative_path(&node.path).to_string_lossy().to_string())
         .collect();
 
-    // Perform fuzzy search
-    let matches = search_items(&node_texts, query, |text| text.as_str());
+    let mut error = None;
+    let mut exclusions = Vec::new();
+    let matches = match search_mode {
+        SearchMode::Fuzzy => {
+            let (inclusions, excluded_terms) = parse_query(query, fuzzy_search);
+            let mut matches = match inclusions.as_slice() {
+                terms @ [_, _, ..] => {
+                    fuzzy_search.search_items_all(&node_texts, terms, |text| text.as_str())
+                }
+                [term] => fuzzy_search.search_items(&node_texts, term, |text| text.as_str()),
+                // Only delimiters or exclusions typed so far
+                [] => fuzzy_search.search_items(&node_texts, "", |text| text.as_str()),
+            };
+            if !excluded_terms.is_empty() {
+                let excluded_terms: Vec<String> =
+                    excluded_terms.iter().map(|term| term.to_lowercase()).collect();
+                matches.retain(|match_result| {
+                    let text = node_texts[match_result.item_index].to_lowercase();
+                    !excluded_terms.iter().any(|term| text.contains(term.as_str()))
+                });
+            }
+            boost_filename_matches(&mut matches, &node_texts, fuzzy_search.filename_boost());
+            exclusions = excluded_terms.iter().map(|term| term.to_string()).collect();
+            matches
+        }
+        SearchMode::Substring => {
+            // An escaped pattern keeps the highlight on the original characters
+            let regex = RegexBuilder::new(&regex::escape(query))
+                .case_insensitive(true)
+                .build()
+                .expect("escaped query is a valid regex");
+            search_items_regex(&node_texts, &regex, |text| text.as_str())
+        }
+        SearchMode::Regex => match Regex::new(query) {
+            Ok(regex) => search_items_regex(&node_texts, &regex, |text| text.as_str()),
+            Err(e) => {
+                // Show everything while the pattern is incomplete
+                error = Some(regex_error_summary(&e));
+                fuzzy_search.search_items(&node_texts, "", |text| text.as_str())
+            }
+        },
+        SearchMode::Exact => search_items_exact(&node_texts, query, |text| text.as_str()),
+    };
 
     // Map results back to tree indices
     let visible_items: Vec<usize> = matches
@@ -60,16 +254,146 @@ pub fn filter_tree_nodes(tree: &DirectoryTree, query: &str) -> FilteredResults {
     FilteredResults {
         matches,
         visible_items,
+        search_mode,
+        error,
+        content_matches: Vec::new(),
+        exclusions,
+    }
+}
+
+/// Split a fuzzy query into terms to match and `!`-prefixed terms to exclude
+///
+/// Exclusions drop every path containing them, ignoring case, so `rs !test` lists
+/// Rust files outside tests.
+pub fn parse_query<'a>(
+    query: &'a str,
+    fuzzy_search: &FuzzySearch,
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let mut inclusions = Vec::new();
+    let mut exclusions = Vec::new();
+    for term in fuzzy_search.split_query(query) {
+        match term.trim().strip_prefix('!') {
+            Some("") => {} // A lone `!` while the term is being typed
+            Some(excluded) => exclusions.push(excluded),
+            None => inclusions.push(term),
+        }
+    }
+    (inclusions, exclusions)
+}
+
+/// Multiply the score of matches that lie entirely in the last path component by `boost`
+/// and re-sort, so `src/main.rs` outranks `domain/maintainer.rs` for `main`
+fn boost_filename_matches(matches: &mut [MatchResult], texts: &[String], boost: f64) {
+    for match_result in matches.iter_mut() {
+        let text = &texts[match_result.item_index];
+        let filename_start = text
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| std::path::is_separator(*c))
+            .last()
+            .map_or(0, |(index, _)| index + 1);
+        if !match_result.indices.is_empty()
+            && match_result.indices.iter().all(|&index| index >= filename_start)
+        {
+            match_result.score = (match_result.score as f64 * boost).round() as i64;
+        }
+    }
+
+    // Stable, so equal scores keep the matcher's order
+    matches.sort_by_key(|result| std::cmp::Reverse(result.score));
+}
+
+/// Last line of a regex error, which names the problem without the pattern excerpt
+/// Reorder the rows of path search results, each keeping its match
+///
+/// Ties keep their relevance order; directories and other entries without a size or
+/// modification time go last.
+pub fn sort_results(results: &mut FilteredResults, tree: &DirectoryTree, order: SortOrder) {
+    let mut rows: Vec<usize> = (0..results.visible_items.len()).collect();
+    let node = |row: &usize| &tree.nodes[results.visible_items[*row]];
+    match order {
+        SortOrder::Relevance => return,
+        SortOrder::Path => rows.sort_by_cached_key(|row| tree.relative_path(&node(row).path)),
+        SortOrder::Size => rows.sort_by_key(|row| std::cmp::Reverse(node(row).size)),
+        SortOrder::Modified => rows.sort_by_key(|row| std::cmp::Reverse(node(row).modified)),
+    }
+
+    results.visible_items = rows.iter().map(|&row| results.visible_items[row]).collect();
+    if results.matches.len() == rows.len() {
+        results.matches = rows.iter().map(|&row| results.matches[row].clone()).collect();
     }
 }
 
+/// Keep the rows whose selection state passes `filter`, each with its match
+pub fn filter_by_state(results: &mut FilteredResults, tree: &DirectoryTree, filter: StateFilter) {
+    if filter == StateFilter::All {
+        return;
+    }
+
+    let keep: Vec<bool> = results
+        .visible_items
+        .iter()
+        .map(|&tree_index| filter.shows(tree.nodes[tree_index].state))
+        .collect();
+    retain_rows(&mut results.visible_items, &keep);
+    retain_rows(&mut results.matches, &keep);
+    retain_rows(&mut results.content_matches, &keep);
+}
+
+/// Keep the items of a row-parallel vector whose row is kept; other vectors are left alone
+fn retain_rows<T>(items: &mut Vec<T>, keep: &[bool]) {
+    if items.len() == keep.len() {
+        let mut rows = keep.iter();
+        items.retain(|_| rows.next().copied().unwrap_or(false));
+    }
+}
+
+fn regex_error_summary(error: &regex::Error) -> String {
+    let message = error.to_string();
+    message
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim_start_matches("error: ")
+        .to_string()
+}
+
+/// Rows of the hierarchical view: each node followed by its children while it is expanded
+pub fn flatten_tree(tree: &DirectoryTree, expanded: &HashSet<usize>) -> FilteredResults {
+    let mut results = FilteredResults::new();
+    let mut stack = vec![tree.root_index];
+    while let Some(index) = stack.pop() {
+        let Some(node) = tree.get_node(index) else {
+            continue;
+        };
+        if !node.is_directory && !node.is_text_file {
+            continue;
+        }
+        results
+            .matches
+            .push(MatchResult::new(0, Vec::new(), results.visible_items.len()));
+        results.visible_items.push(index);
+        if node.is_directory && expanded.contains(&index) {
+            stack.extend(node.children.iter().rev());
+        }
+    }
+    results
+}
+
+/// Number of directories between the root and `node_index`
+pub fn node_depth(tree: &DirectoryTree, node_index: usize) -> usize {
+    let mut depth = 0;
+    let mut current = tree.get_node(node_index).and_then(|node| node.parent);
+    while let Some(parent) = current {
+        depth += 1;
+        current = tree.get_node(parent).and_then(|node| node.parent);
+    }
+    depth
+}
+
 pub fn get_node_display_path(tree: &DirectoryTree, node_index: usize) -> String {
     if let Some(node) = tree.get_node(node_index) {
-        if let Ok(relative_path) = node.path.strip_prefix(&tree.nodes[tree.root_index].path) {
-            relative_path.to_string_lossy().to_string()
-        } else {
-            node.name.clone()
-        }
+        tree.relative_path(&node.path).to_string_lossy().to_string()
     } else {
         String::new()
     }
@@ -78,15 +402,167 @@ pub fn get_node_display_path(tree: &DirectoryTree, node_index: usize) -> String
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_flatten_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+        std::fs::create_dir(root_path.join("src")).unwrap();
+        std::fs::write(root_path.join("src").join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root_path.join("README.md"), "# Readme").unwrap();
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        let src = tree.add_node(root_path.join("src"), true, root_path).unwrap();
+        let main = tree
+            .add_node(root_path.join("src").join("main.rs"), false, &root_path.join("src"))
+            .unwrap();
+        let readme = tree.add_node(root_path.join("README.md"), false, root_path).unwrap();
+
+        let mut expanded = HashSet::from([tree.root_index]);
+        let rows = flatten_tree(&tree, &expanded).visible_items;
+        assert_eq!(rows, vec![tree.root_index, src, readme]);
+
+        expanded.insert(src);
+        let rows = flatten_tree(&tree, &expanded).visible_items;
+        assert_eq!(rows, vec![tree.root_index, src, main, readme]);
+        assert_eq!(node_depth(&tree, main), 2);
+    }
+
     #[test]
     fn test_filter_empty_query() {
         let temp_dir = TempDir::new().unwrap();
         let tree = DirectoryTree::new(temp_dir.path().to_path_buf());
 
-        let results = filter_tree_nodes(&tree, "");
+        let results = filter_tree_nodes(&tree, "", SearchMode::Fuzzy, &FuzzySearch::new());
         assert_eq!(results.len(), 1); // Should include the root directory
     }
+
+    #[test]
+    fn test_filter_substring() {
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        let app_rs = tree.add_node(root_path.join("App.rs"), false, root_path).unwrap();
+        tree.add_node(root_path.join("a_p_p.rs"), false, root_path);
+
+        let results = filter_tree_nodes(&tree, "app", SearchMode::Substring, &FuzzySearch::new());
+        assert_eq!(results.visible_items, vec![app_rs]);
+        assert_eq!(results.matches[0].indices, vec![0, 1, 2]);
+
+        // Regex syntax is matched literally
+        let results = filter_tree_nodes(&tree, "a.p", SearchMode::Substring, &FuzzySearch::new());
+        assert!(results.visible_items.is_empty());
+        assert!(results.error.is_none());
+    }
+
+    #[test]
+    fn test_filter_invalid_regex_shows_all() {
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("src"), true, root_path);
+
+        let results = filter_tree_nodes(&tree, "src(", SearchMode::Regex, &FuzzySearch::new());
+        assert_eq!(results.len(), 2);
+        assert!(results.error.is_some());
+
+        let results = filter_tree_nodes(&tree, "^src$", SearchMode::Regex, &FuzzySearch::new());
+        assert_eq!(results.visible_items, vec![1]);
+        assert!(results.error.is_none());
+    }
+
+    #[test]
+    fn test_negative_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("main.rs"), false, root_path);
+        tree.add_node(root_path.join("tests"), true, root_path);
+        tree.add_node(root_path.join("tests").join("cli.rs"), false, root_path);
+        tree.add_node(root_path.join("util_Test.rs"), false, root_path);
+        for node in &mut tree.nodes {
+            node.is_text_file = true;
+        }
+
+        let fuzzy_search = FuzzySearch::new();
+        assert_eq!(parse_query("rs !test !", &fuzzy_search), (vec!["rs"], vec!["test"]));
+
+        let results = filter_tree_nodes(&tree, "rs !test", SearchMode::Fuzzy, &fuzzy_search);
+        let paths: Vec<String> = results
+            .visible_items
+            .iter()
+            .map(|&index| get_node_display_path(&tree, index))
+            .collect();
+        assert_eq!(paths, vec!["main.rs"]);
+        assert_eq!(results.exclusions, vec!["test"]);
+
+        // Exclusions alone filter the full list
+        let results = filter_tree_nodes(&tree, "!rs", SearchMode::Fuzzy, &fuzzy_search);
+        assert!(results.visible_items.iter().all(|&index| {
+            !get_node_display_path(&tree, index).ends_with(".rs")
+        }));
+    }
+
+    #[test]
+    fn test_boost_filename_matches() {
+        let texts = vec![
+            "domain/something/maintainer.rs".to_string(),
+            "src/main.rs".to_string(),
+        ];
+        let mut matches = vec![
+            MatchResult::new(100, vec![2, 3, 4, 5], 0),
+            MatchResult::new(100, vec![4, 5, 6, 7], 1),
+        ];
+
+        boost_filename_matches(&mut matches, &texts, 1.5);
+        assert_eq!(matches[0].item_index, 1);
+        assert_eq!(matches[0].score, 150);
+        assert_eq!(matches[1].score, 100);
+
+        // A boost of 1 leaves scores and order alone
+        let mut matches = vec![
+            MatchResult::new(100, vec![2, 3, 4, 5], 0),
+            MatchResult::new(100, vec![4, 5, 6, 7], 1),
+        ];
+        boost_filename_matches(&mut matches, &texts, 1.0);
+        assert_eq!(matches[0].item_index, 0);
+    }
+
+    #[test]
+    fn test_match_indices_follow_sorted_rows() {
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("app_config"), true, root_path);
+        tree.add_node(root_path.join("config"), true, root_path);
+
+        // Rows are sorted by score, and each row keeps its own indices
+        let results = filter_tree_nodes(&tree, "config", SearchMode::Fuzzy, &FuzzySearch::new());
+        assert_eq!(results.len(), 2);
+        for row in 0..results.len() {
+            let path = get_node_display_path(&tree, results.visible_items[row]);
+            let matched: String = results
+                .match_indices(row)
+                .iter()
+                .map(|&index| path.chars().nth(index).unwrap())
+                .collect();
+            assert_eq!(matched, "config");
+        }
+        assert!(results.match_indices(2).is_empty());
+    }
+
+    #[test]
+    fn test_highlighted_spans() {
+        let mut results = FilteredResults::new();
+        results.matches = vec![
+            MatchResult::new(100, vec![11, 4, 5, 12, 13], 0),
+            MatchResult::new(100, Vec::new(), 1),
+        ];
+        results.visible_items = vec![0, 1];
+
+        assert_eq!(results.highlighted_spans(0), vec![(4, 6), (11, 14)]);
+        assert!(results.highlighted_spans(1).is_empty());
+        assert!(results.highlighted_spans(2).is_empty());
+    }
 }
\ No newline at end of file
@@ -1,7 +1,37 @@
+use crate::config::settings::Settings;
+use crate::constants::DEFAULT_FILENAME_MATCH_BOOST;
+use anyhow::{Result, bail};
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use regex::Regex;
+use std::str::FromStr;
+
+/// Fuzzy matching backend, set with the `fuzzy_scorer` setting
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FuzzyScorer {
+    /// `SkimMatcherV2` from `fuzzy-matcher`
+    #[default]
+    Skim,
+    /// `nucleo-matcher`, as used by Helix; needs the `nucleo` feature
+    Nucleo,
+}
+
+impl FromStr for FuzzyScorer {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "skim" => Ok(FuzzyScorer::Skim),
+            "nucleo" => Ok(FuzzyScorer::Nucleo),
+            _ => bail!("Unknown fuzzy scorer \"{}\" (expected \"skim\" or \"nucleo\")", name),
+        }
+    }
+}
 
 pub struct FuzzySearch {
-    matcher: SkimMatcherV2,
+    matcher: Box<dyn FuzzyMatcher>,
+    scorer: FuzzyScorer,
+    filename_boost: f64,
+    multi_query_delimiter: Option<char>,
 }
 
 impl Default for FuzzySearch {
@@ -13,7 +43,86 @@ impl Default for FuzzySearch {
 impl FuzzySearch {
     pub fn new() -> Self {
         Self {
-            matcher: SkimMatcherV2::default(),
+            matcher: Box::new(SkimMatcherV2::default()),
+            scorer: FuzzyScorer::Skim,
+            filename_boost: DEFAULT_FILENAME_MATCH_BOOST,
+            multi_query_delimiter: Some(' '),
+        }
+    }
+
+    /// Use `scorer`; without the `nucleo` feature, `Nucleo` falls back to skim
+    pub fn with_scorer(mut self, scorer: FuzzyScorer) -> Self {
+        match scorer {
+            FuzzyScorer::Skim => {
+                self.matcher = Box::new(SkimMatcherV2::default());
+                self.scorer = FuzzyScorer::Skim;
+            }
+            #[cfg(feature = "nucleo")]
+            FuzzyScorer::Nucleo => {
+                self.matcher = Box::new(super::nucleo::NucleoMatcher::default());
+                self.scorer = FuzzyScorer::Nucleo;
+            }
+            #[cfg(not(feature = "nucleo"))]
+            FuzzyScorer::Nucleo => {}
+        }
+        self
+    }
+
+    /// Backend selected by the `fuzzy_scorer` setting, warning about unusable values
+    pub fn from_settings(settings: &Settings) -> Self {
+        let scorer = match settings.fuzzy_scorer.parse() {
+            Ok(scorer) => scorer,
+            Err(e) => {
+                crate::warn!("{}; using skim", e);
+                FuzzyScorer::Skim
+            }
+        };
+        let search = Self::new()
+            .with_scorer(scorer)
+            .with_filename_boost(settings.score_boost_filename_match)
+            .with_multi_query_delimiter(
+                settings
+                    .use_multi_query
+                    .then_some(settings.multi_query_delimiter),
+            );
+        if search.scorer != scorer {
+            crate::warn!(
+                "fuzzy_scorer = \"nucleo\" needs gthr built with the `nucleo` feature; using skim"
+            );
+        }
+        search
+    }
+
+    /// Backend actually in use
+    pub fn scorer(&self) -> FuzzyScorer {
+        self.scorer
+    }
+
+    /// Multiply the score of path matches that lie entirely in the file name by `boost`
+    pub fn with_filename_boost(mut self, boost: f64) -> Self {
+        self.filename_boost = boost;
+        self
+    }
+
+    pub fn filename_boost(&self) -> f64 {
+        self.filename_boost
+    }
+
+    /// Split queries on `delimiter` into terms that must all match; `None` searches the
+    /// query as a whole
+    pub fn with_multi_query_delimiter(mut self, delimiter: Option<char>) -> Self {
+        self.multi_query_delimiter = delimiter;
+        self
+    }
+
+    /// Non-empty terms of `query`, or the whole query when multi-query search is off
+    pub fn split_query<'a>(&self, query: &'a str) -> Vec<&'a str> {
+        match self.multi_query_delimiter {
+            Some(delimiter) => query
+                .split(delimiter)
+                .filter(|term| !term.trim().is_empty())
+                .collect(),
+            None => vec![query],
         }
     }
 
@@ -24,6 +133,65 @@ impl FuzzySearch {
     pub fn search_score(&self, query: &str, text: &str) -> Option<i64> {
         self.matcher.fuzzy_match(text, query)
     }
+
+    /// Items matching `query`, best score first; an empty query keeps every item in order
+    pub fn search_items<T, F>(&self, items: &[T], query: &str, extract_text: F) -> Vec<MatchResult>
+    where
+        F: Fn(&T) -> &str,
+    {
+        if query.is_empty() {
+            return (0..items.len())
+                .map(|i| MatchResult::new(0, Vec::new(), i))
+                .collect();
+        }
+
+        let mut results = Vec::new();
+
+        for (index, item) in items.iter().enumerate() {
+            let text = extract_text(item);
+            if let Some((score, indices)) = self.search(query, text) {
+                results.push(MatchResult::new(score, indices, index));
+            }
+        }
+
+        // Sort by score (descending)
+        results.sort_by_key(|result| std::cmp::Reverse(result.score));
+        results
+    }
+
+    /// Items matching every term, scored by the sum of the term scores, best first
+    ///
+    /// Indices are the union of each term's indices, for highlighting.
+    pub fn search_items_all<T, F>(
+        &self,
+        items: &[T],
+        terms: &[&str],
+        extract_text: F,
+    ) -> Vec<MatchResult>
+    where
+        F: Fn(&T) -> &str,
+    {
+        let mut results = Vec::new();
+
+        'items: for (index, item) in items.iter().enumerate() {
+            let text = extract_text(item);
+            let mut total_score = 0;
+            let mut all_indices = Vec::new();
+            for term in terms {
+                let Some((score, indices)) = self.search(term, text) else {
+                    continue 'items;
+                };
+                total_score += score;
+                all_indices.extend(indices);
+            }
+            all_indices.sort_unstable();
+            all_indices.dedup();
+            results.push(MatchResult::new(total_score, all_indices, index));
+        }
+
+        results.sort_by_key(|result| std::cmp::Reverse(result.score));
+        results
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +211,7 @@ impl MatchResult {
     }
 }
 
+/// `FuzzySearch::search_items` with the default skim scorer
 pub fn search_items<T, F>(
     items: &[T],
     query: &str,
@@ -51,25 +220,52 @@ pub fn search_items<T, F>(
 where
     F: Fn(&T) -> &str,
 {
-    if query.is_empty() {
-        return (0..items.len())
-            .map(|i| MatchResult::new(0, Vec::new(), i))
-            .collect();
-    }
+    FuzzySearch::new().search_items(items, query, extract_text)
+}
 
-    let fuzzy_search = FuzzySearch::new();
-    let mut results = Vec::new();
+/// Keep items matching `regex`, in their original order
+///
+/// Every match scores 1 so results keep filesystem order; indices cover the first match.
+pub fn search_items_regex<T, F>(items: &[T], regex: &Regex, extract_text: F) -> Vec<MatchResult>
+where
+    F: Fn(&T) -> &str,
+{
+    items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let text = extract_text(item);
+            regex
+                .find(text)
+                .map(|found| MatchResult::new(1, char_indices(text, found.range()), index))
+        })
+        .collect()
+}
 
-    for (index, item) in items.iter().enumerate() {
-        let text = extract_text(item);
-        if let Some((score, indices)) = fuzzy_search.search(query, text) {
-            results.push(MatchResult::new(score, indices, index));
-        }
-    }
+/// Keep items containing `query` verbatim, in their original order
+pub fn search_items_exact<T, F>(items: &[T], query: &str, extract_text: F) -> Vec<MatchResult>
+where
+    F: Fn(&T) -> &str,
+{
+    items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let text = extract_text(item);
+            text.find(query).map(|start| {
+                MatchResult::new(1, char_indices(text, start..start + query.len()), index)
+            })
+        })
+        .collect()
+}
 
-    // Sort by score (descending)
-    results.sort_by(|a, b| b.score.cmp(&a.score));
-    results
+/// Character positions covered by a byte range, matching the fuzzy matcher's indices
+fn char_indices(text: &str, range: std::ops::Range<usize>) -> Vec<usize> {
+    text.char_indices()
+        .enumerate()
+        .filter(|(_, (byte_index, _))| range.contains(byte_index))
+        .map(|(char_index, _)| char_index)
+        .collect()
 }
 
 #[cfg(test)]
@@ -88,6 +284,49 @@ mod tests {
         assert_eq!(indices, vec![5, 6]);
     }
 
+    #[test]
+    fn test_fuzzy_scorer() {
+        assert_eq!("skim".parse::<FuzzyScorer>().unwrap(), FuzzyScorer::Skim);
+        assert_eq!("Nucleo".parse::<FuzzyScorer>().unwrap(), FuzzyScorer::Nucleo);
+        assert!("fzf".parse::<FuzzyScorer>().is_err());
+
+        let fuzzy = FuzzySearch::new().with_scorer(FuzzyScorer::Nucleo);
+        let expected = if cfg!(feature = "nucleo") {
+            FuzzyScorer::Nucleo
+        } else {
+            FuzzyScorer::Skim
+        };
+        assert_eq!(fuzzy.scorer(), expected);
+
+        // Either backend reports character positions of the match
+        let (score, indices) = fuzzy.search("mrs", "src/main.rs").unwrap();
+        assert!(score > 0);
+        assert_eq!(indices.len(), 3);
+        assert!(fuzzy.search("xyz", "src/main.rs").is_none());
+    }
+
+    #[test]
+    fn test_search_items_all() {
+        let fuzzy = FuzzySearch::new();
+        let items = vec!["src/main.rs", "main.py", "lib.rs"];
+        let terms = fuzzy.split_query("main  rs");
+        assert_eq!(terms, vec!["main", "rs"]);
+
+        let results = fuzzy.search_items_all(&items, &terms, |item| item);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item_index, 0);
+        assert_eq!(results[0].indices, vec![4, 5, 6, 7, 9, 10]);
+        let main_score = fuzzy.search_score("main", "src/main.rs").unwrap();
+        let rs_score = fuzzy.search_score("rs", "src/main.rs").unwrap();
+        assert_eq!(results[0].score, main_score + rs_score);
+
+        // Other delimiters keep spaces searchable
+        let fuzzy = FuzzySearch::new().with_multi_query_delimiter(Some('|'));
+        assert_eq!(fuzzy.split_query("my file|rs"), vec!["my file", "rs"]);
+        let fuzzy = FuzzySearch::new().with_multi_query_delimiter(None);
+        assert_eq!(fuzzy.split_query("main rs"), vec!["main rs"]);
+    }
+
     #[test]
     fn test_search_items() {
         let items = vec!["main.rs", "lib.rs", "config.toml", "README.md"];
@@ -96,4 +335,33 @@ mod tests {
         assert_eq!(results.len(), 2);
         assert!(results[0].score >= results[1].score);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_search_items_regex() {
+        let items = vec![
+            "src/api/user_controller.rs",
+            "src/main.rs",
+            "src/api/routes.rs",
+        ];
+        let regex = Regex::new(r"^src/api/.*controller\.rs$").unwrap();
+        let results = search_items_regex(&items, &regex, |item| item);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item_index, 0);
+        assert_eq!(results[0].score, 1);
+
+        let regex = Regex::new(r"\.rs$").unwrap();
+        let results = search_items_regex(&items, &regex, |item| item);
+        let order: Vec<usize> = results.iter().map(|result| result.item_index).collect();
+        assert_eq!(order, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_search_items_exact() {
+        let items = vec!["main.rs", "domain.rs", "README.md"];
+        let results = search_items_exact(&items, "main", |item| item);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].indices, vec![2, 3, 4, 5]);
+    }
+}
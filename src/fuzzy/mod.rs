@@ -1,3 +1,6 @@
+pub mod content;
 pub mod matcher;
 pub mod filter;
+#[cfg(feature = "nucleo")]
+pub mod nucleo;
 
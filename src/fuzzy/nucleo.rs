@@ -0,0 +1,49 @@
+use fuzzy_matcher::FuzzyMatcher;
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::{Config, Matcher, Utf32Str};
+use std::sync::Mutex;
+
+/// `nucleo-matcher` behind the `fuzzy_matcher` interface, configured for paths
+pub struct NucleoMatcher {
+    // Nucleo scores through `&mut Matcher`, which reuses its scratch buffers
+    matcher: Mutex<Matcher>,
+}
+
+impl Default for NucleoMatcher {
+    fn default() -> Self {
+        Self {
+            matcher: Mutex::new(Matcher::new(Config::DEFAULT.match_paths())),
+        }
+    }
+}
+
+impl FuzzyMatcher for NucleoMatcher {
+    fn fuzzy_indices(&self, choice: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        let pattern = Pattern::parse(pattern, CaseMatching::Smart, Normalization::Smart);
+        let mut matcher = self.matcher.lock().unwrap_or_else(|e| e.into_inner());
+        let mut buffer = Vec::new();
+        let mut indices = Vec::new();
+        let score = pattern.indices(
+            Utf32Str::new(choice, &mut buffer),
+            &mut matcher,
+            &mut indices,
+        )?;
+
+        // Indices of several whitespace-separated atoms arrive unsorted and may overlap
+        indices.sort_unstable();
+        indices.dedup();
+        Some((
+            score as i64,
+            indices.into_iter().map(|index| index as usize).collect(),
+        ))
+    }
+
+    fn fuzzy_match(&self, choice: &str, pattern: &str) -> Option<i64> {
+        let pattern = Pattern::parse(pattern, CaseMatching::Smart, Normalization::Smart);
+        let mut matcher = self.matcher.lock().unwrap_or_else(|e| e.into_inner());
+        let mut buffer = Vec::new();
+        pattern
+            .score(Utf32Str::new(choice, &mut buffer), &mut matcher)
+            .map(|score| score as i64)
+    }
+}
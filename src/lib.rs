@@ -0,0 +1,53 @@
+//! Interactive directory text ingestion for LLM context.
+//!
+//! The `gthr` binary is a thin wrapper around this library, so the same traversal and
+//! output pipeline can be used programmatically:
+//!
+//! ```no_run
+//! use gthr::{DirectoryTraverser, OutputFormatter, SelectionState};
+//! use std::path::Path;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let traverser = DirectoryTraverser::new(true, false, 2 * 1024 * 1024, SelectionState::Included);
+//! let tree = traverser.traverse(Path::new("."))?;
+//! let markdown = OutputFormatter::new().format_output(&tree)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cli;
+pub mod config;
+pub mod constants;
+pub mod directory;
+pub mod error;
+pub mod fuzzy;
+pub mod output;
+pub mod ui;
+pub mod util;
+pub mod verbosity;
+pub mod watch;
+
+pub use config::settings::Settings;
+pub use directory::builder::build_directory_tree;
+pub use directory::patterns::apply_patterns;
+pub use directory::state::SelectionState;
+pub use directory::traversal::{DirectoryTraverser, TraversalDecision};
+pub use directory::tree::{DirectoryTree, FileNode};
+pub use output::formatter::OutputFormatter;
+pub use output::writer::OutputWriter;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_pipeline_types_are_send_sync() {
+        assert_send_sync::<DirectoryTree>();
+        assert_send_sync::<DirectoryTraverser>();
+        assert_send_sync::<OutputFormatter>();
+        assert_send_sync::<OutputWriter>();
+        assert_send_sync::<Settings>();
+    }
+}
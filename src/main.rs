@@ -1,52 +1,147 @@
-mod cli;
-mod config;
-mod constants;
-mod directory;
-mod fuzzy;
-mod output;
-mod ui;
-
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use clap::Parser;
-use cli::{Cli, Commands};
-use config::settings::Settings;
-use constants::DEFAULT_MAX_FILE_SIZE;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use directory::traversal::DirectoryTraverser;
-use output::formatter::OutputFormatter;
-use output::writer::OutputWriter;
+use gthr::cli::{
+    Cli, Commands, ConfigAction, OpenWith, ProfilesAction, StatsFormat, generate_completions,
+};
+use gthr::config::settings::Settings;
+use gthr::constants::SELECTION_FILENAME;
+use gthr::directory::builder::{
+    build_directory_tree, build_directory_tree_with_gitignore, respect_gitignore,
+};
+use gthr::directory::cache::{cache_file, temp_path};
+use gthr::directory::diff::TreeDiff;
+use gthr::error::{EXIT_FAILURE, GthrError, is_broken_pipe};
+use gthr::fuzzy::matcher::FuzzySearch;
+use gthr::output::encoding::EncodingFallback;
+use gthr::output::formatter::{
+    OutputFormatter, format_file_list, format_path_list, format_styled_path_list,
+};
+use gthr::output::stats::{StatsReport, TokenReport};
+use gthr::output::writer::{
+    ClipboardBackend, OutputWriter, OverwriteMode, try_write_to_clipboard_with_fallbacks,
+};
+use gthr::ui::app::{App, AppMode};
+use gthr::ui::events::{AppAction, AppEvent, EventHandler, handle_key_event, handle_mouse_event};
+use gthr::ui::interface::draw_ui;
+use gthr::warn;
+use gthr::watch::ChangeWatcher;
+use gthr::{DirectoryTree, FileNode, SelectionState};
 use ratatui::{
     Terminal,
     backend::{Backend, CrosstermBackend},
 };
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use ui::app::{App, AppMode};
-use ui::events::{AppAction, AppEvent, EventHandler, handle_key_event};
-use ui::interface::draw_ui;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let settings = Settings::load_with_project_root(&cli.root);
+async fn main() {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        // clap exits with 2 on usage errors, which would read as an empty selection
+        Err(e) if e.use_stderr() => {
+            let _ = e.print();
+            std::process::exit(EXIT_FAILURE);
+        }
+        // --help and --version
+        Err(e) => e.exit(),
+    };
+    cli.verbosity().set_global();
+    if let Err(e) = run(&cli).await {
+        // A reader like `head` closed stdout after reading all it wanted
+        if is_broken_pipe(&e) {
+            std::process::exit(0);
+        }
+        // Quitting is a choice, so only the exit code reports it
+        if !matches!(e.downcast_ref(), Some(GthrError::Cancelled)) {
+            eprintln!("Error: {:?}", e);
+        }
+        std::process::exit(GthrError::exit_code_of(&e));
+    }
+}
+
+async fn run(cli: &Cli) -> Result<()> {
+    let mut settings = Settings::load_with_project_root(cli.project_root());
+    if let Some(profile) = &cli.profile {
+        settings.apply_profile(profile)?;
+    }
 
     match cli.command.as_ref().unwrap_or(&Commands::Interactive) {
         Commands::Interactive => {
-            run_interactive_mode(&cli, &settings).await?;
+            // Build the formatter up front so template errors are reported at startup
+            let formatter = build_formatter(cli, &settings)?;
+            check_output_dir(cli, &settings);
+            run_interactive_mode(cli, &settings, &formatter).await?;
         }
         Commands::Direct => {
-            run_direct_mode(&cli, &settings).await?;
+            let formatter = build_formatter(cli, &settings)?;
+            check_output_dir(cli, &settings);
+            run_direct_mode(cli, &settings, &formatter).await?;
+        }
+        Commands::Stats { format } => {
+            run_stats_mode(cli, &settings, *format)?;
+        }
+        Commands::List { long, null } => {
+            let tree = build_directory_tree(cli, &settings)?;
+            check_not_empty(cli, &tree)?;
+            print_stdout(&format_file_list(&tree, *long, *null || cli.print0))?;
+        }
+        Commands::Tokens { top } => {
+            run_tokens_mode(cli, &settings, *top)?;
+        }
+        Commands::Config { action } => {
+            run_config_command(cli, &settings, action)?;
+        }
+        Commands::Completions { shell } => {
+            generate_completions(*shell, &mut io::stdout());
         }
     }
 
     Ok(())
 }
 
-async fn run_interactive_mode(cli: &Cli, settings: &Settings) -> Result<()> {
+async fn run_interactive_mode(
+    cli: &Cli,
+    settings: &Settings,
+    formatter: &OutputFormatter,
+) -> Result<()> {
+    // Create application state before entering raw mode so traversal warnings print cleanly
+    let mut tree = build_directory_tree(cli, settings)?;
+    // Printed before the alternate screen, so it is still visible after quitting
+    print_session_diff(cli, formatter, &tree)?;
+    let session_path = session_path(cli, settings);
+    // Patterns, git flags or --load-selection given on the command line take precedence over
+    // the last session
+    if !cli.sets_selection() && session_path.exists() {
+        App::load_session(&session_path, &mut tree)?;
+    }
+    let mut app = App::new(tree)
+        .with_query(cli.query.clone().unwrap_or_default())
+        .with_output_dir(settings.output_dir(cli.project_root()))
+        .with_output_path(cli.output.clone())
+        .with_export_paths(cli.export_paths.clone())
+        .with_path_style(formatter.path_style())
+        .with_max_depth(cli.max_depth.or(settings.max_depth))
+        .with_max_total_size_warning(settings.max_total_size_warning)
+        .with_token_warn_threshold(settings.token_warn_threshold)
+        .with_no_tree(formatter.show_tree() == Some(false))
+        .with_respect_gitignore(respect_gitignore(cli, settings))
+        .with_profile(cli.profile.clone())
+        .with_fuzzy_search(FuzzySearch::from_settings(settings))
+        .with_settings(
+            settings.clone(),
+            Settings::get_project_config_path(cli.project_root()),
+        );
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -54,12 +149,16 @@ async fn run_interactive_mode(cli: &Cli, settings: &Settings) -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create application state
-    let tree = build_directory_tree(cli, settings)?;
-    let mut app = App::new(tree);
-
     let event_handler = EventHandler::new();
-    let result = run_app(&mut terminal, &mut app, &event_handler, cli, settings).await;
+    let result = run_app(
+        &mut terminal,
+        &mut app,
+        &event_handler,
+        cli,
+        formatter,
+        &session_path,
+    )
+    .await;
 
     // Restore terminal
     disable_raw_mode()?;
@@ -69,8 +168,30 @@ async fn run_interactive_mode(cli: &Cli, settings: &Settings) -> Result<()> {
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    result?;
+    if !app.exported {
+        return Err(GthrError::Cancelled.into());
+    }
 
-    result
+    // Piped output is sent once the terminal is restored, so the command can use it
+    if let Some(command) = &cli.pipe
+        && let Some(content) = app.pending_content.take()
+    {
+        pipe_output(command, &content)?;
+    } else if cli.print0
+        && cli.copy_path
+        && let Some(content) = app.pending_content.take()
+    {
+        print_stdout(&content)?;
+    }
+
+    // Viewers and editors need the terminal, so they too wait until it is restored
+    if let Some(open_with) = cli.open
+        && let Some(path) = app.saved_path.take()
+    {
+        open_output(&path, open_with);
+    }
+    Ok(())
 }
 
 async fn run_app<B: Backend>(
@@ -78,9 +199,14 @@ async fn run_app<B: Backend>(
     app: &mut App,
     event_handler: &EventHandler,
     cli: &Cli,
-    settings: &Settings,
+    formatter: &OutputFormatter,
+    session_path: &Path,
 ) -> Result<()> {
+    // Rebuilt when the settings panel changes the settings
+    let mut formatter = formatter.clone();
     loop {
+        app.poll_content_search();
+        app.poll_line_counts();
         terminal.draw(|f| draw_ui(f, app))?;
 
         if app.should_quit {
@@ -88,44 +214,159 @@ async fn run_app<B: Backend>(
         }
 
         if let Some(event) = event_handler.next_event(Duration::from_millis(50))? {
-            match event {
+            let action = match event {
                 AppEvent::Key(key_event) => {
                     if app.mode == AppMode::Help {
-                        app.set_mode(AppMode::Main);
+                        app.close_help();
                         continue;
                     }
+                    handle_key_event(key_event, &app.mode, app.focus)
+                }
+                AppEvent::Mouse(mouse_event) => handle_mouse_event(mouse_event, &app.mode),
+                AppEvent::Tick => {
+                    app.expire_status_message();
+                    None
+                }
+                AppEvent::Quit => {
+                    app.quit();
+                    None
+                }
+            };
 
-                    if let Some(action) = handle_key_event(key_event, &app.mode) {
-                        match action {
-                            AppAction::Escape => app.handle_escape(),
-                            AppAction::Export => {
-                                handle_export(app, cli, settings)?;
+            // The command palette hands back the action it runs
+            let mut pending = action;
+            while let Some(action) = pending.take() {
+                match action {
+                    AppAction::Escape => app.handle_escape(),
+                    AppAction::Export => {
+                        handle_export(app, cli, &formatter, false)?;
+                    }
+                    AppAction::ExportAndQuit => {
+                        handle_export(app, cli, &formatter, true)?;
+                    }
+                    AppAction::ShowHelp => app.show_help(),
+                    AppAction::OpenSettings => app.open_settings(),
+                    AppAction::OpenCommandPalette => app.open_command_palette(),
+                    AppAction::RunCommand => pending = app.run_command(),
+                    AppAction::CommandPaletteChar(c) => app.command_palette_char(c),
+                    AppAction::CommandPaletteBackspace => app.command_palette_backspace(),
+                    AppAction::OpenSelectionSummary => app.open_selection_summary(),
+                    AppAction::SummaryJump => app.summary_jump(),
+                    AppAction::SummaryDeselect => app.summary_deselect(),
+                    AppAction::SettingsActivate => app.settings_activate(),
+                    AppAction::SettingsSave => app.save_settings(),
+                    AppAction::SettingsChar(c) => app.settings_char(c),
+                    AppAction::SettingsBackspace => app.settings_backspace(),
+                    AppAction::ToggleSelection => app.toggle_selection(),
+                    AppAction::SelectAll => app.select_all(),
+                    AppAction::SelectNone => app.select_none(),
+                    AppAction::InvertSelection => app.invert_selection(),
+                    AppAction::Undo => app.undo(),
+                    AppAction::Redo => app.redo(),
+                    AppAction::SaveSession => match app.save_session(session_path) {
+                        Ok(()) => app.set_status_message("[saved]"),
+                        Err(e) => app.set_status_message(format!("Failed to save session: {}", e)),
+                    },
+                    AppAction::CopyPaths => {
+                        match copy_path_list(&app.tree, cli.relative, &app.settings) {
+                            Ok(count) => app.set_status_message(format!(
+                                "Paths copied to clipboard ({})",
+                                count
+                            )),
+                            Err(e) => {
+                                app.set_status_message(format!("Failed to copy paths: {}", e))
+                            }
+                        }
+                    }
+                    AppAction::ExportPaths => {
+                        let path = app.path_list_path();
+                        match app.export_path_list(&path) {
+                            Ok(()) => {
+                                app.set_status_message(format!("Paths saved to {}", path.display()))
+                            }
+                            Err(e) => {
+                                app.set_status_message(format!("Failed to save paths: {}", e))
+                            }
+                        }
+                    }
+                    AppAction::SaveSelection => {
+                        let path = cli.project_root().join(SELECTION_FILENAME);
+                        match app.save_selection(&path) {
+                            Ok(()) => app.set_status_message(format!(
+                                "Selection saved to {}",
+                                path.display()
+                            )),
+                            Err(e) => {
+                                app.set_status_message(format!("Failed to save selection: {}", e))
                             }
-                            AppAction::ShowHelp => app.set_mode(AppMode::Help),
-                            AppAction::ToggleSelection => app.toggle_selection(),
-                            AppAction::MoveUp => app.move_up(),
-                            AppAction::MoveDown => app.move_down(),
-                            AppAction::PageUp => app.page_up(),
-                            AppAction::PageDown => app.page_down(),
-                            AppAction::MoveToTop => app.move_to_top(),
-                            AppAction::MoveToBottom => app.move_to_bottom(),
-                            AppAction::SearchChar(c) => app.add_search_char(c),
-                            AppAction::SearchBackspace => app.search_backspace(),
-                            AppAction::FileSaveChar(c) => app.add_file_save_char(c),
-                            AppAction::FileSaveBackspace => app.file_save_backspace(),
-                            AppAction::FileSaveConfirm => {
-                                if let Some(content) = &app.pending_content.clone() {
-                                    save_file_from_dialog(&app, content)?;
-                                    app.quit();
-                                }
+                        }
+                    }
+                    AppAction::OpenInEditor => open_in_editor(terminal, app)?,
+                    AppAction::TogglePreview => app.toggle_preview(),
+                    AppAction::ToggleFocus => app.toggle_focus(),
+                    AppAction::ToggleNoTree => app.toggle_no_tree(),
+                    AppAction::ToggleTreeView => app.toggle_tree_view(),
+                    AppAction::Rescan => rescan(terminal, app, cli, app.respect_gitignore)?,
+                    AppAction::ToggleGitignore => {
+                        rescan(terminal, app, cli, !app.respect_gitignore)?
+                    }
+                    AppAction::Collapse => app.collapse(),
+                    AppAction::Expand => app.expand(),
+                    AppAction::CycleSearchMode => app.cycle_search_mode(),
+                    AppAction::CycleSortOrder => app.cycle_sort_order(),
+                    AppAction::CycleStateFilter => app.cycle_state_filter(),
+                    AppAction::ToggleContentSearch => app.toggle_content_search(),
+                    AppAction::MoveUp => app.move_up(),
+                    AppAction::MoveDown => app.move_down(),
+                    AppAction::PageUp => app.page_up(),
+                    AppAction::PageDown => app.page_down(),
+                    AppAction::MoveToTop => app.move_to_top(),
+                    AppAction::MoveToBottom => app.move_to_bottom(),
+                    AppAction::ScrollUp => app.scroll(false),
+                    AppAction::ScrollDown => app.scroll(true),
+                    AppAction::Click(column, row) => app.click(column, row),
+                    AppAction::SearchChar(c) => app.add_search_char(c),
+                    AppAction::SearchBackspace => app.search_backspace(),
+                    AppAction::FileSaveChar(c) => app.add_file_save_char(c),
+                    AppAction::FileSaveBackspace => app.file_save_backspace(),
+                    AppAction::FileSaveConfirm => {
+                        if let Some(content) = app.pending_content.clone() {
+                            let path = app.file_save_path();
+                            let overwrite = cli.overwrite_mode();
+                            if overwrite == OverwriteMode::Refuse && path.exists() {
+                                app.confirm_overwrite(path);
+                            } else {
+                                save_output_file(&path, &content, overwrite, cli.quiet)?;
+                                app.exported = true;
+                                app.saved_path = Some(path);
+                                app.quit();
                             }
                         }
                     }
+                    AppAction::OverwriteConfirm => {
+                        if let (Some(path), Some(content)) =
+                            (app.overwrite_path.clone(), app.pending_content.clone())
+                        {
+                            save_output_file(&path, &content, OverwriteMode::Force, cli.quiet)?;
+                            app.exported = true;
+                            app.saved_path = Some(path);
+                            app.quit();
+                        }
+                    }
+                    AppAction::OverwriteCancel => app.cancel_overwrite(),
                 }
-                AppEvent::Tick => {
-                    // Handle periodic updates if needed
+            }
+
+            if app.take_settings_changed() {
+                match build_formatter(cli, &app.settings) {
+                    Ok(rebuilt) => {
+                        app.path_style = rebuilt.path_style();
+                        formatter = rebuilt;
+                    }
+                    Err(e) => {
+                        app.set_status_message(format!("Output settings not applied: {:#}", e))
+                    }
                 }
-                AppEvent::Quit => app.quit(),
             }
         }
     }
@@ -133,168 +374,494 @@ async fn run_app<B: Backend>(
     Ok(())
 }
 
-async fn run_direct_mode(cli: &Cli, settings: &Settings) -> Result<()> {
-    let tree = build_directory_tree(cli, settings)?;
-    handle_output(&tree, cli, settings, false)?;
+/// Suspend the TUI and edit the file under the cursor in `$EDITOR`
+///
+/// The terminal is restored however the editor exits; failures go to the status bar.
+fn open_in_editor<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    let Some(index) = app.get_selected_tree_index() else {
+        return Ok(());
+    };
+    let node = &app.tree.nodes[index];
+    if node.is_directory {
+        app.set_status_message("Only files can be opened in the editor");
+        return Ok(());
+    }
+    let editor = match std::env::var("EDITOR") {
+        Ok(editor) if !editor.trim().is_empty() => editor,
+        _ => {
+            app.set_status_message("$EDITOR is not set");
+            return Ok(());
+        }
+    };
+    let path = node.path.clone();
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    let status = OutputWriter::open_command(&path, Some(&editor)).status();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+
+    app.reload_file(index);
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => app.set_status_message(format!("{} exited with {}", editor, status)),
+        Err(e) => app.set_status_message(format!("Failed to run {}: {}", editor, e)),
+    }
     Ok(())
 }
 
-/// Build the directory tree with common logic for both modes
-fn build_directory_tree(cli: &Cli, settings: &Settings) -> Result<directory::tree::DirectoryTree> {
-    let max_file_size = if cli.max_file_size == DEFAULT_MAX_FILE_SIZE {
-        // If using default CLI value
-        settings.max_file_size // Use config file value
-    } else {
-        cli.max_file_size // Use explicitly set CLI value
-    };
-    let respect_gitignore = cli.respect_gitignore.unwrap_or(settings.respect_gitignore);
-    let show_hidden = cli.show_hidden.unwrap_or(settings.show_hidden);
-    let traverser = DirectoryTraverser::new(
-        respect_gitignore,
-        show_hidden,
-        max_file_size,
-        cli.include_all,
-    );
-    let mut tree = traverser.traverse(&cli.root)?;
+/// Rebuild the tree from disk, keeping the selection of files that are still there
+fn rescan<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    cli: &Cli,
+    respect_gitignore: bool,
+) -> Result<()> {
+    // Traversal blocks, so show that it is running first
+    app.set_status_message("Rescanning…");
+    terminal.draw(|f| draw_ui(f, app))?;
+    match build_directory_tree_with_gitignore(cli, &app.settings, respect_gitignore) {
+        Ok(tree) => {
+            app.respect_gitignore = respect_gitignore;
+            let (added, removed) = app.replace_tree(tree);
+            let noun = |count: usize| if count == 1 { "file" } else { "files" };
+            app.set_status_message(format!(
+                "Rescanned: +{} {}, -{} {}",
+                added,
+                noun(added),
+                removed,
+                noun(removed)
+            ));
+        }
+        Err(e) => app.set_status_message(format!("Rescan failed: {:#}", e)),
+    }
+    Ok(())
+}
+
+/// Create `default_output_dir` up front, warning when exports could not be saved there
+fn check_output_dir(cli: &Cli, settings: &Settings) {
+    if let Some(output_dir) = settings.output_dir(cli.project_root())
+        && let Err(e) = OutputWriter::ensure_writable_dir(&output_dir)
+    {
+        warn!(
+            "default_output_dir {} is not writable: {}",
+            output_dir.display(),
+            e
+        );
+    }
+}
 
-    // Apply include/exclude patterns if provided
-    if !cli.include.is_empty() || !cli.exclude.is_empty() {
-        apply_patterns(&mut tree, &cli.include, &cli.exclude);
+/// Resolve the session file: `--session`, else `default_output_dir`, else the temp dir
+fn session_path(cli: &Cli, settings: &Settings) -> PathBuf {
+    if let Some(path) = &cli.session {
+        return path.clone();
     }
 
-    Ok(tree)
+    let roots: Vec<PathBuf> = cli
+        .root
+        .iter()
+        .map(|root| root.canonicalize().unwrap_or_else(|_| root.clone()))
+        .collect();
+    match settings.output_dir(cli.project_root()) {
+        Some(output_dir) => output_dir.join(".gthr-session.json"),
+        None => {
+            // One session per set of roots, named after the first
+            let mut hasher = DefaultHasher::new();
+            roots.hash(&mut hasher);
+            let root_name = roots[0]
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "root".to_string());
+            std::env::temp_dir().join(format!(
+                "gthr-session-{}-{:016x}.json",
+                root_name,
+                hasher.finish()
+            ))
+        }
+    }
 }
 
-fn apply_patterns(
-    tree: &mut directory::tree::DirectoryTree,
-    include: &[String],
-    exclude: &[String],
-) {
-    use directory::state::SelectionState;
-
-    // If no include patterns are specified, include everything by default
-    let include_all = include.is_empty();
-
-    for i in 0..tree.nodes.len() {
-        if let Some(node) = tree.nodes.get(i) {
-            // Use relative path from the root for pattern matching
-            let relative_path = if let Some(root_node) = tree.nodes.get(tree.root_index) {
-                node.path
-                    .strip_prefix(&root_node.path)
-                    .unwrap_or(&node.path)
-                    .to_string_lossy()
-            } else {
-                node.path.to_string_lossy()
-            };
+async fn run_direct_mode(
+    cli: &Cli,
+    settings: &Settings,
+    formatter: &OutputFormatter,
+) -> Result<()> {
+    let tree = build_directory_tree(cli, settings)?;
+    print_session_diff(cli, formatter, &tree)?;
+    let action = handle_output(&tree, cli, settings, formatter, false)?;
+    if let Some(path) = &cli.export_paths {
+        let paths = format_styled_path_list(&tree, formatter.path_style());
+        OutputWriter::write_content(path, &paths, false)?;
+        if !cli.quiet {
+            eprintln!("✓ Paths saved to: {}", path.display());
+        }
+    }
+    if let OutputAction::Saved(path) = action
+        && let Some(open_with) = cli.open
+    {
+        open_output(&path, open_with);
+    }
+    if cli.watch {
+        watch_output(cli, settings, formatter)?;
+    }
+    Ok(())
+}
 
-            let mut should_include = include_all;
+/// `--watch`: rebuild the tree and rewrite the -o file after every change until Ctrl+C
+fn watch_output(cli: &Cli, settings: &Settings, formatter: &OutputFormatter) -> Result<()> {
+    let Some(output_path) = &cli.output else {
+        return Ok(());
+    };
 
-            // Check include patterns
-            for pattern in include {
-                if path_matches_pattern(&relative_path, pattern)
-                    || path_matches_pattern(&node.name, pattern)
-                {
-                    should_include = true;
-                    break;
-                }
-            }
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_on_ctrl_c = Arc::clone(&stop);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            stop_on_ctrl_c.store(true, Ordering::SeqCst);
+        }
+    });
 
-            // Check exclude patterns (these override includes)
-            for pattern in exclude {
-                if path_matches_pattern(&relative_path, pattern)
-                    || path_matches_pattern(&node.name, pattern)
-                {
-                    should_include = false;
-                    break;
-                }
-            }
+    let mut watcher = ChangeWatcher::new(&cli.root, cli.watch_interval.map(Duration::from_secs))?
+        .with_ignored_path(output_path);
+    // Saving the text detection cache would otherwise trigger another rebuild
+    let cache_path = settings.cache_path(cli.project_root());
+    for root in &cli.root {
+        let cache_file = cache_file(root, cache_path.as_deref());
+        watcher = watcher
+            .with_ignored_path(&temp_path(&cache_file))
+            .with_ignored_path(&cache_file);
+    }
+    if !cli.quiet {
+        eprintln!("Watching for changes (Ctrl+C to stop)...");
+    }
 
-            let new_state = if should_include {
-                SelectionState::Included
-            } else {
-                SelectionState::Excluded
-            };
+    let mut previous = std::fs::read_to_string(output_path).unwrap_or_default();
+    while watcher.wait_for_change(&stop)? {
+        // Files can vanish mid-traversal; keep watching and retry on the next change
+        let content = match build_directory_tree(cli, settings).and_then(|mut tree| {
+            exclude_output_file(&mut tree, output_path);
+            formatter.format_output(&tree)
+        }) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to regenerate output: {:#}", e);
+                continue;
+            }
+        };
+        if content == previous {
+            continue;
+        }
 
-            tree.set_state(i, new_state);
+        write_output(output_path, &content, false)?;
+        if !cli.quiet {
+            eprintln!("✓ Output updated: {}", output_path.display());
         }
+        previous = content;
     }
+    Ok(())
 }
 
-fn path_matches_pattern(path: &str, pattern: &str) -> bool {
-    // Simple glob-like matching
-    if pattern == "**/*" {
-        return true;
+/// Exclude the -o file when it lies inside a root, so `--watch` never nests its own output
+fn exclude_output_file(tree: &mut DirectoryTree, output_path: &Path) {
+    let Ok(output_path) = output_path.canonicalize() else {
+        return;
+    };
+    let index = tree.nodes.iter().position(|node| {
+        node.path
+            .canonicalize()
+            .is_ok_and(|path| path == output_path)
+    });
+    if let Some(index) = index {
+        tree.set_state(index, SelectionState::Excluded);
     }
+}
 
-    // Handle common patterns
-    if pattern.ends_with("*") {
-        let prefix = &pattern[..pattern.len() - 1];
-        return path.starts_with(prefix);
+/// `--diff-against`: print what changed since the session was saved to stderr
+fn print_session_diff(cli: &Cli, formatter: &OutputFormatter, tree: &DirectoryTree) -> Result<()> {
+    if let Some(session) = &cli.diff_against {
+        let before = App::session_fingerprints(session)
+            .with_context(|| format!("Failed to read session {}", session.display()))?;
+        let diff = TreeDiff::between(&before, &tree.fingerprints());
+        eprintln!("{}", formatter.format_diff(&diff));
     }
+    Ok(())
+}
 
-    if pattern.starts_with("*") {
-        let suffix = &pattern[1..];
-        return path.ends_with(suffix);
+fn run_tokens_mode(cli: &Cli, settings: &Settings, top: Option<usize>) -> Result<()> {
+    let tree = build_directory_tree(cli, settings)?;
+    check_not_empty(cli, &tree)?;
+    print_stdout(&TokenReport::from_tree(&tree, top).format_text())?;
+    Ok(())
+}
+
+/// `--fail-on-empty`: treat an empty selection as an error where it would otherwise print nothing
+fn check_not_empty(cli: &Cli, tree: &DirectoryTree) -> Result<()> {
+    if cli.fail_on_empty && tree.get_all_included_files().is_empty() {
+        return Err(GthrError::NoFilesIncluded.into());
     }
+    Ok(())
+}
 
-    // Convert glob pattern to regex-like matching
-    let regex_pattern = pattern
-        .replace(".", "\\.")
-        .replace("**", ".*")
-        .replace("*", "[^/]*")
-        .replace("?", ".");
+fn run_stats_mode(cli: &Cli, settings: &Settings, format: StatsFormat) -> Result<()> {
+    let tree = build_directory_tree(cli, settings)?;
+    check_not_empty(cli, &tree)?;
+    let report = StatsReport::from_tree(&tree);
 
-    if let Ok(regex) = regex::Regex::new(&format!("^{}$", regex_pattern)) {
-        regex.is_match(path)
-    } else {
-        // Fallback to simple equality check
-        path == pattern
+    match format {
+        StatsFormat::Text => print_stdout(&report.format_text())?,
+        StatsFormat::Json => print_stdout(&format!("{}\n", report.format_json()?))?,
     }
+    Ok(())
+}
+
+fn run_config_command(cli: &Cli, settings: &Settings, action: &ConfigAction) -> Result<()> {
+    use std::io::Write;
+
+    let mut out = io::stdout().lock();
+    let global_path = Settings::get_global_config_path();
+    let project_path = Settings::get_project_config_path(cli.project_root());
+    let describe = |path: &Path| {
+        let status = if path.exists() { "found" } else { "not found" };
+        format!("{} ({})", path.display(), status)
+    };
+
+    match action {
+        ConfigAction::Init { global, force } => {
+            let path = if *global { &global_path } else { &project_path };
+            Settings::write_default_config(path, *force)?;
+            if !cli.quiet {
+                eprintln!("✓ Config written to: {}", path.display());
+            }
+        }
+        ConfigAction::Show { format, diff } => {
+            let effective: Vec<_> = settings
+                .effective_settings(cli.project_root(), cli.profile.as_deref())?
+                .into_iter()
+                .filter(|setting| !diff || !setting.is_default)
+                .collect();
+
+            match format {
+                StatsFormat::Text => {
+                    writeln!(out, "# Global config:  {}", describe(&global_path))?;
+                    writeln!(out, "# Project config: {}", describe(&project_path))?;
+                    writeln!(out)?;
+                    for setting in &effective {
+                        match &setting.value {
+                            Some(value) => {
+                                writeln!(out, "{} = {}  # {}", setting.key, value, setting.source)?
+                            }
+                            None => writeln!(out, "# {} is not set", setting.key)?,
+                        }
+                    }
+                }
+                StatsFormat::Json => {
+                    let mut object = serde_json::Map::new();
+                    for setting in &effective {
+                        object.insert(
+                            setting.key.to_string(),
+                            serde_json::json!({
+                                "value": serde_json::to_value(&setting.value)?,
+                                "source": setting.source.to_string(),
+                            }),
+                        );
+                    }
+                    writeln!(out, "{}", serde_json::to_string_pretty(&object)?)?;
+                }
+            }
+        }
+        ConfigAction::Path => {
+            writeln!(out, "Global:  {}", describe(&global_path))?;
+            writeln!(out, "Project: {}", describe(&project_path))?;
+            if let Some(ignore_path) = Settings::get_global_ignore_path() {
+                writeln!(out, "Ignore:  {}", describe(&ignore_path))?;
+            }
+        }
+        ConfigAction::Profiles {
+            action: ProfilesAction::List,
+        } => {
+            let profiles = settings.profile_names();
+            if profiles.is_empty() && !cli.quiet {
+                eprintln!("No profiles defined; add a [profiles.NAME] section to a config");
+            }
+            for (name, extends) in profiles {
+                match extends {
+                    Some(parent) => writeln!(out, "{} (extends {})", name, parent)?,
+                    None => writeln!(out, "{}", name)?,
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
 enum OutputAction {
     Quit,
+    /// The output was written to this file
+    Saved(PathBuf),
     StartFileSave(String),
-    Continue,
+    Pipe(String),
+    /// Print to stdout once the terminal is restored
+    Print(String),
+    /// Ask in the interactive mode before replacing an existing file
+    ConfirmOverwrite(PathBuf, String),
+    /// Stay in the interactive mode and show a message in the status bar
+    Status(String),
+    /// The output of this many bytes was copied to the clipboard in the interactive mode
+    Copied(usize),
+}
+
+/// Build the output formatter, loading and compiling the template if one is configured
+fn build_formatter(cli: &Cli, settings: &Settings) -> Result<OutputFormatter> {
+    let mut formatter = OutputFormatter::new()
+        .with_format(cli.format)
+        .with_metadata(settings.include_metadata.unwrap_or(false))
+        .with_line_numbers(false)
+        .with_strip_comments(cli.strip_comments || settings.strip_comments)
+        .with_skip_empty_files(cli.skip_empty || settings.skip_empty_files)
+        .with_hash_files(settings.hash_files)
+        .with_encoding_fallbacks(EncodingFallback::parse_list(&settings.encoding_fallbacks))
+        .with_summary_only(cli.summary_only)
+        .with_tree_only(cli.tree_only)
+        .with_tree_style(cli.tree_style)
+        .with_path_style(cli.path_style().unwrap_or(settings.path_style))
+        .with_show_tree(cli.show_tree().or(settings.show_tree))
+        .with_tags(cli.tags.clone());
+    if let Some(front_matter) = cli.front_matter.or(settings.front_matter) {
+        formatter = formatter.with_front_matter(front_matter);
+    }
+    if let Some(limit) = cli.token_limit {
+        formatter = formatter.with_token_limit(limit, cli.token_priority);
+    }
+
+    // --template overrides the config file
+    match cli.template.as_ref().or(settings.template_path.as_ref()) {
+        Some(template_path) => {
+            let template = std::fs::read_to_string(template_path)
+                .with_context(|| format!("Failed to read template {}", template_path.display()))?;
+            formatter
+                .with_template(&template)
+                .with_context(|| format!("Failed to load template {}", template_path.display()))
+        }
+        None => Ok(formatter),
+    }
 }
 
 /// Unified output handler for both interactive and direct modes
 ///
 /// Returns OutputAction to indicate what the caller should do
 fn handle_output(
-    tree: &directory::tree::DirectoryTree,
+    tree: &DirectoryTree,
     cli: &Cli,
     settings: &Settings,
+    formatter: &OutputFormatter,
     is_interactive: bool,
 ) -> Result<OutputAction> {
-    // No output file specified, format the content
-    let formatter = OutputFormatter::new()
-        .with_metadata(false)
-        .with_line_numbers(false);
+    if cli.copy_path {
+        return handle_path_list_output(tree, cli, settings, is_interactive);
+    }
+
     let content = formatter.format_output(tree)?;
 
-    // Check if content is empty (no files included)
-    if content.trim().is_empty() {
-        println!("⚠ No content included. Please include at least one file.");
+    // Check if content is empty (no files included); the metadata header alone does not count
+    if tree.get_all_included_files().is_empty() || content.trim().is_empty() {
+        return Err(GthrError::NoFilesIncluded.into());
+    }
+
+    if let Some(path) = &cli.save_selection {
+        OutputWriter::write_content(path, &format_file_list(tree, false, false), false)?;
+        if !cli.quiet {
+            eprintln!("✓ Selection saved to: {}", path.display());
+        }
+    }
+
+    if let Some(limit) = cli.max_total_size
+        && content.len() as u64 > limit
+    {
+        let size = content.len() as u64;
+        let to_exclude = files_to_exclude(tree, size - limit);
+        if is_interactive {
+            let paths: Vec<String> = to_exclude
+                .iter()
+                .map(|node| tree.relative_path(&node.path).display().to_string())
+                .collect();
+            return Ok(OutputAction::Status(format!(
+                "Output is {} bytes over --max-total-size; exclude {}",
+                size - limit,
+                paths.join(", ")
+            )));
+        }
+
+        let mut message = format!(
+            "Output is {} bytes, {} bytes over the --max-total-size limit of {} bytes\n\
+             Exclude these files to fit:",
+            size,
+            size - limit,
+            limit
+        );
+        for node in to_exclude {
+            message.push_str(&format!(
+                "\n  {} ({} bytes)",
+                tree.relative_path(&node.path).display(),
+                node.size.unwrap_or(0)
+            ));
+        }
+        bail!(message);
+    }
+
+    if let Some(command) = &cli.pipe {
+        if is_interactive {
+            return Ok(OutputAction::Pipe(content));
+        }
+        pipe_output(command, &content)?;
         return Ok(OutputAction::Quit);
     }
 
     // If -o flag is provided, write directly to file
     if let Some(output_path) = &cli.output {
-        let writer = OutputWriter::new().with_formatter(formatter);
-        writer.write_to_file(tree, output_path)?;
-        println!("✓ Output written to: {}", output_path.display());
+        if !cli.append {
+            if is_interactive
+                && cli.overwrite_mode() == OverwriteMode::Refuse
+                && output_path.exists()
+            {
+                return Ok(OutputAction::ConfirmOverwrite(output_path.clone(), content));
+            }
+            prepare_output_path(output_path, cli.overwrite_mode())?;
+        }
+        write_output(output_path, &content, cli.append)?;
+        if !cli.quiet {
+            let verb = if cli.append { "appended" } else { "written" };
+            eprintln!("✓ Output {} to: {}", verb, output_path.display());
+        }
+        return Ok(OutputAction::Saved(output_path.clone()));
+    }
+
+    if cli.clipboard {
+        // Explicitly requested, so the size limit does not apply and there is no fallback
+        copy_to_clipboard(&content, settings)
+            .map_err(|e| GthrError::ClipboardUnavailable(e.to_string()))?;
+        if is_interactive {
+            return Ok(OutputAction::Copied(content.len()));
+        }
+        if !cli.quiet {
+            eprintln!("✓ Output copied to clipboard ({} bytes)", content.len());
+        }
         return Ok(OutputAction::Quit);
     }
 
     // Try clipboard if content is small enough
-    if content.len() <= settings.max_clipboard_size {
-        if let Ok(mut clipboard) = arboard::Clipboard::new() {
-            if clipboard.set_text(&content).is_ok() {
-                println!("✓ Output copied to clipboard ({} bytes)", content.len());
-                return Ok(OutputAction::Quit);
-            }
+    if content.len() <= settings.max_clipboard_size && copy_to_clipboard(&content, settings).is_ok()
+    {
+        if cli.append {
+            warn!("--append only applies to -o files; the clipboard was overwritten");
+        }
+        if is_interactive {
+            return Ok(OutputAction::Copied(content.len()));
         }
+        if !cli.quiet {
+            eprintln!("✓ Output copied to clipboard ({} bytes)", content.len());
+        }
+        return Ok(OutputAction::Quit);
     }
 
     // Clipboard failed or content too large
@@ -303,92 +870,276 @@ fn handle_output(
         Ok(OutputAction::StartFileSave(content))
     } else {
         // Direct mode: use text prompt
-        save_file_with_text_prompt(tree, &content, settings)?;
-        Ok(OutputAction::Continue)
+        let default_path = OutputWriter::default_output_path(
+            tree,
+            settings.output_dir(cli.project_root()).as_deref(),
+        );
+        let path = save_file_with_text_prompt(
+            &content,
+            settings,
+            &default_path,
+            cli.overwrite_mode(),
+            cli.quiet,
+        )?;
+        Ok(OutputAction::Saved(path))
     }
 }
 
-fn handle_export(app: &mut App, cli: &Cli, settings: &Settings) -> Result<()> {
-    match handle_output(&app.tree, cli, settings, true)? {
+/// Largest included files whose combined size covers `shortfall` bytes
+fn files_to_exclude(tree: &DirectoryTree, shortfall: u64) -> Vec<&FileNode> {
+    let mut files = tree.get_all_included_files();
+    files.sort_by_key(|node| std::cmp::Reverse(node.size.unwrap_or(0)));
+
+    let mut freed = 0;
+    files
+        .into_iter()
+        .take_while(|node| {
+            let needed = freed < shortfall;
+            freed += node.size.unwrap_or(0);
+            needed
+        })
+        .collect()
+}
+
+/// `--copy-path`: copy the included file paths, printing them if the clipboard is unavailable
+///
+/// With `--print0` they are always printed, NUL-separated; the interactive mode prints
+/// them once the terminal is restored.
+fn handle_path_list_output(
+    tree: &DirectoryTree,
+    cli: &Cli,
+    settings: &Settings,
+    is_interactive: bool,
+) -> Result<OutputAction> {
+    if cli.print0 {
+        check_not_empty(cli, tree)?;
+        let paths = format_path_list(tree, cli.relative, true);
+        if is_interactive {
+            return Ok(OutputAction::Print(paths));
+        }
+        print_stdout(&paths)?;
+        return Ok(OutputAction::Quit);
+    }
+
+    match copy_path_list(tree, cli.relative, settings) {
+        Ok(0) => return Err(GthrError::NoFilesIncluded.into()),
+        Ok(_) if cli.quiet => {}
+        Ok(count) => eprintln!("✓ {} paths copied to clipboard", count),
+        Err(e) if cli.clipboard => {
+            return Err(GthrError::ClipboardUnavailable(format!("{:#}", e)).into());
+        }
+        Err(_) => print_stdout(&format_path_list(tree, cli.relative, false))?,
+    }
+    Ok(OutputAction::Quit)
+}
+
+/// Copy the included file paths to the clipboard, returning how many were copied
+fn copy_path_list(tree: &DirectoryTree, relative: bool, settings: &Settings) -> Result<usize> {
+    let paths = format_path_list(tree, relative, false);
+    let count = paths.lines().count();
+    if count > 0 {
+        copy_to_clipboard(&paths, settings)?;
+    }
+    Ok(count)
+}
+
+/// Copy `content` with the configured `clipboard_backends`
+fn copy_to_clipboard(content: &str, settings: &Settings) -> Result<()> {
+    let backends = ClipboardBackend::parse_list(&settings.clipboard_backends);
+    try_write_to_clipboard_with_fallbacks(content, &backends)
+}
+
+/// Export the selection; clipboard copies keep the app open unless `quit` is set
+fn handle_export(app: &mut App, cli: &Cli, formatter: &OutputFormatter, quit: bool) -> Result<()> {
+    // Ctrl+O overrides the configured file tree setting
+    let show_tree = app.export_show_tree(formatter.show_tree());
+    let toggled;
+    let formatter = if show_tree == formatter.show_tree() {
+        formatter
+    } else {
+        toggled = formatter.clone().with_show_tree(show_tree);
+        &toggled
+    };
+    let settings = app.settings.clone();
+    let action = handle_output(&app.tree, cli, &settings, formatter, true)?;
+    // Only these leave the mode without output, which the user can still cancel
+    if !matches!(
+        action,
+        OutputAction::StartFileSave(_)
+            | OutputAction::ConfirmOverwrite(..)
+            | OutputAction::Status(_)
+    ) {
+        app.exported = true;
+    }
+    match action {
         OutputAction::Quit => app.quit(),
+        OutputAction::Saved(path) => {
+            app.saved_path = Some(path);
+            app.quit();
+        }
         OutputAction::StartFileSave(content) => app.start_file_save(content),
-        OutputAction::Continue => {}
+        OutputAction::Pipe(content) | OutputAction::Print(content) => {
+            app.pending_content = Some(content);
+            app.quit();
+        }
+        OutputAction::ConfirmOverwrite(path, content) => {
+            app.pending_content = Some(content);
+            app.confirm_overwrite(path);
+        }
+        OutputAction::Status(message) => app.set_status_message(message),
+        OutputAction::Copied(size) if quit => {
+            if !cli.quiet {
+                eprintln!("✓ Output copied to clipboard ({} bytes)", size);
+            }
+            app.quit();
+        }
+        OutputAction::Copied(size) => app.notify_copied(size),
+    }
+    Ok(())
+}
+
+/// `--open`: launch the written file, reporting failures without failing the export
+fn open_output(path: &Path, open_with: OpenWith) {
+    let editor = match open_with {
+        OpenWith::Default => None,
+        OpenWith::Editor => match std::env::var("EDITOR") {
+            Ok(editor) if !editor.trim().is_empty() => Some(editor),
+            _ => {
+                warn!("$EDITOR is not set; opening with the default application");
+                None
+            }
+        },
+    };
+
+    let mut command = OutputWriter::open_command(path, editor.as_deref());
+    let program = command.get_program().to_string_lossy().to_string();
+    match command.status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!(
+            "Failed to open {}: {} exited with {}",
+            path.display(),
+            program,
+            status
+        ),
+        Err(e) => warn!("Failed to open {} with {}: {}", path.display(), program, e),
+    }
+}
+
+/// Write to stdout, returning errors where `print!` would panic
+///
+/// Rust ignores `SIGPIPE`, so a reader exiting early shows up as a `BrokenPipe` error.
+fn print_stdout(content: &str) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(content.as_bytes())?;
+    stdout.flush()
+}
+
+/// Write the output to the stdin of `sh -c <command>`, exiting with its status if it fails
+fn pipe_output(command: &str, content: &str) -> Result<()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run pipe command: {}", command))?;
+
+    if let Some(mut stdin) = child.stdin.take()
+        && let Err(e) = stdin.write_all(content.as_bytes())
+        // Commands like `head` may exit before reading everything
+        && e.kind() != io::ErrorKind::BrokenPipe
+    {
+        return Err(e.into());
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
     }
     Ok(())
 }
 
 fn save_file_with_text_prompt(
-    tree: &directory::tree::DirectoryTree,
     content: &str,
     settings: &Settings,
-) -> Result<()> {
-    use std::fs;
+    default_path: &Path,
+    overwrite: OverwriteMode,
+    quiet: bool,
+) -> Result<PathBuf> {
     use std::io::{self, Write};
-    use std::path::Path;
 
     if content.len() > settings.max_clipboard_size {
-        println!(
-            "⚠ Output is too large for clipboard ({} bytes > {})",
+        warn!(
+            "Output is too large for clipboard ({} bytes > {})",
             content.len(),
             settings.format_clipboard_size()
         );
     }
 
-    print!("Enter file path to save output (or press Enter for default): ");
-    io::stdout().flush()?;
+    eprint!(
+        "Enter file path to save output (or press Enter for {}): ",
+        default_path.display()
+    );
+    io::stderr().flush()?;
 
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
     let input = input.trim();
 
-    let filename = if input.is_empty() {
-        OutputWriter::generate_default_filename(tree)
+    let path = if input.is_empty() {
+        default_path.to_path_buf()
     } else {
         // Add .md extension if not present and doesn't have any extension
         if !input.contains('.') {
-            format!("{}.md", input)
+            PathBuf::from(format!("{}.md", input))
         } else {
-            input.to_string()
+            PathBuf::from(input)
         }
     };
 
-    let path = Path::new(&filename);
+    save_output_file(&path, content, overwrite, quiet)?;
+    Ok(path)
+}
 
-    // Create parent directories if they don't exist
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
+/// Write `content` to a newly chosen output file
+fn save_output_file(
+    path: &Path,
+    content: &str,
+    overwrite: OverwriteMode,
+    quiet: bool,
+) -> Result<()> {
+    prepare_output_path(path, overwrite)?;
+    write_output(path, content, false)?;
+    if !quiet {
+        eprintln!("✓ Output saved to: {}", path.display());
     }
-
-    fs::write(path, content)?;
-    println!("✓ Output saved to: {}", path.display());
     Ok(())
 }
 
-fn save_file_from_dialog(app: &App, content: &str) -> Result<()> {
-    use std::fs;
-    use std::path::Path;
+/// `OutputWriter::write_content`, failing with the unwritable-output exit code
+fn write_output(path: &Path, content: &str, append: bool) -> Result<()> {
+    OutputWriter::write_content(path, content, append)
+        .map_err(|e| GthrError::OutputUnwritable(path.to_path_buf(), format!("{:#}", e)).into())
+}
 
-    let filename = if app.file_save_input.trim().is_empty() {
-        // Generate default filename
-        OutputWriter::generate_default_filename(&app.tree)
-    } else {
-        let input = app.file_save_input.trim();
-        // Add .md extension if not present and doesn't have any extension
-        if !input.contains('.') {
-            format!("{}.md", input)
-        } else {
-            input.to_string()
-        }
-    };
+/// Make way for writing `path`, asking on a terminal before replacing an existing file
+fn prepare_output_path(path: &Path, overwrite: OverwriteMode) -> Result<()> {
+    use std::io::{IsTerminal, Write};
 
-    let path = Path::new(&filename);
+    if overwrite == OverwriteMode::Refuse && path.exists() && io::stdin().is_terminal() {
+        eprint!("{} exists — overwrite? (y/n) ", path.display());
+        io::stderr().flush()?;
 
-    // Create parent directories if they don't exist
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            bail!("Not overwriting {}", path.display());
+        }
+        return Ok(());
     }
-
-    fs::write(path, content)?;
-    println!("✓ Output saved to: {}", path.display());
-    Ok(())
+    OutputWriter::prepare_overwrite(path, overwrite)
 }
@@ -0,0 +1,88 @@
+use super::formatter::estimate_tokens;
+use crate::directory::tree::FileNode;
+
+/// Which included files `--token-limit` keeps when the output has to shrink
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum TokenPriority {
+    /// Keep small files, excluding the largest first
+    #[default]
+    SizeAsc,
+    /// Keep large files, excluding the smallest first
+    SizeDesc,
+    /// Keep files in path order, excluding the last paths first
+    Name,
+}
+
+/// Files to leave out so the rest fit in `limit` estimated tokens, in the order they are
+/// dropped
+pub fn files_over_token_limit<'a>(
+    files: &[&'a FileNode],
+    limit: usize,
+    priority: TokenPriority,
+) -> Vec<&'a FileNode> {
+    let tokens = |node: &FileNode| estimate_tokens(node.size.unwrap_or(0));
+    let mut total: usize = files.iter().map(|node| tokens(node)).sum();
+    if total <= limit {
+        return Vec::new();
+    }
+
+    let mut candidates = files.to_vec();
+    match priority {
+        TokenPriority::SizeAsc => {
+            candidates.sort_by(|a, b| tokens(b).cmp(&tokens(a)).then(b.path.cmp(&a.path)))
+        }
+        TokenPriority::SizeDesc => {
+            candidates.sort_by(|a, b| tokens(a).cmp(&tokens(b)).then(b.path.cmp(&a.path)))
+        }
+        TokenPriority::Name => candidates.sort_by(|a, b| b.path.cmp(&a.path)),
+    }
+
+    candidates
+        .into_iter()
+        .take_while(|node| {
+            let over = total > limit;
+            total = total.saturating_sub(tokens(node));
+            over
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn file(name: &str, size: u64) -> FileNode {
+        let mut node = FileNode::new(PathBuf::from(name), false, None);
+        node.size = Some(size);
+        node
+    }
+
+    #[test]
+    fn test_files_over_token_limit() {
+        // 100, 50 and 10 tokens
+        let nodes = [file("a.rs", 400), file("b.rs", 200), file("c.rs", 40)];
+        let files: Vec<&FileNode> = nodes.iter().collect();
+        let names = |dropped: Vec<&FileNode>| -> Vec<String> {
+            dropped.iter().map(|node| node.name.clone()).collect()
+        };
+
+        assert!(files_over_token_limit(&files, 160, TokenPriority::SizeAsc).is_empty());
+        assert_eq!(
+            names(files_over_token_limit(&files, 60, TokenPriority::SizeAsc)),
+            vec!["a.rs"]
+        );
+        assert_eq!(
+            names(files_over_token_limit(&files, 100, TokenPriority::SizeDesc)),
+            vec!["c.rs", "b.rs"]
+        );
+        assert_eq!(
+            names(files_over_token_limit(&files, 150, TokenPriority::Name)),
+            vec!["c.rs"]
+        );
+        assert_eq!(
+            files_over_token_limit(&files, 0, TokenPriority::Name).len(),
+            3
+        );
+    }
+}
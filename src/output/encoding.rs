@@ -0,0 +1,249 @@
+use anyhow::{Result, bail};
+use encoding_rs::Encoding;
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Encodings tried, in order, for text that is neither UTF-8 nor UTF-16
+pub const DEFAULT_ENCODING_FALLBACKS: &[&str] = &["windows-1252", "iso-8859-1"];
+
+/// A legacy encoding to try when file bytes are not valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodingFallback {
+    /// ISO-8859-1, mapping every byte to the code point of the same value
+    ///
+    /// `encoding_rs` treats this label as windows-1252, as browsers do, so it is kept separate.
+    Latin1,
+    Other(&'static Encoding),
+}
+
+impl EncodingFallback {
+    /// Parse `labels`, warning about and skipping unknown ones
+    pub fn parse_list(labels: &[String]) -> Vec<Self> {
+        labels
+            .iter()
+            .filter_map(|label| match label.parse() {
+                Ok(fallback) => Some(fallback),
+                Err(e) => {
+                    crate::warn!("{}; skipping it", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn defaults() -> Vec<Self> {
+        DEFAULT_ENCODING_FALLBACKS
+            .iter()
+            .map(|label| label.parse().expect("default encodings are known"))
+            .collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EncodingFallback::Latin1 => "ISO-8859-1",
+            EncodingFallback::Other(encoding) => encoding.name(),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Option<String> {
+        match self {
+            EncodingFallback::Latin1 => Some(bytes.iter().map(|&byte| byte as char).collect()),
+            EncodingFallback::Other(encoding) => encoding
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(Cow::into_owned),
+        }
+    }
+}
+
+impl FromStr for EncodingFallback {
+    type Err = anyhow::Error;
+
+    fn from_str(label: &str) -> Result<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" => Ok(EncodingFallback::Latin1),
+            _ => match Encoding::for_label(label.trim().as_bytes()) {
+                Some(encoding) => Ok(EncodingFallback::Other(encoding)),
+                None => bail!("Unknown encoding \"{}\" in encoding_fallbacks", label),
+            },
+        }
+    }
+}
+
+/// Text decoded from raw file bytes
+pub struct DecodedText<'a> {
+    pub content: Cow<'a, str>,
+    /// Name of the source encoding when the content had to be transcoded
+    pub transcoded_from: Option<&'static str>,
+}
+
+/// Decode file bytes as UTF-8, falling back to UTF-16 (with or without BOM) and then to each
+/// of `fallbacks` in order.
+///
+/// Returns `None` when the bytes don't look like text in any supported encoding.
+pub fn decode_text<'a>(bytes: &'a [u8], fallbacks: &[EncodingFallback]) -> Option<DecodedText<'a>> {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return decode_utf8(rest);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return Some(decode_utf16(rest, u16::from_le_bytes, "UTF-16LE"));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return Some(decode_utf16(rest, u16::from_be_bytes, "UTF-16BE"));
+    }
+
+    // NUL bytes are valid UTF-8, so check for BOM-less UTF-16 first
+    match guess_utf16_byte_order(bytes) {
+        Some(Utf16Order::LittleEndian) => {
+            return Some(decode_utf16(bytes, u16::from_le_bytes, "UTF-16LE"));
+        }
+        Some(Utf16Order::BigEndian) => {
+            return Some(decode_utf16(bytes, u16::from_be_bytes, "UTF-16BE"));
+        }
+        None => {}
+    }
+
+    if let Some(decoded) = decode_utf8(bytes) {
+        return Some(decoded);
+    }
+
+    if !looks_like_text(bytes) {
+        return None;
+    }
+    fallbacks.iter().find_map(|fallback| {
+        fallback.decode(bytes).map(|content| DecodedText {
+            content: Cow::Owned(content),
+            transcoded_from: Some(fallback.name()),
+        })
+    })
+}
+
+fn decode_utf8(bytes: &[u8]) -> Option<DecodedText<'_>> {
+    std::str::from_utf8(bytes).ok().map(|content| DecodedText {
+        content: Cow::Borrowed(content),
+        transcoded_from: None,
+    })
+}
+
+fn decode_utf16(
+    bytes: &[u8],
+    to_unit: fn([u8; 2]) -> u16,
+    encoding: &'static str,
+) -> DecodedText<'static> {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| to_unit([pair[0], pair[1]]));
+    let content: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+
+    DecodedText {
+        content: Cow::Owned(content),
+        transcoded_from: Some(encoding),
+    }
+}
+
+enum Utf16Order {
+    LittleEndian,
+    BigEndian,
+}
+
+/// BOM-less UTF-16 text is mostly ASCII, so every other byte is zero
+fn guess_utf16_byte_order(bytes: &[u8]) -> Option<Utf16Order> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let pairs = bytes.len() / 2;
+    let even_zeros = bytes.iter().step_by(2).filter(|&&byte| byte == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&byte| byte == 0).count();
+
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 10 < pairs {
+        Some(Utf16Order::LittleEndian)
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 10 < pairs {
+        Some(Utf16Order::BigEndian)
+    } else {
+        None
+    }
+}
+
+/// Single-byte encodings map almost every byte to a char, so reject content with binary
+/// control bytes before trying them
+fn looks_like_text(bytes: &[u8]) -> bool {
+    let control_bytes = bytes
+        .iter()
+        .filter(|&&byte| byte < 0x20 && !matches!(byte, b'\n' | b'\r' | b'\t' | 0x0C))
+        .count();
+    !bytes.contains(&0) && control_bytes * 20 <= bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = if bom { vec![0xFF, 0xFE] } else { Vec::new() };
+        bytes.extend(text.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        let decoded = decode_text("héllo".as_bytes(), &[]).unwrap();
+        assert_eq!(decoded.content, "héllo");
+        assert!(decoded.transcoded_from.is_none());
+
+        let decoded = decode_text(b"\xEF\xBB\xBFbom", &[]).unwrap();
+        assert_eq!(decoded.content, "bom");
+        assert!(decoded.transcoded_from.is_none());
+    }
+
+    #[test]
+    fn test_decode_utf16() {
+        for bom in [true, false] {
+            let bytes = utf16le("fn main() {}\n", bom);
+            let decoded = decode_text(&bytes, &[]).unwrap();
+            assert_eq!(decoded.content, "fn main() {}\n");
+            assert_eq!(decoded.transcoded_from, Some("UTF-16LE"));
+        }
+
+        let bytes: Vec<u8> = [0xFE, 0xFF]
+            .into_iter()
+            .chain("hi".encode_utf16().flat_map(|unit| unit.to_be_bytes()))
+            .collect();
+        let decoded = decode_text(&bytes, &[]).unwrap();
+        assert_eq!(decoded.content, "hi");
+        assert_eq!(decoded.transcoded_from, Some("UTF-16BE"));
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        let decoded = decode_text(b"caf\xE9 cr\xE8me\n", &[EncodingFallback::Latin1]).unwrap();
+        assert_eq!(decoded.content, "café crème\n");
+        assert_eq!(decoded.transcoded_from, Some("ISO-8859-1"));
+    }
+
+    #[test]
+    fn test_decode_fallbacks() {
+        // 0x80 is the euro sign in windows-1252 but a control character in Latin-1
+        let decoded = decode_text(b"\x80 5 caf\xE9", &EncodingFallback::defaults()).unwrap();
+        assert_eq!(decoded.content, "€ 5 café");
+        assert_eq!(decoded.transcoded_from, Some("windows-1252"));
+
+        // Fallbacks that cannot decode the bytes are skipped
+        let fallbacks =
+            EncodingFallback::parse_list(&["shift_jis".to_string(), "latin1".to_string()]);
+        assert_eq!(fallbacks[0].name(), "Shift_JIS");
+        let decoded = decode_text(b"caf\xE9", &fallbacks).unwrap();
+        assert_eq!(decoded.transcoded_from, Some("ISO-8859-1"));
+
+        // Without fallbacks, non-UTF-8 text is not decoded
+        assert!(decode_text(b"caf\xE9", &[]).is_none());
+        assert!("klingon".parse::<EncodingFallback>().is_err());
+    }
+
+    #[test]
+    fn test_undecodable_content() {
+        let bytes = b"\x01\x02\x03\xFF\x00\x9B\x04\x05\x06";
+        assert!(decode_text(bytes, &EncodingFallback::defaults()).is_none());
+    }
+}
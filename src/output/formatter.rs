@@ -1,10 +1,104 @@
+use super::budget::{TokenPriority, files_over_token_limit};
+use super::encoding::{DecodedText, EncodingFallback, decode_text};
+use super::preprocessor::{Notebook, NotebookCell, parse_notebook, strip_comments};
+use crate::constants::CHARS_PER_TOKEN;
+use crate::directory::diff::TreeDiff;
+use crate::directory::stats::count_lines_in;
 use crate::directory::tree::{DirectoryTree, FileNode};
-use anyhow::Result;
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use quick_xml::Writer;
+use quick_xml::events::{BytesCData, BytesDecl, BytesText, Event};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
+/// Number of hex characters shown for file hashes in the header
+const SHORT_HASH_LEN: usize = 12;
+
+/// Name the user template is registered under
+const TEMPLATE_NAME: &str = "output";
+
+/// Layout of the formatted output
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Markdown with fenced code blocks
+    #[default]
+    Markdown,
+    /// `<documents>` XML with CDATA file contents
+    Xml,
+}
+
+/// Drawing style of the file tree
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum TreeStyle {
+    /// `+--` and `|` connectors
+    Ascii,
+    /// `├──` and `│` connectors
+    #[default]
+    Unicode,
+    /// Nested bullet lists
+    Markdown,
+}
+
+impl TreeStyle {
+    /// Connector before a node and the prefix continuing below it, by whether it is last
+    fn connectors(self, is_last: bool) -> (&'static str, &'static str) {
+        match (self, is_last) {
+            (TreeStyle::Ascii, true) => ("`-- ", "    "),
+            (TreeStyle::Ascii, false) => ("+-- ", "|   "),
+            (TreeStyle::Unicode, true) => ("└── ", "    "),
+            (TreeStyle::Unicode, false) => ("├── ", "│   "),
+            (TreeStyle::Markdown, _) => ("- ", "  "),
+        }
+    }
+}
+
+/// How file paths are written in the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathStyle {
+    /// Relative to the root, e.g. `src/main.rs`
+    #[default]
+    Relative,
+    /// Absolute, as traversed
+    Absolute,
+    /// Relative to the root's parent, starting with the root's name, e.g. `gthr/src/main.rs`
+    RootRelative,
+}
+
+/// Metadata block prepended to Markdown output for static site generators and note apps
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontMatterFormat {
+    /// YAML between `---` lines
+    Yaml,
+    /// TOML between `+++` lines
+    Toml,
+}
+
+#[derive(Clone)]
 pub struct OutputFormatter {
+    format: OutputFormat,
+    front_matter: Option<FrontMatterFormat>,
+    tags: Vec<String>,
     include_metadata: bool,
     include_line_numbers: bool,
+    strip_comments: bool,
+    skip_empty_files: bool,
+    hash_files: bool,
+    summary_only: bool,
+    show_tree: Option<bool>,
+    tree_only: bool,
+    tree_style: TreeStyle,
+    path_style: PathStyle,
+    token_limit: Option<(usize, TokenPriority)>,
+    encoding_fallbacks: Vec<EncodingFallback>,
+    template: Option<Handlebars<'static>>,
 }
 
 impl Default for OutputFormatter {
@@ -16,11 +110,42 @@ impl Default for OutputFormatter {
 impl OutputFormatter {
     pub fn new() -> Self {
         Self {
+            format: OutputFormat::Markdown,
+            front_matter: None,
+            tags: Vec::new(),
             include_metadata: true,
             include_line_numbers: false,
+            strip_comments: false,
+            skip_empty_files: false,
+            hash_files: false,
+            summary_only: false,
+            show_tree: None,
+            tree_only: false,
+            tree_style: TreeStyle::Unicode,
+            path_style: PathStyle::Relative,
+            token_limit: None,
+            encoding_fallbacks: EncodingFallback::defaults(),
+            template: None,
         }
     }
 
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Start Markdown output with front matter describing the export
+    pub fn with_front_matter(mut self, format: FrontMatterFormat) -> Self {
+        self.front_matter = Some(format);
+        self
+    }
+
+    /// Tags listed in the front matter
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
     pub fn with_metadata(mut self, include_metadata: bool) -> Self {
         self.include_metadata = include_metadata;
         self
@@ -31,145 +156,925 @@ impl OutputFormatter {
         self
     }
 
+    pub fn with_strip_comments(mut self, strip_comments: bool) -> Self {
+        self.strip_comments = strip_comments;
+        self
+    }
+
+    pub fn with_skip_empty_files(mut self, skip_empty_files: bool) -> Self {
+        self.skip_empty_files = skip_empty_files;
+        self
+    }
+
+    pub fn with_hash_files(mut self, hash_files: bool) -> Self {
+        self.hash_files = hash_files;
+        self
+    }
+
+    /// Output only the header, file tree and a table of file sizes, without file contents
+    pub fn with_summary_only(mut self, summary_only: bool) -> Self {
+        self.summary_only = summary_only;
+        self
+    }
+
+    /// Force the file tree on or off; by default XML and templates include it when more
+    /// than one file is included, summaries always and Markdown never
+    pub fn with_show_tree(mut self, show_tree: Option<bool>) -> Self {
+        self.show_tree = show_tree;
+        self
+    }
+
+    /// Leave out included files, in `priority` order, until the rest fit in `limit` estimated
+    /// tokens; the files left out are listed at the end of the output
+    pub fn with_token_limit(mut self, limit: usize, priority: TokenPriority) -> Self {
+        self.token_limit = Some((limit, priority));
+        self
+    }
+
+    /// Output only the tree of every traversed file and directory, whether included or not
+    pub fn with_tree_only(mut self, tree_only: bool) -> Self {
+        self.tree_only = tree_only;
+        self
+    }
+
+    pub fn with_tree_style(mut self, tree_style: TreeStyle) -> Self {
+        self.tree_style = tree_style;
+        self
+    }
+
+    /// Encodings tried, in order, for files that are neither UTF-8 nor UTF-16
+    pub fn with_encoding_fallbacks(mut self, fallbacks: Vec<EncodingFallback>) -> Self {
+        self.encoding_fallbacks = fallbacks;
+        self
+    }
+
+    /// Write file paths, including the file tree's root, in `path_style`
+    pub fn with_path_style(mut self, path_style: PathStyle) -> Self {
+        self.path_style = path_style;
+        self
+    }
+
+    /// Forced file tree setting, `None` for the per-format default
+    pub fn show_tree(&self) -> Option<bool> {
+        self.show_tree
+    }
+
+    pub fn path_style(&self) -> PathStyle {
+        self.path_style
+    }
+
+    /// Render output with a Handlebars template instead of the built-in Markdown layout.
+    ///
+    /// The template is compiled here so syntax errors surface before any export.
+    pub fn with_template(mut self, template: &str) -> Result<Self> {
+        let mut registry = Handlebars::new();
+        // Output is Markdown, not HTML
+        registry.register_escape_fn(handlebars::no_escape);
+        registry
+            .register_template_string(TEMPLATE_NAME, template)
+            .context("Invalid output template")?;
+        self.template = Some(registry);
+        Ok(self)
+    }
+
     pub fn format_output(&self, tree: &DirectoryTree) -> Result<String> {
-        let included_files = tree.get_all_included_files();
-        let mut output = String::new();
+        if self.tree_only {
+            let all_nodes: HashSet<usize> = (0..tree.nodes.len()).collect();
+            let tree_structure = self.draw_tree(tree, &all_nodes);
+            return Ok(match self.tree_style {
+                TreeStyle::Markdown => tree_structure,
+                _ => format!("```\n{}```\n", tree_structure),
+            });
+        }
+
+        let (nodes, token_limit_excluded) = self.files_within_token_limit(tree);
+
+        // Read every file once so hashing and empty checks reuse the same bytes
+        let mut included_files: Vec<LoadedFile> = nodes
+            .into_iter()
+            .map(|node| LoadedFile::load(node, self.hash_files))
+            .collect();
+        let mut skipped_empty = 0;
+
+        if self.skip_empty_files {
+            let total = included_files.len();
+            included_files.retain(|file| !file.is_empty());
+            skipped_empty = total - included_files.len();
+        }
+
+        if let Some(registry) = &self.template {
+            return self.render_template(registry, tree, &included_files, &token_limit_excluded);
+        }
+
+        if self.format == OutputFormat::Xml {
+            return self.format_xml(tree, &included_files, &token_limit_excluded);
+        }
+
+        let mut output = match self.front_matter {
+            Some(format) => self.format_front_matter(format, tree, &included_files)?,
+            None => String::new(),
+        };
+
+        if self.summary_only {
+            output.push_str(&self.format_summary(tree, &included_files, skipped_empty)?);
+            output.push_str(&self.format_token_limit_excluded(tree, &token_limit_excluded));
+            return Ok(output);
+        }
 
         if self.include_metadata {
             // Add header
-            output.push_str(&self.format_header(tree, &included_files)?);
+            output.push_str(&self.format_header(tree, &included_files, skipped_empty, true)?);
             output.push_str("\n\n");
         }
 
+        if self.show_tree == Some(true) {
+            output.push_str("## File Tree\n\n```\n");
+            output.push_str(&self.format_tree_structure(tree, &included_files));
+            output.push_str("```\n\n");
+        }
+
         // Add file contents
-        for (index, file_node) in included_files.iter().enumerate() {
+        for (index, file) in included_files.iter().enumerate() {
             if index > 0 {
                 output.push_str("\n\n");
             }
-            output.push_str(&self.format_file(tree, file_node)?);
+            output.push_str(&self.format_file(tree, file)?);
+        }
+        output.push_str(&self.format_token_limit_excluded(tree, &token_limit_excluded));
+
+        Ok(output)
+    }
+
+    /// Write the output to `writer`, reading and writing one file at a time
+    ///
+    /// Output whose beginning depends on every file, such as the metadata header, XML,
+    /// templates, front matter and summaries, is formatted in full first.
+    pub fn write_output<W: Write>(&self, tree: &DirectoryTree, writer: &mut W) -> Result<()> {
+        if !self.streams() {
+            writer.write_all(self.format_output(tree)?.as_bytes())?;
+            return Ok(());
+        }
+
+        let (nodes, token_limit_excluded) = self.files_within_token_limit(tree);
+        if self.show_tree == Some(true) {
+            writer.write_all(b"## File Tree\n\n```\n")?;
+            writer.write_all(self.format_node_tree(tree, nodes.iter().copied()).as_bytes())?;
+            writer.write_all(b"```\n\n")?;
+        }
+
+        let mut written = 0;
+        for node in nodes {
+            let file = LoadedFile::load(node, self.hash_files);
+            if self.skip_empty_files && file.is_empty() {
+                continue;
+            }
+            if written > 0 {
+                writer.write_all(b"\n\n")?;
+            }
+            writer.write_all(self.format_file(tree, &file)?.as_bytes())?;
+            written += 1;
         }
+        let excluded = self.format_token_limit_excluded(tree, &token_limit_excluded);
+        writer.write_all(excluded.as_bytes())?;
+        Ok(())
+    }
+
+    /// Whether `write_output` can write each file as it is read: nothing before the file
+    /// sections depends on all of them
+    fn streams(&self) -> bool {
+        self.template.is_none()
+            && self.format == OutputFormat::Markdown
+            && self.front_matter.is_none()
+            && !self.tree_only
+            && !self.summary_only
+            && !self.include_metadata
+            // The tree leaves out empty files, known only once every file was read
+            && !(self.show_tree == Some(true) && self.skip_empty_files)
+    }
+
+    /// Included files within `--token-limit`, and the files it left out sorted by path
+    fn files_within_token_limit<'a>(
+        &self,
+        tree: &'a DirectoryTree,
+    ) -> (Vec<&'a FileNode>, Vec<&'a FileNode>) {
+        let mut nodes = tree.get_all_included_files();
+        let mut token_limit_excluded = Vec::new();
+        if let Some((limit, priority)) = self.token_limit {
+            token_limit_excluded = files_over_token_limit(&nodes, limit, priority);
+            let excluded: HashSet<&Path> =
+                token_limit_excluded.iter().map(|node| node.path.as_path()).collect();
+            nodes.retain(|node| !excluded.contains(node.path.as_path()));
+            token_limit_excluded.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        (nodes, token_limit_excluded)
+    }
+
+    /// Markdown summary of added, removed and modified files
+    pub fn format_diff(&self, diff: &TreeDiff) -> String {
+        let mut output = String::from("## Changes\n");
+        if diff.is_empty() {
+            output.push_str("\nNo files changed.\n");
+            return output;
+        }
+
+        for (title, paths) in [
+            ("Added", &diff.added),
+            ("Removed", &diff.removed),
+            ("Modified", &diff.modified),
+        ] {
+            if paths.is_empty() {
+                continue;
+            }
+            output.push_str(&format!("\n### {} ({})\n\n", title, paths.len()));
+            for path in paths {
+                output.push_str(&format!("- `{}`\n", path.display()));
+            }
+        }
+        output
+    }
+
+    fn render_template(
+        &self,
+        registry: &Handlebars<'static>,
+        tree: &DirectoryTree,
+        included_files: &[LoadedFile],
+        token_limit_excluded: &[&FileNode],
+    ) -> Result<String> {
+        let total_size: u64 = included_files
+            .iter()
+            .filter_map(|file| file.node.size)
+            .sum();
+
+        let files: Vec<_> = included_files
+            .iter()
+            .map(|file| {
+                let path = &file.node.path;
+                let language = file.node.language();
+                let content = match file.text(&self.encoding_fallbacks) {
+                    Ok(decoded) if self.strip_comments => strip_comments(&decoded.content, language),
+                    Ok(decoded) => decoded.content.into_owned(),
+                    Err(e) => format!("*Error reading file: {}*", e),
+                };
+                let line_numbers: String = content
+                    .lines()
+                    .enumerate()
+                    .map(|(line_num, line)| format!("{:4} | {}\n", line_num + 1, line))
+                    .collect();
+
+                json!({
+                    "path": path.display().to_string(),
+                    "relative_path": tree.relative_path(path).display().to_string(),
+                    "language": language,
+                    "size": file.node.size.map(format_file_size).unwrap_or_else(|| "Unknown".to_string()),
+                    "content": content,
+                    "line_numbers": line_numbers,
+                })
+            })
+            .collect();
+
+        let data = json!({
+            "root": tree.root_label(),
+            "file_count": included_files.len(),
+            "total_size": format_file_size(total_size),
+            "tree": if self.shows_tree_by_default(included_files) {
+                self.format_tree_structure(tree, included_files)
+            } else {
+                String::new()
+            },
+            "files": files,
+            "token_limit_excluded": token_limit_excluded
+                .iter()
+                .map(|node| self.display_path(tree, &node.path).display().to_string())
+                .collect::<Vec<_>>(),
+        });
 
+        registry
+            .render(TEMPLATE_NAME, &data)
+            .context("Failed to render output template")
+    }
+
+    /// `<documents>` with the file tree and one `<document>` per file, contents as CDATA
+    fn format_xml(
+        &self,
+        tree: &DirectoryTree,
+        included_files: &[LoadedFile],
+        token_limit_excluded: &[&FileNode],
+    ) -> Result<String> {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        writer.write_event(Event::Comment(BytesText::new(&format!(
+            " Generated by gthr {} ",
+            env!("CARGO_PKG_VERSION")
+        ))))?;
+
+        writer
+            .create_element("documents")
+            .write_inner_content(|writer| {
+                if self.shows_tree_by_default(included_files) {
+                    let tree_structure = self.format_tree_structure(tree, included_files);
+                    write_cdata_element(writer, "tree", &tree_structure)?;
+                }
+
+                for (index, file) in included_files.iter().enumerate() {
+                    let path = &file.node.path;
+                    let language = file.node.language();
+                    let content = match file.text(&self.encoding_fallbacks) {
+                        Ok(decoded) if self.strip_comments => {
+                            strip_comments(&decoded.content, language)
+                        }
+                        Ok(decoded) => decoded.content.into_owned(),
+                        Err(e) => format!("Error reading file: {}", e),
+                    };
+                    let size = file.node.size.unwrap_or(0).to_string();
+
+                    writer
+                        .create_element("document")
+                        .with_attribute(("index", (index + 1).to_string().as_str()))
+                        .write_inner_content(|writer| {
+                            let source = self.display_path(tree, path).display().to_string();
+                            writer
+                                .create_element("source")
+                                .write_text_content(BytesText::new(&source))?;
+                            writer
+                                .create_element("language")
+                                .write_text_content(BytesText::new(language))?;
+                            writer
+                                .create_element("size")
+                                .write_text_content(BytesText::new(&size))?;
+                            write_cdata_element(writer, "content", &content)
+                        })?;
+                }
+
+                if !token_limit_excluded.is_empty() {
+                    writer
+                        .create_element("excluded")
+                        .with_attribute(("reason", "token-limit"))
+                        .write_inner_content(|writer| {
+                            for node in token_limit_excluded {
+                                let source =
+                                    self.display_path(tree, &node.path).display().to_string();
+                                writer
+                                    .create_element("source")
+                                    .write_text_content(BytesText::new(&source))?;
+                            }
+                            Ok(())
+                        })?;
+                }
+                Ok(())
+            })?;
+
+        let mut output = String::from_utf8(writer.into_inner())?;
+        output.push('\n');
         Ok(output)
     }
 
-    fn format_header(&self, tree: &DirectoryTree, included_files: &[&FileNode]) -> Result<String> {
-        let root_path = &tree.nodes[tree.root_index].path;
-        let total_size: u64 = included_files.iter().filter_map(|node| node.size).sum();
+    fn format_front_matter(
+        &self,
+        format: FrontMatterFormat,
+        tree: &DirectoryTree,
+        included_files: &[LoadedFile],
+    ) -> Result<String> {
+        let root = &tree.nodes[tree.root_index].path;
+        // `.` has no name of its own, so title the export after the directory it refers to
+        let title = fs::canonicalize(root)
+            .ok()
+            .and_then(|root| {
+                root.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| tree.root_label());
+        let front_matter = FrontMatter {
+            title,
+            date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            root: tree.root_label(),
+            file_count: included_files.len(),
+            total_size: included_files
+                .iter()
+                .filter_map(|file| file.node.size)
+                .sum(),
+            tags: &self.tags,
+        };
+
+        match format {
+            FrontMatterFormat::Yaml => {
+                // JSON scalars and arrays are valid YAML flow values
+                let mut output = String::from("---\n");
+                output.push_str(&format!("title: {}\n", json!(front_matter.title)));
+                output.push_str(&format!("date: {}\n", front_matter.date));
+                output.push_str(&format!("root: {}\n", json!(front_matter.root)));
+                output.push_str(&format!("file_count: {}\n", front_matter.file_count));
+                output.push_str(&format!("total_size: {}\n", front_matter.total_size));
+                output.push_str(&format!("tags: {}\n", json!(front_matter.tags)));
+                output.push_str("---\n\n");
+                Ok(output)
+            }
+            FrontMatterFormat::Toml => Ok(format!(
+                "+++\n{}+++\n\n",
+                toml::to_string(&front_matter).context("Failed to write TOML front matter")?
+            )),
+        }
+    }
+
+    fn format_summary(
+        &self,
+        tree: &DirectoryTree,
+        included_files: &[LoadedFile],
+        skipped_empty: usize,
+    ) -> Result<String> {
+        let total_size: u64 = included_files
+            .iter()
+            .filter_map(|file| file.node.size)
+            .sum();
+
+        let mut output = self.format_header(tree, included_files, skipped_empty, false)?;
+        output.push_str(&format!(
+            "**Estimated Tokens:** ~{}\n",
+            estimate_tokens(total_size)
+        ));
+
+        if self.show_tree != Some(false) {
+            output.push_str("\n## File Tree\n\n```\n");
+            output.push_str(&self.format_tree_structure(tree, included_files));
+            output.push_str("```\n");
+        }
+
+        output.push_str("\n## Included Files\n\n| File | Size |\n| --- | --- |\n");
+        for file in included_files {
+            let size_str = file
+                .node
+                .size
+                .map(format_file_size)
+                .unwrap_or_else(|| "Unknown".to_string());
+            output.push_str(&format!(
+                "| {} | {} |\n",
+                self.display_path(tree, &file.node.path).display(),
+                size_str
+            ));
+        }
+
+        Ok(output)
+    }
+
+    /// Whether XML and template output include the tree: unless disabled, when it shows
+    /// more than a single file
+    fn shows_tree_by_default(&self, included_files: &[LoadedFile]) -> bool {
+        self.show_tree.unwrap_or(included_files.len() > 1)
+    }
+
+    fn format_header(
+        &self,
+        tree: &DirectoryTree,
+        included_files: &[LoadedFile],
+        skipped_empty: usize,
+        list_files: bool,
+    ) -> Result<String> {
+        let total_size: u64 = included_files
+            .iter()
+            .filter_map(|file| file.node.size)
+            .sum();
 
         let mut header = String::new();
-        header.push_str(&format!("# Text Ingest Report\n"));
-        header.push_str(&format!("**Root Directory:** {}\n", root_path.display()));
+        header.push_str("# Text Ingest Report\n");
+        header.push_str(&format!("**Root Directory:** {}\n", tree.root_label()));
         header.push_str(&format!("**Files Included:** {}\n", included_files.len()));
+        if skipped_empty > 0 {
+            header.push_str(&format!("**Empty Files Skipped:** {}\n", skipped_empty));
+        }
         header.push_str(&format!(
             "**Total Size:** {}\n",
             format_file_size(total_size)
         ));
+        let total_lines: usize = included_files
+            .iter()
+            .filter_map(|file| file.bytes.as_deref().ok())
+            .map(count_lines_in)
+            .sum();
+        header.push_str(&format!("**Total Lines:** {}\n", total_lines));
         header.push_str(&format!(
             "**Generated:** {}\n",
             chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
         ));
 
-        if self.include_metadata {
+        if list_files {
             header.push_str("\n## Included Files\n");
-            for file_node in included_files {
-                let relative_path = file_node
-                    .path
-                    .strip_prefix(root_path)
-                    .unwrap_or(&file_node.path);
-                let size_str = file_node
+            for file in included_files {
+                let relative_path = self.display_path(tree, &file.node.path);
+                let size_str = file
+                    .node
                     .size
                     .map(format_file_size)
                     .unwrap_or_else(|| "Unknown".to_string());
-                header.push_str(&format!("- {} ({})\n", relative_path.display(), size_str));
+                match &file.sha256 {
+                    Some(hash) => header.push_str(&format!(
+                        "- {} ({}, sha256:{})\n",
+                        relative_path.display(),
+                        size_str,
+                        &hash[..SHORT_HASH_LEN]
+                    )),
+                    None => {
+                        header.push_str(&format!("- {} ({})\n", relative_path.display(), size_str))
+                    }
+                }
             }
         }
 
         Ok(header)
     }
 
-    fn format_file(&self, tree: &DirectoryTree, file_node: &FileNode) -> Result<String> {
-        let root_path = &tree.nodes[tree.root_index].path;
-        let relative_path = file_node
-            .path
-            .strip_prefix(root_path)
-            .unwrap_or(&file_node.path);
+    fn format_file(&self, tree: &DirectoryTree, file: &LoadedFile) -> Result<String> {
+        let file_node = file.node;
+        let relative_path = self.display_path(tree, &file_node.path);
 
         let mut output = String::new();
 
         // Always include file header for context
         output.push_str(&format!("# {}\n\n", relative_path.display()));
 
+        let text = file.text(&self.encoding_fallbacks);
+
         if self.include_metadata {
             if let Some(size) = file_node.size {
                 output.push_str(&format!("**Size:** {}\n", format_file_size(size)));
             }
             output.push_str(&format!("**Path:** {}\n", file_node.path.display()));
-            output.push_str("\n");
+            if let Some(hash) = &file.sha256 {
+                output.push_str(&format!("**SHA256:** {}\n", hash));
+            }
+            if let Ok(DecodedText {
+                transcoded_from: Some(encoding),
+                ..
+            }) = &text
+            {
+                output.push_str(&format!(
+                    "**Encoding:** {} (transcoded to UTF-8)\n",
+                    encoding
+                ));
+            }
+            output.push('\n');
+        } else if let Some(hash) = &file.sha256 {
+            // `hash_files` does not need the rest of the metadata
+            output.push_str(&format!("**SHA256:** {}\n\n", hash));
         }
 
         // File content
-        match fs::read_to_string(&file_node.path) {
+        let text = text.map(|decoded| decoded.content);
+        match text.as_deref() {
+            Ok(content) if is_notebook(&file_node.path) => match parse_notebook(content) {
+                Ok(notebook) => output.push_str(&self.format_notebook(&notebook)),
+                Err(e) => {
+                    output.push_str(&format!(
+                        "*Warning: could not parse notebook ({}), showing raw JSON*\n\n",
+                        e
+                    ));
+                    output.push_str(&self.format_code_block(content, "json"));
+                }
+            },
             Ok(content) => {
-                output.push_str("```");
-
                 // Add language hint based on file extension
-                if let Some(ext) = file_node.path.extension() {
-                    let ext_str = ext.to_string_lossy().to_lowercase();
-                    let language = match ext_str.as_str() {
-                        "rs" => "rust",
-                        "py" => "python",
-                        "js" => "javascript",
-                        "ts" => "typescript",
-                        "jsx" => "jsx",
-                        "tsx" => "tsx",
-                        "html" => "html",
-                        "css" => "css",
-                        "scss" | "sass" => "scss",
-                        "json" => "json",
-                        "yaml" | "yml" => "yaml",
-                        "toml" => "toml",
-                        "xml" => "xml",
-                        "sql" => "sql",
-                        "sh" | "bash" => "bash",
-                        "c" => "c",
-                        "cpp" | "cc" | "cxx" => "cpp",
-                        "h" | "hpp" | "hxx" => "cpp",
-                        "java" => "java",
-                        "go" => "go",
-                        "rb" => "ruby",
-                        "php" => "php",
-                        "swift" => "swift",
-                        "kt" | "kts" => "kotlin",
-                        "scala" => "scala",
-                        "md" => "markdown",
-                        "typ" => "typst",
-                        _ => "",
-                    };
-                    output.push_str(language);
-                }
+                let language = file_node.language();
+                output.push_str(&self.format_code_block(content, language));
+            }
+            Err(e) => {
+                output.push_str(&format!("*Error reading file: {}*", e));
+            }
+        }
+
+        Ok(output)
+    }
 
-                output.push('\n');
+    /// `path` written in the configured path style
+    fn display_path(&self, tree: &DirectoryTree, path: &Path) -> PathBuf {
+        styled_path(tree, path, self.path_style)
+    }
 
-                if self.include_line_numbers {
-                    for (line_num, line) in content.lines().enumerate() {
-                        output.push_str(&format!("{:4} | {}\n", line_num + 1, line));
-                    }
-                } else {
-                    output.push_str(&content);
+    /// Markdown list of the files `--token-limit` left out, or nothing if none were
+    fn format_token_limit_excluded(&self, tree: &DirectoryTree, excluded: &[&FileNode]) -> String {
+        if excluded.is_empty() {
+            return String::new();
+        }
+
+        let mut output = String::from("\n\n# Files excluded to meet token limit\n\n");
+        for node in excluded {
+            output.push_str(&format!(
+                "- `{}` (~{} tokens)\n",
+                self.display_path(tree, &node.path).display(),
+                estimate_tokens(node.size.unwrap_or(0))
+            ));
+        }
+        output
+    }
+
+    /// ASCII tree of the included files and the directories that contain them
+    fn format_tree_structure(&self, tree: &DirectoryTree, included_files: &[LoadedFile]) -> String {
+        self.format_node_tree(tree, included_files.iter().map(|file| file.node))
+    }
+
+    /// `format_tree_structure` of files that have not been read yet
+    fn format_node_tree<'a>(
+        &self,
+        tree: &DirectoryTree,
+        nodes: impl IntoIterator<Item = &'a FileNode>,
+    ) -> String {
+        // Keep every included file plus all of its ancestors
+        let mut visible = HashSet::new();
+        for node in nodes {
+            let mut index = tree.path_to_index.get(&node.path).copied();
+            while let Some(current) = index {
+                if !visible.insert(current) {
+                    break;
                 }
+                index = tree.nodes[current].parent;
+            }
+        }
+
+        self.draw_tree(tree, &visible)
+    }
 
-                output.push_str("\n```");
+    /// The root and its `visible` descendants; absolute paths show the root's full path
+    fn draw_tree(&self, tree: &DirectoryTree, visible: &HashSet<usize>) -> String {
+        let root = &tree.nodes[tree.root_index];
+        let (root_marker, prefix) = match self.tree_style {
+            TreeStyle::Markdown => ("- ", "  "),
+            _ => ("", ""),
+        };
+        let root_name = match self.path_style {
+            PathStyle::Absolute if !tree.is_multi_root() => root.path.display().to_string(),
+            _ => root.name.clone(),
+        };
+        let mut output = format!("{}{}/\n", root_marker, root_name);
+        append_tree_children(tree, tree.root_index, visible, prefix, self.tree_style, &mut output);
+        output
+    }
+
+    fn format_code_block(&self, content: &str, language: &str) -> String {
+        let mut output = String::new();
+        output.push_str("```");
+        output.push_str(language);
+        output.push('\n');
+
+        let content = if self.strip_comments {
+            strip_comments(content, language)
+        } else {
+            content.to_string()
+        };
+
+        if self.include_line_numbers {
+            for (line_num, line) in content.lines().enumerate() {
+                output.push_str(&format!("{:4} | {}\n", line_num + 1, line));
             }
-            Err(e) => {
-                output.push_str(&format!("*Error reading file: {}*", e));
+        } else {
+            output.push_str(&content);
+        }
+
+        output.push_str("\n```");
+        output
+    }
+
+    /// Render notebook cells as prose and code blocks, dropping outputs
+    fn format_notebook(&self, notebook: &Notebook) -> String {
+        let cells: Vec<String> = notebook
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| match cell {
+                NotebookCell::Markdown(source) => {
+                    format!("**Cell {} (markdown)**\n\n{}", index + 1, source.trim_end())
+                }
+                NotebookCell::Code(source) => format!(
+                    "**Cell {} (code)**\n\n{}",
+                    index + 1,
+                    self.format_code_block(source.trim_end(), &notebook.language)
+                ),
+                NotebookCell::Raw(source) => format!(
+                    "**Cell {} (raw)**\n\n{}",
+                    index + 1,
+                    self.format_code_block(source.trim_end(), "")
+                ),
+            })
+            .collect();
+
+        cells.join("\n\n")
+    }
+}
+
+/// Fields written by `with_front_matter`
+#[derive(Serialize)]
+struct FrontMatter<'a> {
+    title: String,
+    date: String,
+    root: String,
+    file_count: usize,
+    total_size: u64,
+    tags: &'a [String],
+}
+
+/// An included file whose bytes have been read once for formatting
+struct LoadedFile<'a> {
+    node: &'a FileNode,
+    bytes: io::Result<Vec<u8>>,
+    sha256: Option<String>,
+}
+
+impl<'a> LoadedFile<'a> {
+    fn load(node: &'a FileNode, hash: bool) -> Self {
+        let bytes = fs::read(&node.path);
+        let sha256 = match &bytes {
+            Ok(bytes) if hash => Some(sha256_hex(bytes)),
+            _ => None,
+        };
+
+        Self {
+            node,
+            bytes,
+            sha256,
+        }
+    }
+
+    /// Zero-length or whitespace-only files; unreadable files are kept so the error is reported
+    fn is_empty(&self) -> bool {
+        match &self.bytes {
+            Ok(bytes) => bytes.iter().all(|byte| byte.is_ascii_whitespace()),
+            Err(_) => false,
+        }
+    }
+
+    fn text(&self, fallbacks: &[EncodingFallback]) -> io::Result<DecodedText<'_>> {
+        match &self.bytes {
+            Ok(bytes) => decode_text(bytes, fallbacks).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )
+            }),
+            Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+        }
+    }
+}
+
+fn append_tree_children(
+    tree: &DirectoryTree,
+    index: usize,
+    visible: &HashSet<usize>,
+    prefix: &str,
+    style: TreeStyle,
+    output: &mut String,
+) {
+    let children: Vec<usize> = tree.nodes[index]
+        .children
+        .iter()
+        .copied()
+        .filter(|child| visible.contains(child))
+        .collect();
+
+    for (position, &child) in children.iter().enumerate() {
+        let is_last = position == children.len() - 1;
+        let node = &tree.nodes[child];
+        let (connector, continuation) = style.connectors(is_last);
+        let suffix = if node.is_directory { "/" } else { "" };
+        output.push_str(&format!("{}{}{}{}\n", prefix, connector, node.name, suffix));
+
+        if node.is_directory {
+            let child_prefix = format!("{}{}", prefix, continuation);
+            append_tree_children(tree, child, visible, &child_prefix, style, output);
+        }
+    }
+}
+
+/// Element whose text is CDATA, split where the text itself contains `]]>`
+fn write_cdata_element(writer: &mut Writer<Vec<u8>>, name: &str, text: &str) -> io::Result<()> {
+    writer.create_element(name).write_inner_content(|writer| {
+        for cdata in BytesCData::escaped(text) {
+            writer.write_event(Event::CData(cdata))?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn is_notebook(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.to_string_lossy().eq_ignore_ascii_case("ipynb"))
+}
+
+/// `path` of a node in `tree`, written in `path_style`
+pub fn styled_path(tree: &DirectoryTree, path: &Path, path_style: PathStyle) -> PathBuf {
+    match path_style {
+        PathStyle::Relative => tree.relative_path(path),
+        PathStyle::Absolute => path.to_path_buf(),
+        // Multi-root paths already start with the name of their root
+        PathStyle::RootRelative if tree.is_multi_root() => tree.relative_path(path),
+        PathStyle::RootRelative => {
+            let root = &tree.nodes[tree.root_index];
+            // A root given as `.` is named after the directory it resolves to
+            let root_name = fs::canonicalize(&root.path)
+                .ok()
+                .and_then(|root_path| root_path.file_name().map(|name| name.to_os_string()))
+                .unwrap_or_else(|| root.name.clone().into());
+            Path::new(&root_name).join(tree.relative_path(path))
+        }
+    }
+}
+
+/// `--export-paths`: included text files, one path per line in `path_style`
+///
+/// Absolute paths are canonicalized so a root given as `.` still yields full paths.
+pub fn format_styled_path_list(tree: &DirectoryTree, path_style: PathStyle) -> String {
+    tree.get_all_included_files()
+        .iter()
+        .map(|node| {
+            let path = match path_style {
+                PathStyle::Absolute => {
+                    fs::canonicalize(&node.path).unwrap_or_else(|_| node.path.clone())
+                }
+                _ => styled_path(tree, &node.path, path_style),
+            };
+            format!("{}\n", path.display())
+        })
+        .collect()
+}
+
+/// Included text files, one path per line, absolute or relative to the root
+///
+/// With `null`, each path ends with a NUL byte instead of `\n`.
+pub fn format_path_list(tree: &DirectoryTree, relative: bool, null: bool) -> String {
+    let terminator = if null { '\0' } else { '\n' };
+    tree.get_all_included_files()
+        .iter()
+        .map(|node| {
+            let path = if relative {
+                tree.relative_path(&node.path)
+            } else {
+                fs::canonicalize(&node.path).unwrap_or_else(|_| node.path.clone())
+            };
+            format!("{}{}", path.display(), terminator)
+        })
+        .collect()
+}
+
+/// `gthr list`: included files relative to the root, optionally with size and language
+///
+/// Each entry ends with `\n`, or with a NUL byte when `null` is set so any path survives
+/// `xargs -0`.
+pub fn format_file_list(tree: &DirectoryTree, long: bool, null: bool) -> String {
+    let terminator = if null { '\0' } else { '\n' };
+    tree.get_all_included_files()
+        .iter()
+        .map(|node| {
+            let path = tree.relative_path(&node.path);
+            if long {
+                let language = match node.language() {
+                    "" => "-",
+                    language => language,
+                };
+                format!(
+                    "{:>10}  {:<12}  {}{}",
+                    node.size.unwrap_or(0),
+                    language,
+                    path.display(),
+                    terminator
+                )
+            } else {
+                format!("{}{}", path.display(), terminator)
             }
+        })
+        .collect()
+}
+
+/// Rough token estimate for LLM context budgeting
+pub fn estimate_tokens(size: u64) -> usize {
+    (size as usize).div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Count with thousands separators, like `1,234`
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut output = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            output.push(',');
         }
+        output.push(digit);
+    }
+    output
+}
 
-        Ok(output)
+/// Token count shortened like `950`, `42.3k` or `1.2M`
+pub fn format_token_count(tokens: usize) -> String {
+    match tokens {
+        0..1_000 => tokens.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
     }
 }
 
-fn format_file_size(size: u64) -> String {
+pub fn format_file_size(size: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut size_f = size as f64;
     let mut unit_index = 0;
@@ -185,3 +1090,527 @@ fn format_file_size(size: u64) -> String {
         format!("{:.1} {}", size_f, UNITS[unit_index])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::state::SelectionState;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_token_count() {
+        assert_eq!(format_token_count(950), "950");
+        assert_eq!(format_token_count(42_300), "42.3k");
+        assert_eq!(format_token_count(1_250_000), "1.2M");
+        assert_eq!(format_count(950), "950");
+        assert_eq!(format_count(1_234), "1,234");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_format_diff() {
+        let formatter = OutputFormatter::new();
+        let diff = TreeDiff {
+            added: vec![PathBuf::from("src/new.rs")],
+            removed: Vec::new(),
+            modified: vec![PathBuf::from("src/main.rs"), PathBuf::from("Cargo.toml")],
+        };
+
+        assert_eq!(
+            formatter.format_diff(&diff),
+            "## Changes\n\n### Added (1)\n\n- `src/new.rs`\n\n### Modified (2)\n\n\
+             - `src/main.rs`\n- `Cargo.toml`\n"
+        );
+        assert_eq!(
+            formatter.format_diff(&TreeDiff::default()),
+            "## Changes\n\nNo files changed.\n"
+        );
+    }
+
+    #[test]
+    fn test_skip_empty_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("__init__.py"), "")?;
+        fs::write(root_path.join("blank.txt"), "  \n\t\n")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        for name in ["main.rs", "__init__.py", "blank.txt"] {
+            let path = root_path.join(name);
+            let size = fs::metadata(&path)?.len();
+            let index = tree.add_node(path, false, root_path).unwrap();
+            tree.get_node_mut(index).unwrap().size = Some(size);
+        }
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let output = OutputFormatter::new()
+            .with_skip_empty_files(true)
+            .format_output(&tree)?;
+
+        assert!(output.contains("**Files Included:** 1"));
+        assert!(output.contains("**Empty Files Skipped:** 2"));
+        assert!(output.contains("# main.rs"));
+        assert!(!output.contains("# __init__.py"));
+        assert!(!output.contains("# blank.txt"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        let path = root_path.join("hello.txt");
+        fs::write(&path, "hello world")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(path, false, root_path);
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let output = OutputFormatter::new()
+            .with_hash_files(true)
+            .format_output(&tree)?;
+
+        let expected = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        assert!(output.contains(&format!("**SHA256:** {}", expected)));
+        assert!(output.contains(&format!("sha256:{}", &expected[..SHORT_HASH_LEN])));
+
+        let output = OutputFormatter::new().format_output(&tree)?;
+        assert!(!output.contains("SHA256"));
+
+        // Without the metadata, each file still gets its hash
+        let output = OutputFormatter::new()
+            .with_metadata(false)
+            .with_hash_files(true)
+            .format_output(&tree)?;
+        assert!(output.starts_with(&format!("# hello.txt\n\n**SHA256:** {}\n\n", expected)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_utf8_files_are_transcoded() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain(
+                "utf16 text"
+                    .encode_utf16()
+                    .flat_map(|unit| unit.to_le_bytes()),
+            )
+            .collect();
+        fs::write(root_path.join("utf16.txt"), utf16)?;
+        fs::write(root_path.join("latin1.txt"), b"caf\xE9")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("utf16.txt"), false, root_path);
+        tree.add_node(root_path.join("latin1.txt"), false, root_path);
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let output = OutputFormatter::new().format_output(&tree)?;
+
+        assert!(output.contains("utf16 text"));
+        assert!(output.contains("**Encoding:** UTF-16LE (transcoded to UTF-8)"));
+        assert!(output.contains("café"));
+        assert!(output.contains("**Encoding:** windows-1252 (transcoded to UTF-8)"));
+        assert!(!output.contains("Error reading file"));
+
+        let output = OutputFormatter::new()
+            .with_encoding_fallbacks(vec![EncodingFallback::Latin1])
+            .format_output(&tree)?;
+        assert!(output.contains("**Encoding:** ISO-8859-1 (transcoded to UTF-8)"));
+
+        // Without fallbacks the error is kept
+        let output = OutputFormatter::new()
+            .with_encoding_fallbacks(Vec::new())
+            .format_output(&tree)?;
+        assert!(output.contains("Error reading file"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_template() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::create_dir(root_path.join("src"))?;
+        fs::write(root_path.join("src").join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("notes.txt"), "a < b")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("src"), true, root_path);
+        tree.add_node(
+            root_path.join("src").join("main.rs"),
+            false,
+            &root_path.join("src"),
+        );
+        tree.add_node(root_path.join("notes.txt"), false, root_path);
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let template = "{{file_count}} files\n{{tree}}{{#each files}}<file path=\"{{relative_path}}\" lang=\"{{language}}\">\n{{content}}\n</file>\n{{/each}}";
+        let output = OutputFormatter::new()
+            .with_template(template)?
+            .format_output(&tree)?;
+
+        let root_name = root_path.file_name().unwrap().to_string_lossy();
+        assert!(output.starts_with(&format!(
+            "2 files\n{}/\n├── src/\n│   └── main.rs\n└── notes.txt\n",
+            root_name
+        )));
+        assert!(
+            output.contains("<file path=\"src/main.rs\" lang=\"rust\">\nfn main() {}\n</file>")
+        );
+        // Content is not HTML-escaped
+        assert!(output.contains("a < b"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_template_is_rejected() {
+        let result = OutputFormatter::new().with_template("{{#each files}}{{path}}");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_xml_output() -> Result<()> {
+        use quick_xml::Reader;
+
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        let content = "if a < b && c]]>d {}";
+        fs::write(root_path.join("main.rs"), content)?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        let index = tree
+            .add_node(root_path.join("main.rs"), false, root_path)
+            .unwrap();
+        tree.nodes[index].size = Some(content.len() as u64);
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let output = OutputFormatter::new()
+            .with_format(OutputFormat::Xml)
+            .format_output(&tree)?;
+        assert!(output.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(output.contains("<!-- Generated by gthr "));
+        assert!(output.contains("<source>main.rs</source>"));
+        assert!(output.contains("<language>rust</language>"));
+        assert!(output.contains(&format!("<size>{}</size>", content.len())));
+
+        // The document parses, and CDATA sections join back into the original content
+        let mut reader = Reader::from_str(&output);
+        let mut element = String::new();
+        let mut parsed_content = String::new();
+        loop {
+            match reader.read_event()? {
+                Event::Start(start) => {
+                    element = String::from_utf8(start.name().as_ref().to_vec())?;
+                }
+                Event::CData(cdata) if element == "content" => {
+                    parsed_content.push_str(std::str::from_utf8(&cdata)?);
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        assert_eq!(parsed_content, content);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_path_list() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path().canonicalize()?;
+        fs::create_dir(root_path.join("src"))?;
+        fs::write(root_path.join("src").join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("README.md"), "# Test")?;
+
+        let mut tree = DirectoryTree::new(root_path.clone());
+        tree.add_node(root_path.join("src"), true, &root_path);
+        tree.add_node(
+            root_path.join("src").join("main.rs"),
+            false,
+            &root_path.join("src"),
+        );
+        tree.add_node(root_path.join("README.md"), false, &root_path);
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        assert_eq!(
+            format_path_list(&tree, true, false),
+            "src/main.rs\nREADME.md\n"
+        );
+        assert_eq!(
+            format_path_list(&tree, true, true),
+            "src/main.rs\0README.md\0"
+        );
+        assert_eq!(
+            format_path_list(&tree, false, false),
+            format!(
+                "{}\n{}\n",
+                root_path.join("src").join("main.rs").display(),
+                root_path.join("README.md").display()
+            )
+        );
+
+        let root_name = root_path.file_name().unwrap().to_string_lossy().to_string();
+        assert_eq!(
+            format_styled_path_list(&tree, PathStyle::RootRelative),
+            format!("{0}/src/main.rs\n{0}/README.md\n", root_name)
+        );
+        assert_eq!(
+            format_styled_path_list(&tree, PathStyle::Absolute),
+            format_path_list(&tree, false, false)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_limit() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("big.rs"), "// padding\n".repeat(40))?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        for (name, size) in [("main.rs", 12), ("big.rs", 440)] {
+            let index = tree.add_node(root_path.join(name), false, root_path).unwrap();
+            tree.nodes[index].size = Some(size);
+        }
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let output = OutputFormatter::new()
+            .with_metadata(false)
+            .with_token_limit(100, TokenPriority::SizeAsc)
+            .format_output(&tree)?;
+        assert!(output.contains("fn main() {}"));
+        assert!(!output.contains("// padding"));
+        assert!(output.ends_with(
+            "# Files excluded to meet token limit\n\n- `big.rs` (~110 tokens)\n"
+        ));
+
+        // The selection itself is left alone
+        assert_eq!(tree.get_all_included_files().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_only() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        let index = tree
+            .add_node(root_path.join("main.rs"), false, root_path)
+            .unwrap();
+        tree.nodes[index].size = Some(12);
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let output = OutputFormatter::new()
+            .with_metadata(false)
+            .with_summary_only(true)
+            .format_output(&tree)?;
+
+        assert!(output.contains("**Files Included:** 1"));
+        assert!(output.contains("**Total Lines:** 1\n"));
+        assert!(output.contains("**Estimated Tokens:** ~3"));
+        assert!(output.contains("└── main.rs"));
+        assert!(output.contains("| main.rs | 12 B |"));
+        assert!(!output.contains("fn main() {}"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_tree() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("lib.rs"), "pub fn lib() {}")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("main.rs"), false, root_path);
+        tree.set_state(tree.root_index, SelectionState::Included);
+        let xml = |show_tree, tree: &DirectoryTree| {
+            OutputFormatter::new()
+                .with_format(OutputFormat::Xml)
+                .with_show_tree(show_tree)
+                .format_output(tree)
+        };
+
+        // A lone file gets no tree unless asked for
+        assert!(!xml(None, &tree)?.contains("<tree>"));
+        assert!(xml(Some(true), &tree)?.contains("<tree>"));
+
+        tree.add_node(root_path.join("lib.rs"), false, root_path);
+        tree.set_state(tree.root_index, SelectionState::Included);
+        assert!(xml(None, &tree)?.contains("<tree>"));
+        assert!(!xml(Some(false), &tree)?.contains("<tree>"));
+
+        let markdown = |show_tree| {
+            OutputFormatter::new()
+                .with_metadata(false)
+                .with_show_tree(show_tree)
+                .format_output(&tree)
+        };
+        assert!(!markdown(None)?.contains("## File Tree"));
+        assert!(markdown(Some(true))?.starts_with("## File Tree\n\n```\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_output() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("empty.rs"), "")?;
+        fs::write(root_path.join("big.rs"), "x".repeat(400))?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        for name in ["big.rs", "empty.rs", "main.rs"] {
+            let path = root_path.join(name);
+            tree.add_node(path.clone(), false, root_path);
+            let index = tree.path_to_index[&path];
+            tree.nodes[index].size = Some(fs::metadata(&path)?.len());
+        }
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        // Written file by file or formatted in full, the output is the same
+        let streamed = OutputFormatter::new()
+            .with_metadata(false)
+            .with_show_tree(Some(true))
+            .with_token_limit(50, TokenPriority::SizeAsc);
+        let skipping = OutputFormatter::new()
+            .with_skip_empty_files(true)
+            .with_show_tree(Some(true));
+        assert!(streamed.streams());
+        assert!(!skipping.streams());
+        for formatter in [streamed, skipping.clone(), skipping.with_metadata(false)] {
+            let mut buffer = Vec::new();
+            formatter.write_output(&tree, &mut buffer)?;
+            assert_eq!(String::from_utf8(buffer)?, formatter.format_output(&tree)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_only() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::create_dir(root_path.join("src"))?;
+        fs::write(root_path.join("src").join("main.rs"), "fn main() {}")?;
+        fs::write(root_path.join("README.md"), "# Readme")?;
+
+        // Nothing is included, yet every traversed node is listed
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("src"), true, root_path);
+        tree.add_node(root_path.join("src").join("main.rs"), false, &root_path.join("src"));
+        tree.add_node(root_path.join("README.md"), false, root_path);
+        let root = tree.nodes[tree.root_index].name.clone();
+        let tree_only = |style| {
+            OutputFormatter::new()
+                .with_tree_only(true)
+                .with_tree_style(style)
+                .format_output(&tree)
+        };
+
+        assert_eq!(
+            tree_only(TreeStyle::Unicode)?,
+            format!("```\n{}/\n├── src/\n│   └── main.rs\n└── README.md\n```\n", root)
+        );
+        assert_eq!(
+            tree_only(TreeStyle::Ascii)?,
+            format!("```\n{}/\n+-- src/\n|   `-- main.rs\n`-- README.md\n```\n", root)
+        );
+        assert_eq!(
+            tree_only(TreeStyle::Markdown)?,
+            format!("- {}/\n  - src/\n    - main.rs\n  - README.md\n", root)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_style() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::create_dir(root_path.join("src"))?;
+        fs::write(root_path.join("src").join("main.rs"), "fn main() {}")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("src"), true, root_path);
+        tree.add_node(root_path.join("src").join("main.rs"), false, &root_path.join("src"));
+        tree.set_state(tree.root_index, SelectionState::Included);
+        let root = tree.nodes[tree.root_index].name.clone();
+        let output = |path_style| {
+            OutputFormatter::new()
+                .with_metadata(false)
+                .with_show_tree(Some(true))
+                .with_path_style(path_style)
+                .format_output(&tree)
+        };
+
+        let relative = output(PathStyle::Relative)?;
+        assert!(relative.contains(&format!("```\n{}/\n└── src/", root)));
+        assert!(relative.contains("# src/main.rs\n"));
+
+        let absolute = output(PathStyle::Absolute)?;
+        assert!(absolute.contains(&format!("```\n{}/\n└── src/", root_path.display())));
+        let main_path = root_path.join("src").join("main.rs");
+        assert!(absolute.contains(&format!("# {}\n", main_path.display())));
+
+        let root_relative = output(PathStyle::RootRelative)?;
+        assert!(root_relative.contains(&format!("# {}/src/main.rs\n", root)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_front_matter() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("main.rs"), "fn main() {}")?;
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        let index = tree
+            .add_node(root_path.join("main.rs"), false, root_path)
+            .unwrap();
+        tree.nodes[index].size = Some(12);
+        tree.set_state(tree.root_index, SelectionState::Included);
+        let formatter = |format| {
+            OutputFormatter::new()
+                .with_front_matter(format)
+                .with_tags(vec!["rust".to_string(), "cli".to_string()])
+        };
+
+        let yaml = formatter(FrontMatterFormat::Yaml).format_output(&tree)?;
+        let (front_matter, body) = yaml
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.split_once("---\n\n"))
+            .expect("YAML front matter delimiters");
+        assert!(front_matter.contains("file_count: 1\n"));
+        assert!(front_matter.contains("total_size: 12\n"));
+        assert!(front_matter.contains("tags: [\"rust\",\"cli\"]\n"));
+        // The existing header follows the front matter
+        assert!(body.starts_with("# Text Ingest Report"));
+
+        let toml_output = formatter(FrontMatterFormat::Toml).format_output(&tree)?;
+        let (front_matter, _) = toml_output
+            .strip_prefix("+++\n")
+            .and_then(|rest| rest.split_once("+++\n\n"))
+            .expect("TOML front matter delimiters");
+        let table: toml::Table = toml::from_str(front_matter)?;
+        assert_eq!(table["file_count"].as_integer(), Some(1));
+        assert_eq!(table["tags"].as_array().map(Vec::len), Some(2));
+
+        Ok(())
+    }
+}
@@ -1,3 +1,6 @@
+pub mod budget;
+pub mod encoding;
 pub mod formatter;
+pub mod preprocessor;
+pub mod stats;
 pub mod writer;
-
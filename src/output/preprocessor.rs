@@ -0,0 +1,505 @@
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Comment syntax for a family of languages
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    /// Block comments may contain other block comments, as in Rust
+    nested_blocks: bool,
+    quotes: &'static [char],
+    /// Delimiters of strings spanning lines, checked before `quotes`
+    multiline_quotes: &'static [&'static str],
+    /// Line comments only start a word, so `$#` in shell scripts is not one
+    line_at_word_start: bool,
+}
+
+fn comment_syntax(language: &str) -> Option<CommentSyntax> {
+    let syntax = match language {
+        "rust" => CommentSyntax {
+            line: &["//"],
+            block: Some(("/*", "*/")),
+            nested_blocks: true,
+            // Single quotes are handled separately because of lifetimes
+            quotes: &['"'],
+            multiline_quotes: &[],
+            line_at_word_start: false,
+        },
+        "swift" | "kotlin" | "scala" => CommentSyntax {
+            line: &["//"],
+            block: Some(("/*", "*/")),
+            nested_blocks: true,
+            quotes: &['"', '\''],
+            multiline_quotes: &[],
+            line_at_word_start: false,
+        },
+        "c" | "cpp" | "java" | "go" | "php" => CommentSyntax {
+            line: &["//"],
+            block: Some(("/*", "*/")),
+            nested_blocks: false,
+            quotes: &['"', '\''],
+            multiline_quotes: &[],
+            line_at_word_start: false,
+        },
+        "javascript" | "typescript" | "jsx" | "tsx" => CommentSyntax {
+            line: &["//"],
+            block: Some(("/*", "*/")),
+            nested_blocks: false,
+            quotes: &['"', '\'', '`'],
+            multiline_quotes: &[],
+            line_at_word_start: false,
+        },
+        "css" | "scss" => CommentSyntax {
+            line: &[],
+            block: Some(("/*", "*/")),
+            nested_blocks: false,
+            quotes: &['"', '\''],
+            multiline_quotes: &[],
+            line_at_word_start: false,
+        },
+        "python" => CommentSyntax {
+            line: &["#"],
+            block: None,
+            nested_blocks: false,
+            quotes: &['"', '\''],
+            multiline_quotes: &["\"\"\"", "'''"],
+            line_at_word_start: false,
+        },
+        "ruby" => CommentSyntax {
+            line: &["#"],
+            block: None,
+            nested_blocks: false,
+            quotes: &['"', '\''],
+            multiline_quotes: &[],
+            line_at_word_start: false,
+        },
+        "bash" => CommentSyntax {
+            line: &["#"],
+            block: None,
+            nested_blocks: false,
+            quotes: &['"', '\''],
+            multiline_quotes: &[],
+            line_at_word_start: true,
+        },
+        "html" | "xml" => CommentSyntax {
+            line: &[],
+            block: Some(("<!--", "-->")),
+            nested_blocks: false,
+            quotes: &[],
+            multiline_quotes: &[],
+            line_at_word_start: false,
+        },
+        "sql" => CommentSyntax {
+            line: &["--"],
+            block: Some(("/*", "*/")),
+            nested_blocks: false,
+            quotes: &['\''],
+            multiline_quotes: &[],
+            line_at_word_start: false,
+        },
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Strip single-line and block comments from `content` written in `language`.
+///
+/// `language` is the code fence language name (e.g. `rust`, `python`). Content in
+/// unsupported languages is returned unchanged. String literals are skipped so that
+/// comment markers inside them (e.g. `"http://"`) are preserved, a `#!` shebang line is
+/// kept, and a block comment between code becomes a space. Runs of blank lines that
+/// include a line a comment was removed from are collapsed to a single empty line, or
+/// removed at the start and end of the file; every other line is kept as written.
+pub fn strip_comments(content: &str, language: &str) -> String {
+    let Some(syntax) = comment_syntax(language) else {
+        return content.to_string();
+    };
+
+    let chars: Vec<char> = content.chars().collect();
+    let mut output = String::with_capacity(content.len());
+    let mut i = 0;
+    // Output keeps the input's lines, so comments are tracked by input line
+    let mut stripped_lines = HashSet::new();
+    let mut position = (0, 0);
+
+    // A shebang looks like a `#` comment but is needed to run the script
+    if syntax.line.contains(&"#") && starts_with_at(&chars, 0, "#!") {
+        while i < chars.len() && chars[i] != '\n' {
+            output.push(chars[i]);
+            i += 1;
+        }
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // Block comments, keeping newlines so line structure is preserved
+        if let Some((open, close)) = syntax.block
+            && starts_with_at(&chars, i, open)
+        {
+            trim_trailing_whitespace(&mut output);
+            let first_line = line_at(&chars, i, &mut position);
+            i += open.chars().count();
+            let mut depth = 1;
+            while i < chars.len() && depth > 0 {
+                if syntax.nested_blocks && starts_with_at(&chars, i, open) {
+                    depth += 1;
+                    i += open.chars().count();
+                } else if starts_with_at(&chars, i, close) {
+                    depth -= 1;
+                    i += close.chars().count();
+                } else {
+                    if chars[i] == '\n' || starts_with_at(&chars, i, "\r\n") {
+                        output.push(chars[i]);
+                    }
+                    i += 1;
+                }
+            }
+            stripped_lines.extend(first_line..=line_at(&chars, i, &mut position));
+            // Keep tokens on both sides apart, as in `return /* value */x;`
+            let code_follows = chars.get(i).is_some_and(|next| !next.is_whitespace());
+            if code_follows && !output.is_empty() && !output.ends_with('\n') {
+                output.push(' ');
+            }
+            continue;
+        }
+
+        // Line comments run until the end of the line
+        let at_word_start =
+            i == 0 || chars[i - 1].is_whitespace() || ";|&()".contains(chars[i - 1]);
+        let is_line_comment = syntax
+            .line
+            .iter()
+            .any(|marker| starts_with_at(&chars, i, marker));
+        if is_line_comment && (at_word_start || !syntax.line_at_word_start) {
+            trim_trailing_whitespace(&mut output);
+            stripped_lines.insert(line_at(&chars, i, &mut position));
+            while i < chars.len() && chars[i] != '\n' && !starts_with_at(&chars, i, "\r\n") {
+                i += 1;
+            }
+            continue;
+        }
+
+        // Rust char literals ('"', '\'') would otherwise open a string
+        if language == "rust" && c == '\'' {
+            let literal_len = rust_char_literal_len(&chars, i);
+            output.extend(&chars[i..i + literal_len]);
+            i += literal_len;
+            continue;
+        }
+
+        // Multi-line strings such as Python docstrings are copied verbatim
+        if let Some(delimiter) = syntax
+            .multiline_quotes
+            .iter()
+            .find(|delimiter| starts_with_at(&chars, i, delimiter))
+        {
+            let delimiter_len = delimiter.chars().count();
+            output.extend(&chars[i..i + delimiter_len]);
+            i += delimiter_len;
+            while i < chars.len() && !starts_with_at(&chars, i, delimiter) {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    output.push(chars[i]);
+                    i += 1;
+                }
+                output.push(chars[i]);
+                i += 1;
+            }
+            let end = (i + delimiter_len).min(chars.len());
+            output.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+
+        // String literals are copied verbatim
+        if syntax.quotes.contains(&c) {
+            output.push(c);
+            i += 1;
+            while i < chars.len() {
+                let current = chars[i];
+                output.push(current);
+                i += 1;
+                if current == '\\' && i < chars.len() {
+                    output.push(chars[i]);
+                    i += 1;
+                } else if current == c || (current == '\n' && c != '`') {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        output.push(c);
+        i += 1;
+    }
+
+    collapse_stripped_lines(&output, &stripped_lines, content.ends_with('\n'))
+}
+
+/// Line of `chars[index]`, counted on from `position`, the index and line of an earlier call
+fn line_at(chars: &[char], index: usize, position: &mut (usize, usize)) -> usize {
+    let (counted, line) = *position;
+    *position = (index, line + chars[counted..index].iter().filter(|&&c| c == '\n').count());
+    position.1
+}
+
+fn starts_with_at(chars: &[char], index: usize, pattern: &str) -> bool {
+    pattern
+        .chars()
+        .enumerate()
+        .all(|(offset, expected)| chars.get(index + offset) == Some(&expected))
+}
+
+/// Length of the Rust char literal starting at `index`, or 1 for a lifetime tick
+fn rust_char_literal_len(chars: &[char], index: usize) -> usize {
+    match chars.get(index + 1) {
+        // Escaped char literal such as '\'' or '\u{1F600}'
+        Some('\\') => chars
+            .get(index + 3..)
+            .and_then(|rest| rest.iter().take(10).position(|&ch| ch == '\''))
+            .map_or(1, |position| position + 4),
+        Some(_) if chars.get(index + 2) == Some(&'\'') => 3,
+        _ => 1,
+    }
+}
+
+fn trim_trailing_whitespace(output: &mut String) {
+    while output.ends_with(' ') || output.ends_with('\t') {
+        output.pop();
+    }
+}
+
+/// Collapse each run of blank lines that includes a stripped line to one empty line, or to
+/// none at the start and end of the file; other lines are kept as written
+fn collapse_stripped_lines(
+    content: &str,
+    stripped_lines: &HashSet<usize>,
+    ends_with_newline: bool,
+) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut result = String::with_capacity(content.len());
+
+    let mut start = 0;
+    while start < lines.len() {
+        let blank_lines = lines[start..].iter().take_while(|line| line.trim().is_empty());
+        let end = start + blank_lines.count();
+        if end == start {
+            result.push_str(lines[start]);
+            start += 1;
+            continue;
+        }
+
+        if !(start..end).any(|line| stripped_lines.contains(&line)) {
+            result.extend(lines[start..end].iter().copied());
+        } else if start > 0 && end < lines.len() {
+            result.push_str(if lines[start].ends_with("\r\n") { "\r\n" } else { "\n" });
+        }
+        start = end;
+    }
+
+    // A comment on a last line without a newline leaves the previous line's newline behind
+    if !ends_with_newline && result.ends_with('\n') {
+        result.pop();
+        if result.ends_with('\r') {
+            result.pop();
+        }
+    }
+
+    result
+}
+
+/// A Jupyter notebook reduced to the source of its cells
+pub struct Notebook {
+    pub language: String,
+    pub cells: Vec<NotebookCell>,
+}
+
+pub enum NotebookCell {
+    Code(String),
+    Markdown(String),
+    Raw(String),
+}
+
+/// Parse `.ipynb` JSON, keeping cell sources and dropping outputs and attachments
+pub fn parse_notebook(content: &str) -> Result<Notebook> {
+    let json: Value = serde_json::from_str(content)?;
+    let cells = json
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("missing `cells` array"))?;
+
+    let language = json
+        .pointer("/metadata/language_info/name")
+        .or_else(|| json.pointer("/metadata/kernelspec/language"))
+        .and_then(Value::as_str)
+        .unwrap_or("python")
+        .to_string();
+
+    let cells = cells
+        .iter()
+        .map(|cell| {
+            let source = match cell.get("source") {
+                // Sources are usually stored as a list of lines
+                Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+                Some(Value::String(source)) => source.clone(),
+                _ => String::new(),
+            };
+
+            match cell.get("cell_type").and_then(Value::as_str) {
+                Some("code") => Ok(NotebookCell::Code(source)),
+                Some("markdown") => Ok(NotebookCell::Markdown(source)),
+                Some("raw") => Ok(NotebookCell::Raw(source)),
+                other => Err(anyhow!("unknown cell type {:?}", other)),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Notebook { language, cells })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_rust_comments() {
+        let content = "// header\n\n\n/// Docs\nfn main() {\n    let url = \"http://example.com\"; // trailing\n    /* block\n       comment */\n    let c = '\"';\n}\n";
+        let stripped = strip_comments(content, "rust");
+
+        assert_eq!(
+            stripped,
+            "fn main() {\n    let url = \"http://example.com\";\n\n    let c = '\"';\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_rust_keeps_lifetimes() {
+        let content = "fn first<'a>(s: &'a str) -> &'a str { s } // done\n";
+        assert_eq!(
+            strip_comments(content, "rust"),
+            "fn first<'a>(s: &'a str) -> &'a str { s }\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_python_comments() {
+        let content = "# comment\nx = '# not a comment'  # comment\n\n\n\ny = 2\n";
+        assert_eq!(
+            strip_comments(content, "python"),
+            "x = '# not a comment'\n\n\n\ny = 2\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_python_keeps_docstrings() {
+        let content =
+            "def f():\n    \"\"\"Ranks #1.\n\n    '# tags'\"\"\"\n    return '''#x''' # done\n";
+        assert_eq!(
+            strip_comments(content, "python"),
+            "def f():\n    \"\"\"Ranks #1.\n\n    '# tags'\"\"\"\n    return '''#x'''\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_keeps_untouched_lines() {
+        // Blank runs, whitespace-only lines and CRLF endings the author wrote stay as they are
+        let content = "\r\nx = 1\r\n  \r\n\r\n\"\"\"a\r\n\r\n\r\nb\"\"\"\r\n";
+        assert_eq!(strip_comments(content, "python"), content);
+        assert_eq!(
+            strip_comments("x = 1 # one\r\n# two\r\n\r\ny = 2\r\n", "python"),
+            "x = 1\r\n\r\ny = 2\r\n"
+        );
+        assert_eq!(
+            strip_comments("int x; /* a\r\n b */\r\nint y;", "c"),
+            "int x;\r\n\r\nint y;"
+        );
+        // A comment on the last line goes with the newline before it
+        assert_eq!(strip_comments("x = 1\n# done", "python"), "x = 1");
+    }
+
+    #[test]
+    fn test_strip_keeps_shebang() {
+        let content = "#!/usr/bin/env python3\n# comment\nprint(1)\n";
+        assert_eq!(
+            strip_comments(content, "python"),
+            "#!/usr/bin/env python3\n\nprint(1)\n"
+        );
+        // Rust inner attributes are not shebangs, and comments after them still go
+        assert_eq!(
+            strip_comments("#![allow(dead_code)] // why\n", "rust"),
+            "#![allow(dead_code)]\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_bash_keeps_argument_count() {
+        let content = "#!/bin/sh\nif [ $# -eq 0 ]; then # no args\n  echo ${#list[@]} x#y\nfi\n";
+        assert_eq!(
+            strip_comments(content, "bash"),
+            "#!/bin/sh\nif [ $# -eq 0 ]; then\n  echo ${#list[@]} x#y\nfi\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_nested_block_comments() {
+        let content = "/* outer /* inner */ still comment */\nfn main() {}\n";
+        assert_eq!(strip_comments(content, "rust"), "fn main() {}\n");
+        // C block comments do not nest
+        assert_eq!(strip_comments("/* a /* b */ int x;\n", "c"), " int x;\n");
+    }
+
+    #[test]
+    fn test_strip_block_comment_between_tokens() {
+        assert_eq!(
+            strip_comments("unsigned/*u*/int f() { return /* v */x; }\n", "c"),
+            "unsigned int f() { return x; }\n"
+        );
+        // Nothing is added at the start or end of a line
+        assert_eq!(strip_comments("/* a */x = 1; /* b */\n", "c"), "x = 1;\n");
+    }
+
+    #[test]
+    fn test_strip_html_and_sql_comments() {
+        assert_eq!(
+            strip_comments("<p>Hi</p><!-- note -->\n", "html"),
+            "<p>Hi</p>\n"
+        );
+        assert_eq!(
+            strip_comments("SELECT '--' FROM t; -- why\n/* x */\n", "sql"),
+            "SELECT '--' FROM t;\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_notebook() {
+        let content = r##"{
+            "cells": [
+                {"cell_type": "markdown", "metadata": {}, "source": ["# Title\n", "Intro"]},
+                {"cell_type": "code", "execution_count": 1, "metadata": {},
+                 "source": "print('hi')",
+                 "outputs": [{"output_type": "display_data", "data": {"image/png": "iVBORw0KGgo="}}]}
+            ],
+            "metadata": {"language_info": {"name": "python"}},
+            "nbformat": 4
+        }"##;
+
+        let notebook = parse_notebook(content).unwrap();
+        assert_eq!(notebook.language, "python");
+        assert_eq!(notebook.cells.len(), 2);
+        assert!(matches!(&notebook.cells[0], NotebookCell::Markdown(source) if source == "# Title\nIntro"));
+        assert!(matches!(&notebook.cells[1], NotebookCell::Code(source) if source == "print('hi')"));
+    }
+
+    #[test]
+    fn test_parse_malformed_notebook() {
+        assert!(parse_notebook("{\"cells\": 42}").is_err());
+        assert!(parse_notebook("not json").is_err());
+    }
+
+    #[test]
+    fn test_unsupported_language_unchanged() {
+        let content = "# Title\n\n\n// not code\n";
+        assert_eq!(strip_comments(content, "markdown"), content);
+    }
+}
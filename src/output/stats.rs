@@ -0,0 +1,266 @@
+use super::formatter::{estimate_tokens, format_file_size};
+use crate::directory::tree::DirectoryTree;
+use anyhow::Result;
+use serde::Serialize;
+
+/// Summary of the files that would be included in the output
+#[derive(Debug, Serialize)]
+pub struct StatsReport {
+    pub root: String,
+    pub total_files: usize,
+    pub total_size: u64,
+    pub total_lines: usize,
+    pub estimated_tokens: usize,
+    pub languages: Vec<LanguageStats>,
+    pub largest_files: Vec<FileStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub files: usize,
+    pub size: u64,
+    pub percentage: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileStats {
+    pub path: String,
+    pub size: u64,
+}
+
+impl StatsReport {
+    pub fn from_tree(tree: &DirectoryTree) -> Self {
+        let stats = tree.stats();
+        let total_size = stats.total_size;
+
+        let mut languages: Vec<LanguageStats> = stats
+            .languages
+            .into_iter()
+            .map(|(language, (files, size))| LanguageStats {
+                language: language.to_string(),
+                files,
+                size,
+                percentage: if total_size > 0 {
+                    size as f64 * 100.0 / total_size as f64
+                } else {
+                    0.0
+                },
+            })
+            .collect();
+        languages.sort_by(|a, b| {
+            b.files
+                .cmp(&a.files)
+                .then(b.size.cmp(&a.size))
+                .then(a.language.cmp(&b.language))
+        });
+
+        let largest_files = stats
+            .largest_files
+            .into_iter()
+            .map(|(path, size)| FileStats {
+                path: path.to_string_lossy().to_string(),
+                size,
+            })
+            .collect();
+
+        Self {
+            root: tree.root_label(),
+            total_files: stats.total_files,
+            total_size,
+            total_lines: stats.total_lines,
+            estimated_tokens: stats.estimated_tokens,
+            languages,
+            largest_files,
+        }
+    }
+
+    /// Plain text report suitable for a terminal or pager
+    pub fn format_text(&self) -> String {
+        let mut output = String::new();
+        output.push_str(&format!("Root:             {}\n", self.root));
+        output.push_str(&format!("Total files:      {}\n", self.total_files));
+        output.push_str(&format!(
+            "Total size:       {} ({} bytes)\n",
+            format_file_size(self.total_size),
+            self.total_size
+        ));
+        output.push_str(&format!("Total lines:      {}\n", self.total_lines));
+        output.push_str(&format!("Estimated tokens: ~{}\n", self.estimated_tokens));
+
+        if self.languages.is_empty() {
+            return output;
+        }
+
+        output.push_str(&format!(
+            "\n{:<14} {:>7} {:>12} {:>8}\n",
+            "Language", "Files", "Size", "Share"
+        ));
+        for language in &self.languages {
+            output.push_str(&format!(
+                "{:<14} {:>7} {:>12} {:>7.1}%\n",
+                language.language,
+                language.files,
+                format_file_size(language.size),
+                language.percentage
+            ));
+        }
+
+        output.push_str("\nLargest files:\n");
+        for file in &self.largest_files {
+            output.push_str(&format!("{:>12}  {}\n", format_file_size(file.size), file.path));
+        }
+
+        output
+    }
+
+    pub fn format_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Estimated tokens per included file, largest first
+#[derive(Debug)]
+pub struct TokenReport {
+    pub files: Vec<FileTokens>,
+    pub total_files: usize,
+    pub total_size: u64,
+    pub total_tokens: usize,
+}
+
+#[derive(Debug)]
+pub struct FileTokens {
+    pub path: String,
+    pub size: u64,
+    pub tokens: usize,
+}
+
+impl TokenReport {
+    /// Count every included file, listing only the `top` largest if given
+    pub fn from_tree(tree: &DirectoryTree, top: Option<usize>) -> Self {
+        let included_files = tree.get_all_included_files();
+        let total_size: u64 = included_files.iter().filter_map(|node| node.size).sum();
+
+        let mut files: Vec<FileTokens> = included_files
+            .iter()
+            .map(|node| {
+                let size = node.size.unwrap_or(0);
+                FileTokens {
+                    path: tree.relative_path(&node.path).to_string_lossy().to_string(),
+                    size,
+                    tokens: estimate_tokens(size),
+                }
+            })
+            .collect();
+        files.sort_by(|a, b| b.tokens.cmp(&a.tokens).then(a.path.cmp(&b.path)));
+        if let Some(top) = top {
+            files.truncate(top);
+        }
+
+        Self {
+            files,
+            total_files: included_files.len(),
+            total_size,
+            total_tokens: included_files
+                .iter()
+                .map(|node| estimate_tokens(node.size.unwrap_or(0)))
+                .sum(),
+        }
+    }
+
+    /// Aligned table of tokens, bytes and paths with a total row
+    pub fn format_text(&self) -> String {
+        let mut output = format!("{:>10} {:>12}  {}\n", "Tokens", "Bytes", "Path");
+        for file in &self.files {
+            output.push_str(&format!(
+                "{:>10} {:>12}  {}\n",
+                file.tokens, file.size, file.path
+            ));
+        }
+
+        let hidden = self.total_files - self.files.len();
+        if hidden > 0 {
+            output.push_str(&format!(
+                "{:>10} {:>12}  ... {} more files\n",
+                "", "", hidden
+            ));
+        }
+        output.push_str(&format!(
+            "{:>10} {:>12}  total ({} files)\n",
+            self.total_tokens, self.total_size, self.total_files
+        ));
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::state::SelectionState;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_stats_report() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        let files = [("main.rs", 300), ("lib.rs", 100), ("build.py", 600), ("notes", 0)];
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        for (name, size) in files {
+            let path = root_path.join(name);
+            fs::write(&path, "a".repeat(size))?;
+            let index = tree.add_node(path, false, root_path).unwrap();
+            tree.get_node_mut(index).unwrap().size = Some(size as u64);
+        }
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let report = StatsReport::from_tree(&tree);
+
+        // The empty file is not detected as text, so it is never included
+        assert_eq!(report.total_files, 3);
+        assert_eq!(report.total_size, 1000);
+        assert_eq!(report.estimated_tokens, 250);
+        assert_eq!(report.total_lines, 3);
+        assert_eq!(report.languages[0].language, "rust");
+        assert_eq!(report.languages[0].files, 2);
+        assert_eq!(report.languages[1].language, "python");
+        assert!((report.languages[1].percentage - 60.0).abs() < f64::EPSILON);
+        assert_eq!(report.largest_files[0].path, "build.py");
+
+        let json: serde_json::Value = serde_json::from_str(&report.format_json()?)?;
+        assert_eq!(json["total_files"], 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_report() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root_path = temp_dir.path();
+        let files = [("main.rs", 300), ("lib.rs", 101), ("build.py", 600)];
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        for (name, size) in files {
+            let path = root_path.join(name);
+            fs::write(&path, "a".repeat(size))?;
+            let index = tree.add_node(path, false, root_path).unwrap();
+            tree.get_node_mut(index).unwrap().size = Some(size as u64);
+        }
+        tree.set_state(tree.root_index, SelectionState::Included);
+
+        let report = TokenReport::from_tree(&tree, Some(2));
+        let paths: Vec<&str> = report.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["build.py", "main.rs"]);
+        assert_eq!(report.total_files, 3);
+        assert_eq!(report.total_size, 1001);
+        // Each file is rounded up separately
+        assert_eq!(report.total_tokens, 150 + 75 + 26);
+
+        let text = report.format_text();
+        assert!(text.contains("... 1 more files"));
+        assert!(text.ends_with("       251         1001  total (3 files)\n"));
+
+        Ok(())
+    }
+}
@@ -1,14 +1,161 @@
 use super::formatter::OutputFormatter;
 use crate::config::settings::Settings;
 use crate::directory::tree::DirectoryTree;
-use anyhow::Result;
+use anyhow::{Result, bail};
 use arboard::Clipboard;
-use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Clipboard backends tried, in order, when `clipboard_backends` is not configured
+pub const DEFAULT_CLIPBOARD_BACKENDS: &[&str] = &["arboard", "xclip", "xsel", "wl-copy"];
+
+/// A way of reaching the system clipboard
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipboardBackend {
+    /// The `arboard` crate, talking to the clipboard directly
+    Arboard,
+    /// `xclip -selection clipboard`
+    Xclip,
+    /// `xsel --clipboard --input`
+    Xsel,
+    /// `wl-copy` from wl-clipboard
+    WlCopy,
+}
+
+impl FromStr for ClipboardBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "arboard" => Ok(ClipboardBackend::Arboard),
+            "xclip" => Ok(ClipboardBackend::Xclip),
+            "xsel" => Ok(ClipboardBackend::Xsel),
+            "wl-copy" | "wl-clipboard" => Ok(ClipboardBackend::WlCopy),
+            _ => bail!(
+                "Unknown clipboard backend \"{}\" \
+                 (expected \"arboard\", \"xclip\", \"xsel\" or \"wl-copy\")",
+                name
+            ),
+        }
+    }
+}
+
+impl ClipboardBackend {
+    /// Parse `names`, warning about and skipping unknown ones
+    pub fn parse_list(names: &[String]) -> Vec<Self> {
+        names
+            .iter()
+            .filter_map(|name| match name.parse() {
+                Ok(backend) => Some(backend),
+                Err(e) => {
+                    crate::warn!("{}; skipping it", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClipboardBackend::Arboard => "arboard",
+            ClipboardBackend::Xclip => "xclip",
+            ClipboardBackend::Xsel => "xsel",
+            ClipboardBackend::WlCopy => "wl-copy",
+        }
+    }
+
+    /// Program and arguments of command line backends
+    fn command(&self) -> Option<(&'static str, &'static [&'static str])> {
+        match self {
+            ClipboardBackend::Arboard => None,
+            ClipboardBackend::Xclip => Some(("xclip", &["-selection", "clipboard"])),
+            ClipboardBackend::Xsel => Some(("xsel", &["--clipboard", "--input"])),
+            ClipboardBackend::WlCopy => Some(("wl-copy", &[])),
+        }
+    }
+
+    /// Whether the backend can be tried at all: command line tools must be on `PATH`
+    pub fn is_available(&self) -> bool {
+        match self.command() {
+            Some((program, _)) => find_in_path(program).is_some(),
+            None => true,
+        }
+    }
+
+    fn write(&self, content: &str) -> Result<()> {
+        let Some((program, args)) = self.command() else {
+            Clipboard::new()?.set_text(content)?;
+            return Ok(());
+        };
+
+        // The tools keep running to serve the selection, so their output must not be waited on
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(content.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            bail!("{} exited with {}", program, status);
+        }
+        Ok(())
+    }
+}
+
+/// Copy `content` with the first of `backends` that works, warning about each one that fails
+///
+/// Command line tools missing from `PATH` are skipped without a warning.
+pub fn try_write_to_clipboard_with_fallbacks(
+    content: &str,
+    backends: &[ClipboardBackend],
+) -> Result<()> {
+    let mut tried = Vec::new();
+    for backend in backends.iter().filter(|backend| backend.is_available()) {
+        match backend.write(content) {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                crate::warn!("Clipboard backend {} failed: {}", backend.name(), e);
+                tried.push(backend.name());
+            }
+        }
+    }
+
+    if tried.is_empty() {
+        bail!("no clipboard backend is available");
+    }
+    bail!("every clipboard backend failed ({})", tried.join(", "))
+}
+
+/// First executable named `program` in the `PATH` directories
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+/// What to do when an output file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OverwriteMode {
+    /// Fail instead of replacing the file
+    #[default]
+    Refuse,
+    /// Replace the file
+    Force,
+    /// Rename the file to `<name>.bak`, then write a new one
+    Backup,
+}
 
 pub struct OutputWriter {
     formatter: OutputFormatter,
+    append: bool,
+    overwrite: OverwriteMode,
 }
 
 impl Default for OutputWriter {
@@ -21,6 +168,8 @@ impl OutputWriter {
     pub fn new() -> Self {
         Self {
             formatter: OutputFormatter::new(),
+            append: false,
+            overwrite: OverwriteMode::default(),
         }
     }
 
@@ -29,21 +178,98 @@ impl OutputWriter {
         self
     }
 
+    /// Append to existing output files instead of overwriting them
+    pub fn with_append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    pub fn with_overwrite(mut self, overwrite: OverwriteMode) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
     pub fn write_to_file(&self, tree: &DirectoryTree, output_path: &Path) -> Result<()> {
-        let content = self.formatter.format_output(tree)?;
+        if self.append {
+            let content = self.formatter.format_output(tree)?;
+            return Self::write_content(output_path, &content, true);
+        }
+
+        Self::prepare_overwrite(output_path, self.overwrite)?;
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = BufWriter::new(File::create(output_path)?);
+        self.write_to_writer(tree, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
 
+    /// Write the formatted output to any destination, such as a socket or an in-memory buffer
+    ///
+    /// File sections are written as each file is read, unless the output starts with
+    /// something computed from every file; see `OutputFormatter::write_output`.
+    pub fn write_to_writer<W: Write>(&self, tree: &DirectoryTree, writer: &mut W) -> Result<()> {
+        self.formatter.write_output(tree, writer)
+    }
+
+    /// Make way for writing `output_path` according to `mode` if it already exists
+    pub fn prepare_overwrite(output_path: &Path, mode: OverwriteMode) -> Result<()> {
+        if !output_path.exists() {
+            return Ok(());
+        }
+
+        match mode {
+            OverwriteMode::Refuse => bail!(
+                "{} already exists; pass --force to overwrite it or --backup to keep a copy",
+                output_path.display()
+            ),
+            OverwriteMode::Force => Ok(()),
+            OverwriteMode::Backup => {
+                let backup_path = Self::backup_path(output_path);
+                fs::rename(output_path, &backup_path)?;
+                eprintln!("✓ Previous output moved to: {}", backup_path.display());
+                Ok(())
+            }
+        }
+    }
+
+    /// `<name>.bak` next to `output_path`
+    pub fn backup_path(output_path: &Path) -> PathBuf {
+        let mut name = output_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".bak");
+        output_path.with_file_name(name)
+    }
+
+    /// Write `content` to `output_path`, creating parent directories
+    ///
+    /// With `append`, content is added after a `---` separator if the file already has any.
+    pub fn write_content(output_path: &Path, content: &str, append: bool) -> Result<()> {
         // Create parent directories if they don't exist
         if let Some(parent) = output_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        fs::write(output_path, content)?;
+        if !append {
+            fs::write(output_path, content)?;
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(output_path)?;
+        if file.metadata()?.len() > 0 {
+            file.write_all(b"\n---\n\n")?;
+        }
+        file.write_all(content.as_bytes())?;
         Ok(())
     }
 
     pub fn write_to_stdout(&self, tree: &DirectoryTree) -> Result<()> {
-        let content = self.formatter.format_output(tree)?;
-        print!("{}", content);
+        let mut stdout = io::stdout().lock();
+        self.write_to_writer(tree, &mut stdout)?;
+        stdout.flush()?;
         Ok(())
     }
 
@@ -51,13 +277,14 @@ impl OutputWriter {
         let content = self.formatter.format_output(tree)?;
 
         if content.len() <= settings.max_clipboard_size {
-            match self.try_write_to_clipboard(&content) {
+            let backends = ClipboardBackend::parse_list(&settings.clipboard_backends);
+            match try_write_to_clipboard_with_fallbacks(&content, &backends) {
                 Ok(()) => {
-                    println!("✓ Output copied to clipboard ({} bytes)", content.len());
+                    eprintln!("✓ Output copied to clipboard ({} bytes)", content.len());
                     return Ok(());
                 }
                 Err(e) => {
-                    eprintln!("⚠ Failed to copy to clipboard: {}", e);
+                    crate::warn!("Failed to copy to clipboard: {}", e);
                     eprintln!("Falling back to file prompt...");
                 }
             }
@@ -67,42 +294,36 @@ impl OutputWriter {
         self.prompt_and_save_to_file(tree, &content, settings)
     }
 
-    fn try_write_to_clipboard(&self, content: &str) -> Result<()> {
-        let mut clipboard = Clipboard::new()?;
-        clipboard.set_text(content)?;
-        Ok(())
-    }
-
     fn prompt_and_save_to_file(&self, tree: &DirectoryTree, content: &str, settings: &Settings) -> Result<()> {
         if content.len() > settings.max_clipboard_size {
-            println!(
-                "⚠ Output is too large for clipboard ({} bytes > {})",
+            crate::warn!(
+                "Output is too large for clipboard ({} bytes > {})",
                 content.len(),
                 settings.format_clipboard_size()
             );
         }
 
-        print!("Enter filename to save output (or press Enter for default): ");
-        io::stdout().flush()?;
+        eprint!("Enter filename to save output (or press Enter for default): ");
+        io::stderr().flush()?;
 
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
         let input = input.trim();
 
-        let filename = if input.is_empty() {
-            Self::generate_default_filename(tree)
+        let path = if input.is_empty() {
+            let project_root = &tree.nodes[tree.root_index].path;
+            Self::default_output_path(tree, settings.output_dir(project_root).as_deref())
         } else {
             // Add .md extension if not present
             if input.ends_with(".md") {
-                input.to_string()
+                PathBuf::from(input)
             } else {
-                format!("{}.md", input)
+                PathBuf::from(format!("{}.md", input))
             }
         };
 
-        let path = Path::new(&filename);
-        self.write_to_file(tree, path)?;
-        println!("✓ Output saved to: {}", path.display());
+        self.write_to_file(tree, &path)?;
+        eprintln!("✓ Output saved to: {}", path.display());
         Ok(())
     }
 
@@ -116,12 +337,65 @@ impl OutputWriter {
         let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
         format!("{}_ingest_{}.md", root_name, timestamp)
     }
+
+    /// Default file name, placed in `output_dir` when one is configured
+    pub fn default_output_path(tree: &DirectoryTree, output_dir: Option<&Path>) -> PathBuf {
+        let filename = Self::generate_default_filename(tree);
+        match output_dir {
+            Some(output_dir) => output_dir.join(filename),
+            None => PathBuf::from(filename),
+        }
+    }
+
+    /// Create `dir` if it is missing and check that files can be written to it
+    pub fn ensure_writable_dir(dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let probe = dir.join(".gthr-write-test");
+        File::create(&probe)?;
+        fs::remove_file(&probe)?;
+        Ok(())
+    }
+
+    /// Default file for a list of the included paths, named after the default output
+    pub fn default_path_list_path(tree: &DirectoryTree, output_dir: Option<&Path>) -> PathBuf {
+        Self::default_output_path(tree, output_dir).with_extension("paths.txt")
+    }
+
+    /// Command that opens `path` in `editor`, or with the platform's default application
+    ///
+    /// `editor` is run through the shell, so values like `code --wait` work as in `$EDITOR`.
+    pub fn open_command(path: &Path, editor: Option<&str>) -> Command {
+        let mut command = match editor {
+            Some(editor) if cfg!(windows) => {
+                let mut command = Command::new("cmd");
+                command.args(["/C", editor]);
+                command
+            }
+            Some(editor) => {
+                let mut command = Command::new("sh");
+                command
+                    .arg("-c")
+                    .arg(format!("{} \"$1\"", editor))
+                    .arg("sh");
+                command
+            }
+            None if cfg!(windows) => {
+                let mut command = Command::new("cmd");
+                command.args(["/C", "start", ""]);
+                command
+            }
+            None if cfg!(target_os = "macos") => Command::new("open"),
+            None => Command::new("xdg-open"),
+        };
+        command.arg(path);
+        command
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
+    use crate::directory::state::SelectionState;
     use tempfile::TempDir;
 
     #[test]
@@ -133,5 +407,153 @@ mod tests {
         assert!(filename.ends_with(".md"));
         assert!(filename.contains("ingest"));
     }
+
+    #[test]
+    fn test_write_content_append() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("out").join("context.md");
+
+        // A missing file is created as usual
+        OutputWriter::write_content(&path, "# First\n", true)?;
+        assert_eq!(fs::read_to_string(&path)?, "# First\n");
+
+        OutputWriter::write_content(&path, "# Second\n", true)?;
+        assert_eq!(fs::read_to_string(&path)?, "# First\n\n---\n\n# Second\n");
+
+        OutputWriter::write_content(&path, "# Third\n", false)?;
+        assert_eq!(fs::read_to_string(&path)?, "# Third\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_overwrite() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("context.md");
+
+        // Nothing to protect yet
+        OutputWriter::prepare_overwrite(&path, OverwriteMode::Refuse)?;
+
+        fs::write(&path, "previous export")?;
+        let error = OutputWriter::prepare_overwrite(&path, OverwriteMode::Refuse).unwrap_err();
+        assert!(error.to_string().contains("already exists"));
+        assert_eq!(fs::read_to_string(&path)?, "previous export");
+
+        OutputWriter::prepare_overwrite(&path, OverwriteMode::Force)?;
+        assert_eq!(fs::read_to_string(&path)?, "previous export");
+
+        OutputWriter::prepare_overwrite(&path, OverwriteMode::Backup)?;
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("context.md.bak"))?,
+            "previous export"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to_file_refuses_to_overwrite() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}")?;
+        let mut tree = DirectoryTree::new(temp_dir.path().to_path_buf());
+        tree.add_node(temp_dir.path().join("main.rs"), false, temp_dir.path());
+        let path = temp_dir.path().join("out.md");
+        fs::write(&path, "previous export")?;
+
+        assert!(OutputWriter::new().write_to_file(&tree, &path).is_err());
+        assert_eq!(fs::read_to_string(&path)?, "previous export");
+
+        OutputWriter::new()
+            .with_overwrite(OverwriteMode::Force)
+            .write_to_file(&tree, &path)?;
+        assert_ne!(fs::read_to_string(&path)?, "previous export");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clipboard_backends() {
+        let names: Vec<String> =
+            DEFAULT_CLIPBOARD_BACKENDS.iter().map(|name| name.to_string()).collect();
+        let backends = ClipboardBackend::parse_list(&names);
+        let parsed: Vec<&str> = backends.iter().map(ClipboardBackend::name).collect();
+        assert_eq!(parsed, DEFAULT_CLIPBOARD_BACKENDS);
+        assert_eq!("wl-clipboard".parse::<ClipboardBackend>().unwrap(), ClipboardBackend::WlCopy);
+        assert!("pbcopy".parse::<ClipboardBackend>().is_err());
+
+        assert!(ClipboardBackend::Arboard.is_available());
+        let error = try_write_to_clipboard_with_fallbacks("text", &[]).unwrap_err();
+        assert_eq!(error.to_string(), "no clipboard backend is available");
+    }
+
+    #[test]
+    fn test_write_to_writer() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}")?;
+        let mut tree = DirectoryTree::new(temp_dir.path().to_path_buf());
+        tree.add_node(temp_dir.path().join("main.rs"), false, temp_dir.path());
+        tree.set_state(tree.root_index, SelectionState::Included);
+        let writer = OutputWriter::new();
+
+        let mut buffer = Vec::new();
+        writer.write_to_writer(&tree, &mut buffer)?;
+        let output = String::from_utf8(buffer)?;
+        assert!(output.contains("fn main() {}"));
+
+        // Files get exactly the same bytes
+        let path = temp_dir.path().join("out").join("context.md");
+        writer.write_to_file(&tree, &path)?;
+        assert_eq!(fs::read_to_string(&path)?, output);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_open_command() {
+        let path = Path::new("out dir/context.md");
+
+        let command = OutputWriter::open_command(path, Some("code --wait"));
+        assert_eq!(command.get_program(), "sh");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            ["-c", "code --wait \"$1\"", "sh", "out dir/context.md"]
+        );
+
+        let command = OutputWriter::open_command(path, None);
+        assert_ne!(command.get_program(), "sh");
+        assert_eq!(command.get_args().last(), Some(path.as_os_str()));
+    }
+
+    #[test]
+    fn test_default_output_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let tree = DirectoryTree::new(temp_dir.path().to_path_buf());
+
+        let path = OutputWriter::default_output_path(&tree, None);
+        assert_eq!(path.parent(), Some(Path::new("")));
+
+        let output_dir = temp_dir.path().join("exports");
+        let path = OutputWriter::default_output_path(&tree, Some(&output_dir));
+        assert_eq!(path.parent(), Some(output_dir.as_path()));
+        assert!(path.to_string_lossy().ends_with(".md"));
+    }
+
+    #[test]
+    fn test_ensure_writable_dir() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let output_dir = temp_dir.path().join("exports/gthr");
+        OutputWriter::ensure_writable_dir(&output_dir)?;
+        assert!(output_dir.is_dir());
+        assert_eq!(fs::read_dir(&output_dir)?.count(), 0);
+
+        // A file in the way cannot be turned into a directory
+        let file = temp_dir.path().join("notes.txt");
+        fs::write(&file, "notes")?;
+        assert!(OutputWriter::ensure_writable_dir(&file).is_err());
+        Ok(())
+    }
 }
 
@@ -1,13 +1,87 @@
+use crate::constants::{
+    DOUBLE_CLICK_MS, MAX_UNDO_HISTORY, MOUSE_SCROLL_ROWS, STATUS_MESSAGE_DURATION_MS,
+};
+use crate::config::settings::Settings;
+use crate::directory::diff::FileFingerprint;
 use crate::directory::state::SelectionState;
+use crate::directory::stats::{FileEstimate, LineCounter};
 use crate::directory::tree::DirectoryTree;
-use crate::fuzzy::filter::{FilteredResults, filter_tree_nodes};
+use crate::fuzzy::content::{ContentMatch, ContentSearch};
+use crate::fuzzy::filter::{
+    FilteredResults, SearchMode, SortOrder, StateFilter, filter_by_state, filter_tree_nodes,
+    flatten_tree, sort_results,
+};
+use crate::fuzzy::matcher::FuzzySearch;
+use crate::error::GthrError;
+use crate::output::formatter::{
+    PathStyle, format_count, format_file_list, format_file_size, format_styled_path_list,
+    format_token_count,
+};
+use crate::output::writer::OutputWriter;
 use crate::ui::colors::ColorScheme;
+use crate::ui::command_palette::CommandPalette;
+use crate::ui::events::AppAction;
+use crate::ui::preview::FilePreview;
+use crate::ui::selection_summary::SelectionSummary;
+use crate::ui::settings_panel::SettingsPanel;
+use anyhow::Result;
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Selection state of every node, parallel to `tree.nodes`
+pub type SelectionSnapshot = Vec<SelectionState>;
+
+/// Saved state of one node; files also record their fingerprint for `--diff-against`
+///
+/// Sessions saved before fingerprints were added hold a bare state for every node.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SessionEntry {
+    File {
+        state: SelectionState,
+        #[serde(flatten)]
+        fingerprint: FileFingerprint,
+    },
+    Directory(SelectionState),
+}
+
+impl SessionEntry {
+    fn state(&self) -> SelectionState {
+        match self {
+            Self::File { state, .. } | Self::Directory(state) => *state,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppMode {
     Main,
     Help,
     FileSave,
+    ContentSearch,
+    ConfirmOverwrite,
+    /// Browsing the settings panel
+    Settings,
+    /// Typing a new value for the selected setting
+    SettingsEdit,
+    /// Searching the actions of the command palette
+    CommandPalette,
+    /// Listing the included files
+    SelectionSummary,
+}
+
+/// Where plain keys go in the main screen, switched with Tab
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Focus {
+    /// Characters are typed into the search
+    #[default]
+    Search,
+    /// Characters navigate the list, vim style
+    List,
 }
 
 pub struct App {
@@ -16,12 +90,77 @@ pub struct App {
     pub selected_index: usize,
     pub scroll_offset: usize,
     pub search_query: String,
+    pub search_mode: SearchMode,
+    pub sort_order: SortOrder,
+    pub state_filter: StateFilter,
     pub mode: AppMode,
+    pub focus: Focus,
     pub color_scheme: ColorScheme,
     pub should_quit: bool,
+    /// Whether any output was exported, so quitting without it counts as cancelling
+    pub exported: bool,
     pub viewport_height: usize,
+    /// Where the file list was last drawn, borders included, for mapping clicks to rows
+    pub file_list_area: Rect,
+    /// Row and time of the last click, to detect double-clicks
+    last_click: Option<(usize, Instant)>,
     pub file_save_input: String,
+    /// Where the save dialog writes when no path is entered
+    pub default_save_path: PathBuf,
+    pub output_dir: Option<PathBuf>,
+    /// `-o` path, pre-filled in the save dialog
+    pub output_path: Option<PathBuf>,
+    /// `--export-paths` file Alt+P writes the included paths to
+    pub export_paths: Option<PathBuf>,
+    /// How paths are written in the output and in path lists
+    pub path_style: PathStyle,
+    /// Existing file the user is asked to confirm overwriting
+    pub overwrite_path: Option<PathBuf>,
+    pub mode_before_overwrite: AppMode,
     pub pending_content: Option<String>,
+    /// File the output was written to, opened with `--open` once the terminal is restored
+    pub saved_path: Option<PathBuf>,
+    pub fuzzy_search: FuzzySearch,
+    pub undo_stack: Vec<SelectionSnapshot>,
+    pub redo_stack: Vec<SelectionSnapshot>,
+    pub status_message: Option<(String, Instant)>,
+    pub content_query: String,
+    pub content_search: Option<ContentSearch>,
+    pub mode_before_help: AppMode,
+    pub max_depth: Option<usize>,
+    pub max_total_size_warning: Option<u64>,
+    pub token_warn_threshold: Option<usize>,
+    pub show_preview: bool,
+    /// Leave the file tree out of the export, toggled with Ctrl+O
+    pub no_tree: bool,
+    /// Show the hierarchy instead of the flat list while no search is typed
+    pub tree_view: bool,
+    /// Directories whose children are shown in the tree view
+    pub expanded: HashSet<usize>,
+    /// Whether the tree was traversed respecting `.gitignore`, toggled with Ctrl+F
+    pub respect_gitignore: bool,
+    /// `--profile` applied to the settings, shown in the status bar
+    pub profile: Option<String>,
+    /// File previews by tree index, read the first time each file is shown
+    preview_cache: HashMap<usize, FilePreview>,
+    /// Size, tokens and lines of the included files, updated as their states change
+    totals: SelectionTotals,
+    /// Counts the lines of newly included or changed files in the background
+    line_counter: LineCounter,
+    pub settings: Settings,
+    /// Project config file the settings panel saves to
+    pub settings_path: PathBuf,
+    /// Open settings panel, opened with Ctrl+,
+    pub settings_panel: Option<SettingsPanel>,
+    pub mode_before_settings: AppMode,
+    /// Open command palette, opened with Ctrl+Shift+P
+    pub command_palette: Option<CommandPalette>,
+    pub mode_before_palette: AppMode,
+    /// Open selection summary, opened with Ctrl+V
+    pub selection_summary: Option<SelectionSummary>,
+    pub mode_before_summary: AppMode,
+    /// Set when the settings panel changed `settings`, until the export is rebuilt
+    settings_changed: bool,
 }
 
 impl App {
@@ -32,20 +171,153 @@ impl App {
             selected_index: 0,
             scroll_offset: 0,
             search_query: String::new(),
+            search_mode: SearchMode::default(),
+            sort_order: SortOrder::default(),
+            state_filter: StateFilter::default(),
             mode: AppMode::Main,
+            focus: Focus::Search,
             color_scheme: ColorScheme::default(),
             should_quit: false,
+            exported: false,
             viewport_height: 20, // Default, will be updated by UI
+            file_list_area: Rect::default(),
+            last_click: None,
             file_save_input: String::new(),
+            default_save_path: PathBuf::new(),
+            output_dir: None,
+            output_path: None,
+            export_paths: None,
+            path_style: PathStyle::default(),
+            overwrite_path: None,
+            mode_before_overwrite: AppMode::Main,
             pending_content: None,
+            saved_path: None,
+            fuzzy_search: FuzzySearch::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            status_message: None,
+            content_query: String::new(),
+            content_search: None,
+            mode_before_help: AppMode::Main,
+            max_depth: None,
+            max_total_size_warning: None,
+            token_warn_threshold: None,
+            show_preview: false,
+            no_tree: false,
+            tree_view: false,
+            expanded: HashSet::new(),
+            respect_gitignore: true,
+            profile: None,
+            preview_cache: HashMap::new(),
+            totals: SelectionTotals::default(),
+            line_counter: LineCounter::spawn(),
+            settings: Settings::default(),
+            settings_path: PathBuf::new(),
+            settings_panel: None,
+            mode_before_settings: AppMode::Main,
+            command_palette: None,
+            mode_before_palette: AppMode::Main,
+            selection_summary: None,
+            mode_before_summary: AppMode::Main,
+            settings_changed: false,
         };
 
+        app.expanded.insert(app.tree.root_index);
+        app.refresh_totals();
         app.update_filtered_results();
         app
     }
 
+    /// Depth limit the tree was traversed with, shown in the status bar
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Start with a search already typed, as if the user had entered it
+    pub fn with_query(mut self, query: impl Into<String>) -> Self {
+        self.search_query = query.into();
+        self.update_filtered_results();
+        self
+    }
+
+    pub fn with_output_dir(mut self, output_dir: Option<PathBuf>) -> Self {
+        self.output_dir = output_dir;
+        self
+    }
+
+    pub fn with_output_path(mut self, output_path: Option<PathBuf>) -> Self {
+        self.output_path = output_path;
+        self
+    }
+
+    pub fn with_export_paths(mut self, export_paths: Option<PathBuf>) -> Self {
+        self.export_paths = export_paths;
+        self
+    }
+
+    pub fn with_path_style(mut self, path_style: PathStyle) -> Self {
+        self.path_style = path_style;
+        self
+    }
+
+    /// Score fuzzy searches with `fuzzy_search` instead of the default skim matcher
+    pub fn with_fuzzy_search(mut self, fuzzy_search: FuzzySearch) -> Self {
+        self.fuzzy_search = fuzzy_search;
+        self.update_filtered_results();
+        self
+    }
+
+    pub fn with_token_warn_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.token_warn_threshold = threshold;
+        self
+    }
+
+    /// Start with the file tree left out, as with `--no-tree`
+    pub fn with_no_tree(mut self, no_tree: bool) -> Self {
+        self.no_tree = no_tree;
+        self
+    }
+
+    /// Whether the tree was traversed respecting `.gitignore`, shown in the status bar
+    pub fn with_respect_gitignore(mut self, respect_gitignore: bool) -> Self {
+        self.respect_gitignore = respect_gitignore;
+        self
+    }
+
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_max_total_size_warning(mut self, threshold: Option<u64>) -> Self {
+        self.max_total_size_warning = threshold;
+        self
+    }
+
+    /// Settings shown in the settings panel, which saves to the config file at `path`
+    pub fn with_settings(mut self, settings: Settings, path: PathBuf) -> Self {
+        self.settings = settings;
+        self.settings_path = path;
+        self
+    }
+
     pub fn update_filtered_results(&mut self) {
-        self.filtered_results = filter_tree_nodes(&self.tree, &self.search_query);
+        self.filtered_results = if self.is_tree_view_active() {
+            let mut results = flatten_tree(&self.tree, &self.expanded);
+            filter_by_state(&mut results, &self.tree, self.state_filter);
+            results
+        } else {
+            let mut results = filter_tree_nodes(
+                &self.tree,
+                &self.search_query,
+                self.search_mode,
+                &self.fuzzy_search,
+            );
+            sort_results(&mut results, &self.tree, self.sort_order);
+            filter_by_state(&mut results, &self.tree, self.state_filter);
+            results
+        };
 
         // Reset scroll position when search changes
         self.scroll_offset = 0;
@@ -61,6 +333,18 @@ impl App {
     }
 
     pub fn move_up(&mut self) {
+        if let Some(panel) = &mut self.settings_panel {
+            panel.move_up();
+            return;
+        }
+        if let Some(palette) = &mut self.command_palette {
+            palette.move_up();
+            return;
+        }
+        if let Some(summary) = &mut self.selection_summary {
+            summary.move_up();
+            return;
+        }
         if self.selected_index > 0 {
             self.selected_index -= 1;
             self.update_scroll_for_move_up();
@@ -68,6 +352,18 @@ impl App {
     }
 
     pub fn move_down(&mut self) {
+        if let Some(panel) = &mut self.settings_panel {
+            panel.move_down();
+            return;
+        }
+        if let Some(palette) = &mut self.command_palette {
+            palette.move_down();
+            return;
+        }
+        if let Some(summary) = &mut self.selection_summary {
+            summary.move_down();
+            return;
+        }
         if self.selected_index + 1 < self.filtered_results.len() {
             self.selected_index += 1;
             self.update_scroll_for_move_down();
@@ -119,6 +415,52 @@ impl App {
         }
     }
 
+    /// Scroll the list by whole wheel steps, keeping the cursor on a visible row
+    pub fn scroll(&mut self, down: bool) {
+        let max_offset = self
+            .filtered_results
+            .len()
+            .saturating_sub(self.viewport_height);
+        self.scroll_offset = if down {
+            (self.scroll_offset + MOUSE_SCROLL_ROWS).min(max_offset)
+        } else {
+            self.scroll_offset.saturating_sub(MOUSE_SCROLL_ROWS)
+        };
+        let last_visible = self.scroll_offset + self.viewport_height.saturating_sub(1);
+        self.selected_index = self.selected_index.clamp(self.scroll_offset, last_visible);
+    }
+
+    /// Move the cursor to the clicked row; a double-click or a click on the ✓/✗ marker
+    /// toggles it. Clicks outside the list are ignored.
+    pub fn click(&mut self, column: u16, row: u16) {
+        let area = self.file_list_area;
+        let inside = column > area.x
+            && column + 1 < area.right()
+            && row > area.y
+            && row + 1 < area.bottom();
+        if !inside {
+            return;
+        }
+        let index = self.scroll_offset + (row - area.y - 1) as usize;
+        if index >= self.filtered_results.len() {
+            return;
+        }
+
+        let now = Instant::now();
+        let double_click = self.last_click.is_some_and(|(last_index, at)| {
+            last_index == index && now.duration_since(at) <= Duration::from_millis(DOUBLE_CLICK_MS)
+        });
+        // Rows start with the two-column cursor, then the marker and a space
+        let on_marker = matches!(column - area.x - 1, 2 | 3);
+        self.selected_index = index;
+        if double_click || on_marker {
+            self.toggle_selection();
+            self.last_click = None;
+        } else {
+            self.last_click = Some((index, now));
+        }
+    }
+
     fn update_scroll_for_move_up(&mut self) {
         // If the selected index is now above the visible area, scroll up
         if self.selected_index < self.scroll_offset {
@@ -142,12 +484,190 @@ impl App {
         }
     }
 
+    /// Whether rows come from the tree view; typing a search falls back to the flat list
+    pub fn is_tree_view_active(&self) -> bool {
+        self.tree_view && self.search_query.is_empty() && self.mode != AppMode::ContentSearch
+    }
+
+    /// Switch between the flat list and the tree view, keeping the cursor on its item
+    pub fn toggle_tree_view(&mut self) {
+        let current = self.get_selected_tree_index();
+        self.tree_view = !self.tree_view;
+        if self.is_tree_view_active()
+            && let Some(index) = current
+        {
+            // Expand the ancestors of the item so it stays visible
+            self.expand_ancestors(index);
+        }
+        self.update_filtered_results();
+        if let Some(index) = current {
+            self.select_tree_index(index);
+        }
+    }
+
+    fn expand_ancestors(&mut self, index: usize) {
+        let mut parent = self.tree.get_node(index).and_then(|node| node.parent);
+        while let Some(parent_index) = parent {
+            self.expanded.insert(parent_index);
+            parent = self.tree.get_node(parent_index).and_then(|node| node.parent);
+        }
+    }
+
+    /// Collapse the directory under the cursor, or move to its parent; moves up in the flat list
+    pub fn collapse(&mut self) {
+        if !self.is_tree_view_active() {
+            self.move_up();
+            return;
+        }
+        let Some(index) = self.get_selected_tree_index() else {
+            return;
+        };
+        if self.expanded.remove(&index) {
+            self.update_filtered_results();
+            self.select_tree_index(index);
+        } else if let Some(parent) = self.tree.get_node(index).and_then(|node| node.parent) {
+            self.select_tree_index(parent);
+        }
+    }
+
+    /// Expand the directory under the cursor, or move down; moves down in the flat list
+    pub fn expand(&mut self) {
+        let selected = self.get_selected_tree_index();
+        let collapsed_directory = selected.filter(|index| {
+            self.tree.get_node(*index).is_some_and(|node| node.is_directory)
+                && !self.expanded.contains(index)
+        });
+        match collapsed_directory {
+            Some(index) if self.is_tree_view_active() => {
+                self.expanded.insert(index);
+                self.update_filtered_results();
+                self.select_tree_index(index);
+            }
+            _ => self.move_down(),
+        }
+    }
+
+    /// Swap in a rescanned tree, keeping the selection of files found in both
+    ///
+    /// The cursor and expanded directories follow their paths; when the file under the
+    /// cursor is gone, the cursor stays on the same row. Undo history refers to node
+    /// indices, so it is cleared. Returns the numbers of files added and removed.
+    pub fn replace_tree(&mut self, mut tree: DirectoryTree) -> (usize, usize) {
+        let changes = tree.keep_selection_from(&self.tree);
+
+        let expanded: HashSet<&Path> = self
+            .expanded
+            .iter()
+            .map(|&index| self.tree.nodes[index].path.as_path())
+            .collect();
+        let new_expanded: HashSet<usize> = tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| expanded.contains(node.path.as_path()))
+            .map(|(index, _)| index)
+            .collect();
+        let selected_path = self
+            .get_selected_tree_index()
+            .map(|index| self.tree.nodes[index].path.clone());
+
+        self.expanded = new_expanded;
+        self.expanded.insert(tree.root_index);
+        self.tree = tree;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.preview_cache.clear();
+        self.refresh_totals();
+        if self.mode == AppMode::ContentSearch {
+            self.run_content_search();
+        } else {
+            self.update_filtered_results();
+        }
+        if let Some(path) = selected_path
+            && let Some(&index) = self.tree.path_to_index.get(&path)
+        {
+            self.select_tree_index(index);
+        }
+        changes
+    }
+
+    /// Move the cursor to the row showing `tree_index`, if it is visible
+    fn select_tree_index(&mut self, tree_index: usize) {
+        if let Some(row) = self
+            .filtered_results
+            .visible_items
+            .iter()
+            .position(|&index| index == tree_index)
+        {
+            self.selected_index = row;
+            self.update_scroll();
+        }
+    }
+
     pub fn toggle_selection(&mut self) {
         if let Some(tree_index) = self.get_selected_tree_index() {
-            self.tree.toggle_state(tree_index);
+            self.push_undo_snapshot();
+            self.toggle_state(tree_index);
+            self.refresh_state_filter();
         }
     }
 
+    /// Set the state of a node and its descendants, updating the totals of the files changed
+    fn set_state(&mut self, tree_index: usize, state: SelectionState) {
+        let files = self.tree.file_indices(tree_index);
+        self.tree.set_state(tree_index, state);
+        self.update_totals(&files);
+    }
+
+    fn toggle_state(&mut self, tree_index: usize) {
+        let files = self.tree.file_indices(tree_index);
+        self.tree.toggle_state(tree_index);
+        self.update_totals(&files);
+    }
+
+    /// Count newly included text files in the totals and take excluded ones out again
+    fn update_totals(&mut self, indices: &[usize]) {
+        for &index in indices {
+            let node = &mut self.tree.nodes[index];
+            if node.is_directory || !node.is_text_file {
+                continue;
+            } else if !node.state.is_included() {
+                self.totals.remove(index);
+            } else if !self.totals.counted.contains_key(&index) {
+                let estimate = node.estimate();
+                if estimate.lines.is_none() {
+                    self.line_counter.request(index, node.path.clone(), estimate);
+                }
+                self.totals.add(index, estimate);
+            }
+        }
+    }
+
+    /// Add the line counts finished in the background to the files and the totals
+    pub fn poll_line_counts(&mut self) {
+        for (index, counted) in self.line_counter.drain() {
+            let Some(node) = self.tree.get_node_mut(index) else {
+                continue;
+            };
+            // Counts of a file that changed since it was requested are stale
+            if node.estimate.is_some_and(|estimate| estimate.same_file(&counted)) {
+                node.estimate = Some(counted);
+            }
+            if self.totals.counted.get(&index).is_some_and(|e| e.same_file(&counted)) {
+                self.totals.add(index, counted);
+            }
+        }
+    }
+
+    /// Recount the totals from scratch, after the tree was replaced
+    pub fn refresh_totals(&mut self) {
+        self.totals = SelectionTotals::default();
+        // Requests still queued refer to indices of the previous tree
+        self.line_counter = LineCounter::spawn();
+        let files = self.tree.file_indices(self.tree.root_index);
+        self.update_totals(&files);
+    }
+
     pub fn get_selected_tree_index(&self) -> Option<usize> {
         self.filtered_results
             .visible_items
@@ -155,32 +675,225 @@ impl App {
             .copied()
     }
 
+    /// Include every visible file; directory states follow from their children
     pub fn select_all(&mut self) {
-        for &tree_index in &self.filtered_results.visible_items {
-            self.tree.set_state(tree_index, SelectionState::Included);
+        self.push_undo_snapshot();
+        for tree_index in self.filtered_results.visible_items.clone() {
+            if !self.tree.nodes[tree_index].is_directory {
+                self.set_state(tree_index, SelectionState::Included);
+            }
         }
+        self.report_bulk_change("Selected");
+        self.refresh_state_filter();
     }
 
+    /// Exclude every visible file; directory states follow from their children
     pub fn select_none(&mut self) {
-        for &tree_index in &self.filtered_results.visible_items {
-            self.tree.set_state(tree_index, SelectionState::Excluded);
+        self.push_undo_snapshot();
+        for tree_index in self.filtered_results.visible_items.clone() {
+            if !self.tree.nodes[tree_index].is_directory {
+                self.set_state(tree_index, SelectionState::Excluded);
+            }
         }
+        self.report_bulk_change("Deselected");
+        self.refresh_state_filter();
     }
 
+    /// Toggle every visible file; directory states follow from their children
     pub fn invert_selection(&mut self) {
-        for &tree_index in &self.filtered_results.visible_items {
-            self.tree.toggle_state(tree_index);
+        self.push_undo_snapshot();
+        for tree_index in self.filtered_results.visible_items.clone() {
+            if !self.tree.nodes[tree_index].is_directory {
+                self.toggle_state(tree_index);
+            }
+        }
+        self.report_bulk_change("Inverted");
+        self.refresh_state_filter();
+    }
+
+    /// Show how many files a bulk operation changed, compared to the last undo snapshot
+    fn report_bulk_change(&mut self, verb: &str) {
+        let changed = self.undo_stack.last().map_or(0, |before| {
+            self.tree
+                .nodes
+                .iter()
+                .zip(before)
+                .filter(|(node, state)| !node.is_directory && node.state != **state)
+                .count()
+        });
+        let noun = if changed == 1 { "file" } else { "files" };
+        self.set_status_message(format!("{} {} {}", verb, changed, noun));
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo_stack.pop() {
+            self.redo_stack.push(self.snapshot_selection());
+            self.restore_selection(&snapshot);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.redo_stack.pop() {
+            self.undo_stack.push(self.snapshot_selection());
+            self.restore_selection(&snapshot);
         }
     }
 
+    /// Record the current selection before a change so it can be undone
+    fn push_undo_snapshot(&mut self) {
+        self.undo_stack.push(self.snapshot_selection());
+        if self.undo_stack.len() > MAX_UNDO_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    fn snapshot_selection(&self) -> SelectionSnapshot {
+        self.tree.nodes.iter().map(|node| node.state).collect()
+    }
+
+    fn restore_selection(&mut self, snapshot: &SelectionSnapshot) {
+        let mut changed = Vec::new();
+        for (index, (node, state)) in self.tree.nodes.iter_mut().zip(snapshot).enumerate() {
+            if node.state != *state {
+                node.state = *state;
+                changed.push(index);
+            }
+        }
+        self.update_totals(&changed);
+        self.refresh_state_filter();
+    }
+
     pub fn add_search_char(&mut self, c: char) {
-        self.search_query.push(c);
-        self.update_filtered_results();
+        if self.mode == AppMode::ContentSearch {
+            self.content_query.push(c);
+            self.run_content_search();
+        } else {
+            self.search_query.push(c);
+            self.update_filtered_results();
+        }
     }
 
     pub fn search_backspace(&mut self) {
-        self.search_query.pop();
+        if self.mode == AppMode::ContentSearch {
+            self.content_query.pop();
+            self.run_content_search();
+        } else {
+            self.search_query.pop();
+            self.update_filtered_results();
+        }
+    }
+
+    /// Switch between searching paths and searching inside files
+    pub fn toggle_content_search(&mut self) {
+        if self.mode == AppMode::ContentSearch {
+            self.mode = AppMode::Main;
+            self.content_search = None;
+            self.selected_index = 0;
+            self.update_filtered_results();
+        } else {
+            self.mode = AppMode::ContentSearch;
+            self.run_content_search();
+        }
+    }
+
+    /// Restart the content search for the current query; must be called inside a Tokio runtime
+    fn run_content_search(&mut self) {
+        // Dropping the previous search cancels it
+        self.content_search = None;
+        self.filtered_results = FilteredResults::new();
+        self.selected_index = 0;
+        self.scroll_offset = 0;
+
+        if self.content_query.is_empty() {
+            return;
+        }
+
+        match ContentSearch::spawn(&self.tree, &self.content_query) {
+            Ok(search) => self.content_search = Some(search),
+            Err(e) => self.set_status_message(format!("Content search failed: {}", e)),
+        }
+    }
+
+    /// Move matches found by the running content search into the file list
+    pub fn poll_content_search(&mut self) {
+        let Some(search) = &self.content_search else {
+            return;
+        };
+
+        // Check before draining so no match sent before completion is missed
+        let finished = search.is_finished();
+        for content_match in search.drain() {
+            self.filtered_results.push_content_match(content_match);
+        }
+        if finished {
+            self.content_search = None;
+        }
+    }
+
+    pub fn is_content_search_running(&self) -> bool {
+        self.content_search.is_some()
+    }
+
+    /// The content search match under the cursor, if any
+    pub fn selected_content_match(&self) -> Option<&ContentMatch> {
+        self.filtered_results
+            .content_matches
+            .get(self.selected_index)
+    }
+
+    pub fn cycle_search_mode(&mut self) {
+        self.search_mode = self.search_mode.next();
+        self.update_filtered_results();
+    }
+
+    /// Cycle the order of the flat list, keeping the cursor on the same item
+    ///
+    /// Content search results keep arriving in search order and are not re-sorted.
+    pub fn cycle_sort_order(&mut self) {
+        self.sort_order = self.sort_order.next();
+        if self.mode == AppMode::ContentSearch {
+            return;
+        }
+
+        let selected = self.get_selected_tree_index();
+        self.update_filtered_results();
+        if let Some(position) = selected.and_then(|selected| {
+            self.filtered_results
+                .visible_items
+                .iter()
+                .position(|&tree_index| tree_index == selected)
+        }) {
+            self.selected_index = position;
+            self.update_scroll();
+        }
+    }
+
+    /// Cycle between showing all items, only included ones and only excluded ones
+    pub fn cycle_state_filter(&mut self) {
+        self.state_filter = self.state_filter.next();
+        if self.mode == AppMode::ContentSearch {
+            return;
+        }
+
+        let selected = self.get_selected_tree_index();
+        self.update_filtered_results();
+        if let Some(selected) = selected {
+            self.select_tree_index(selected);
+        }
+    }
+
+    /// Drop the items a selection change moved out of the state filter
+    ///
+    /// The cursor keeps its row, which then shows the item after the one that left.
+    fn refresh_state_filter(&mut self) {
+        if self.state_filter == StateFilter::All || self.mode == AppMode::ContentSearch {
+            return;
+        }
+        let scroll_offset = self.scroll_offset;
         self.update_filtered_results();
+        self.scroll_offset = scroll_offset.min(self.selected_index);
+        self.update_scroll();
     }
 
     pub fn clear_search(&mut self) {
@@ -192,17 +905,268 @@ impl App {
         self.mode = mode;
     }
 
+    pub fn show_help(&mut self) {
+        self.mode_before_help = self.mode.clone();
+        self.mode = AppMode::Help;
+    }
+
+    /// Return to the mode the help screen was opened from
+    pub fn close_help(&mut self) {
+        self.mode = self.mode_before_help.clone();
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::Search => Focus::List,
+            Focus::List => Focus::Search,
+        };
+    }
+
+    pub fn open_command_palette(&mut self) {
+        self.command_palette = Some(CommandPalette::new());
+        self.mode_before_palette = self.mode.clone();
+        self.mode = AppMode::CommandPalette;
+    }
+
+    pub fn close_command_palette(&mut self) {
+        if self.command_palette.take().is_some() {
+            self.mode = self.mode_before_palette.clone();
+        }
+    }
+
+    /// Close the palette and return the selected command's action for the caller to run
+    pub fn run_command(&mut self) -> Option<AppAction> {
+        let command = self.command_palette.as_ref()?.selected();
+        self.close_command_palette();
+        command.map(|command| command.action.clone())
+    }
+
+    pub fn command_palette_char(&mut self, c: char) {
+        if let Some(palette) = &mut self.command_palette {
+            palette.add_char(c, &self.fuzzy_search);
+        }
+    }
+
+    pub fn command_palette_backspace(&mut self) {
+        if let Some(palette) = &mut self.command_palette {
+            palette.backspace(&self.fuzzy_search);
+        }
+    }
+
+    pub fn open_selection_summary(&mut self) {
+        self.selection_summary = Some(SelectionSummary::new(&self.tree));
+        self.mode_before_summary = self.mode.clone();
+        self.mode = AppMode::SelectionSummary;
+    }
+
+    pub fn close_selection_summary(&mut self) {
+        if self.selection_summary.take().is_some() {
+            self.mode = self.mode_before_summary.clone();
+        }
+    }
+
+    /// Close the summary and move the main list's cursor to the highlighted file
+    ///
+    /// A search or content search hiding the file is cleared, and in the tree view its
+    /// directories are expanded.
+    pub fn summary_jump(&mut self) {
+        let Some(index) = self.selection_summary.as_ref().and_then(|s| s.selected()) else {
+            return;
+        };
+        self.close_selection_summary();
+        if !self.filtered_results.visible_items.contains(&index) {
+            if self.mode == AppMode::ContentSearch {
+                self.toggle_content_search();
+            }
+            self.search_query.clear();
+            self.expand_ancestors(index);
+            self.update_filtered_results();
+        }
+        self.select_tree_index(index);
+    }
+
+    /// Exclude the file highlighted in the summary
+    pub fn summary_deselect(&mut self) {
+        let Some(index) = self.selection_summary.as_ref().and_then(|s| s.selected()) else {
+            return;
+        };
+        self.push_undo_snapshot();
+        self.set_state(index, SelectionState::Excluded);
+        if let Some(summary) = &mut self.selection_summary {
+            summary.refresh(&self.tree);
+        }
+        self.refresh_state_filter();
+    }
+
+    pub fn open_settings(&mut self) {
+        self.settings_panel = Some(SettingsPanel::new(&self.settings));
+        self.mode_before_settings = self.mode.clone();
+        self.mode = AppMode::Settings;
+    }
+
+    /// Apply the panel's changes to `settings` and return to the mode it was opened from
+    pub fn close_settings(&mut self) {
+        let Some(panel) = self.settings_panel.take() else {
+            return;
+        };
+        self.mode = self.mode_before_settings.clone();
+        if panel.changed.is_empty() {
+            return;
+        }
+
+        self.settings = panel.draft;
+        self.settings_changed = true;
+        self.fuzzy_search = FuzzySearch::from_settings(&self.settings);
+        self.token_warn_threshold = self.settings.token_warn_threshold;
+        self.max_total_size_warning = self.settings.max_total_size_warning;
+        if let Some(project_root) = self.settings_path.parent() {
+            self.output_dir = self.settings.output_dir(project_root);
+        }
+        self.update_filtered_results();
+        self.set_status_message(format!("Applied {} setting change(s)", panel.changed.len()));
+    }
+
+    /// Whether the settings panel changed `settings` since the last call
+    pub fn take_settings_changed(&mut self) -> bool {
+        std::mem::take(&mut self.settings_changed)
+    }
+
+    /// Enter in the settings panel: toggle, start editing or confirm the edited value
+    pub fn settings_activate(&mut self) {
+        let Some(panel) = &mut self.settings_panel else {
+            return;
+        };
+        let result = if panel.input.is_some() {
+            panel.confirm_edit()
+        } else {
+            panel.activate()
+        };
+        let editing = panel.input.is_some();
+        match result {
+            Ok(()) => {
+                self.mode = if editing {
+                    AppMode::SettingsEdit
+                } else {
+                    AppMode::Settings
+                }
+            }
+            Err(e) => self.set_status_message(format!("{:#}", e)),
+        }
+    }
+
+    pub fn settings_char(&mut self, c: char) {
+        if let Some(panel) = &mut self.settings_panel {
+            panel.add_char(c);
+        }
+    }
+
+    pub fn settings_backspace(&mut self) {
+        if let Some(panel) = &mut self.settings_panel {
+            panel.backspace();
+        }
+    }
+
+    /// Write the settings changed in the panel to the project config file
+    pub fn save_settings(&mut self) {
+        let Some(panel) = &self.settings_panel else {
+            return;
+        };
+        if panel.changed.is_empty() {
+            self.set_status_message("No settings changed");
+            return;
+        }
+        let message = match panel.draft.save_keys(&self.settings_path, &panel.changed) {
+            Ok(()) => format!(
+                "Saved {} setting(s) to {}",
+                panel.changed.len(),
+                self.settings_path.display()
+            ),
+            Err(e) => format!("Failed to save settings: {:#}", e),
+        };
+        self.set_status_message(message);
+    }
+
+    pub fn toggle_preview(&mut self) {
+        self.show_preview = !self.show_preview;
+    }
+
+    pub fn toggle_no_tree(&mut self) {
+        self.no_tree = !self.no_tree;
+        self.set_status_message(if self.no_tree {
+            "File tree left out of the output"
+        } else {
+            "File tree shown in the output"
+        });
+    }
+
+    /// File tree setting for the export: off with `no_tree`, otherwise on if it was forced off
+    pub fn export_show_tree(&self, configured: Option<bool>) -> Option<bool> {
+        match (self.no_tree, configured) {
+            (true, _) => Some(false),
+            (false, Some(false)) => Some(true),
+            (false, configured) => configured,
+        }
+    }
+
+    /// Preview of the file at `tree_index`, read from disk only when first requested
+    pub fn file_preview(&mut self, tree_index: usize) -> &FilePreview {
+        let node = &self.tree.nodes[tree_index];
+        let (path, is_text) = (&node.path, node.is_text_file);
+        self.preview_cache.entry(tree_index).or_insert_with(|| {
+            if is_text {
+                FilePreview::load(path)
+            } else {
+                FilePreview::Binary
+            }
+        })
+    }
+
+    /// Read the size and preview of a file again, after it was changed in the editor
+    pub fn reload_file(&mut self, tree_index: usize) {
+        self.preview_cache.remove(&tree_index);
+        if let Some(node) = self.tree.get_node_mut(tree_index) {
+            let metadata = fs::metadata(&node.path).ok();
+            node.size = metadata.as_ref().map(|metadata| metadata.len());
+            node.modified = metadata.and_then(|metadata| metadata.modified().ok());
+            // Count the file again with its new estimate
+            self.totals.remove(tree_index);
+            self.update_totals(&[tree_index]);
+        }
+    }
+
     pub fn quit(&mut self) {
         self.should_quit = true;
     }
 
     pub fn handle_escape(&mut self) {
         if self.mode == AppMode::Help {
-            self.mode = AppMode::Main;
+            self.close_help();
+        } else if self.mode == AppMode::SettingsEdit {
+            if let Some(panel) = &mut self.settings_panel {
+                panel.cancel_edit();
+            }
+            self.mode = AppMode::Settings;
+        } else if self.mode == AppMode::Settings {
+            self.close_settings();
+        } else if self.mode == AppMode::CommandPalette {
+            self.close_command_palette();
+        } else if self.mode == AppMode::SelectionSummary {
+            self.close_selection_summary();
         } else if self.mode == AppMode::FileSave {
             self.mode = AppMode::Main;
             self.file_save_input.clear();
             self.pending_content = None;
+        } else if self.focus == Focus::List {
+            // Leave list focus before clearing the search or quitting
+            self.focus = Focus::Search;
+        } else if self.mode == AppMode::ContentSearch {
+            // Clear the content query, then leave content search
+            if self.content_query.is_empty() {
+                self.toggle_content_search();
+            } else {
+                self.content_query.clear();
+                self.run_content_search();
+            }
         } else if !self.search_query.is_empty() {
             // Clear search text if there is any
             self.search_query.clear();
@@ -215,10 +1179,81 @@ impl App {
 
     pub fn start_file_save(&mut self, content: String) {
         self.pending_content = Some(content);
-        self.file_save_input.clear();
+        // An -o path is offered for editing instead of the generated name
+        self.file_save_input = self
+            .output_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        self.default_save_path =
+            OutputWriter::default_output_path(&self.tree, self.output_dir.as_deref());
         self.mode = AppMode::FileSave;
     }
 
+    /// Where Alt+P saves the included paths: the `--export-paths` file, else a
+    /// `.paths.txt` file where exports are saved
+    pub fn path_list_path(&self) -> PathBuf {
+        self.export_paths.clone().unwrap_or_else(|| {
+            OutputWriter::default_path_list_path(&self.tree, self.output_dir.as_deref())
+        })
+    }
+
+    /// Write the included file paths to `path`, one per line in `path_style`
+    pub fn export_path_list(&self, path: &Path) -> Result<()> {
+        let paths = format_styled_path_list(&self.tree, self.path_style);
+        if paths.is_empty() {
+            return Err(GthrError::NoFilesIncluded.into());
+        }
+        OutputWriter::write_content(path, &paths, false)
+    }
+
+    /// Write the included files to `path`, one relative path per line as `--load-selection`
+    /// reads them
+    pub fn save_selection(&self, path: &Path) -> Result<()> {
+        let paths = format_file_list(&self.tree, false, false);
+        if paths.is_empty() {
+            return Err(GthrError::NoFilesIncluded.into());
+        }
+        OutputWriter::write_content(path, &paths, false)
+    }
+
+    /// Path typed in the save dialog, or the default shown there
+    pub fn file_save_path(&self) -> PathBuf {
+        let input = self.file_save_input.trim();
+        if input.is_empty() {
+            self.default_save_path.clone()
+        } else if !input.contains('.') {
+            // Add .md extension if not present and doesn't have any extension
+            PathBuf::from(format!("{}.md", input))
+        } else {
+            PathBuf::from(input)
+        }
+    }
+
+    /// Ask before replacing `path` with the pending content
+    pub fn confirm_overwrite(&mut self, path: PathBuf) {
+        self.overwrite_path = Some(path);
+        self.mode_before_overwrite = self.mode.clone();
+        self.mode = AppMode::ConfirmOverwrite;
+    }
+
+    /// Keep the existing file, going back to the save dialog to pick another path
+    ///
+    /// Declining to replace the `-o` file opens the dialog with that path to edit.
+    pub fn cancel_overwrite(&mut self) {
+        self.overwrite_path = None;
+        self.mode = self.mode_before_overwrite.clone();
+        if self.mode != AppMode::FileSave
+            && self.output_path.is_some()
+            && let Some(content) = self.pending_content.take()
+        {
+            self.start_file_save(content);
+        } else if self.mode != AppMode::FileSave {
+            self.pending_content = None;
+            self.set_status_message("Not saved: file exists");
+        }
+    }
+
     pub fn add_file_save_char(&mut self, c: char) {
         if self.mode == AppMode::FileSave {
             self.file_save_input.push(c);
@@ -231,27 +1266,109 @@ impl App {
         }
     }
 
-    pub fn get_stats(&self) -> AppStats {
-        let total_files = self
+    /// Show a transient message in the status bar
+    pub fn set_status_message(&mut self, message: impl Into<String>) {
+        self.status_message = Some((message.into(), Instant::now()));
+    }
+
+    /// `✓ Copied 38.2 KB to clipboard`, after an export that keeps the app open
+    pub fn notify_copied(&mut self, size: usize) {
+        self.set_status_message(format!("✓ Copied {} to clipboard", format_file_size(size as u64)));
+    }
+
+    /// Clear the status message once it has been visible long enough
+    pub fn expire_status_message(&mut self) {
+        if let Some((_, shown_at)) = &self.status_message
+            && shown_at.elapsed() >= Duration::from_millis(STATUS_MESSAGE_DURATION_MS)
+        {
+            self.status_message = None;
+        }
+    }
+
+    /// Save the selection state of every node as JSON, keyed by path relative to the root
+    pub fn save_session(&self, path: &Path) -> Result<()> {
+        let session: BTreeMap<String, SessionEntry> = self
             .tree
             .nodes
             .iter()
-            .filter(|node| !node.is_directory && node.is_text_file)
-            .count();
+            .map(|node| {
+                let relative_path = self.tree.relative_path(&node.path);
+                let entry = match FileFingerprint::of(node) {
+                    Some(fingerprint) => SessionEntry::File {
+                        state: node.state,
+                        fingerprint,
+                    },
+                    None => SessionEntry::Directory(node.state),
+                };
+                (relative_path.to_string_lossy().to_string(), entry)
+            })
+            .collect();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&session)?)?;
+        Ok(())
+    }
+
+    /// Apply a saved session to the tree, ignoring paths that no longer exist
+    pub fn load_session(path: &Path, tree: &mut DirectoryTree) -> Result<()> {
+        let content = fs::read_to_string(path)?;
+        let session: BTreeMap<String, SessionEntry> = serde_json::from_str(&content)?;
+        let index_by_path: HashMap<String, usize> = tree
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let relative_path = tree.relative_path(&node.path);
+                (relative_path.to_string_lossy().to_string(), index)
+            })
+            .collect();
 
-        let included_files = self.tree.get_all_included_files().len();
+        for (relative_path, entry) in session {
+            let Some(&index) = index_by_path.get(&relative_path) else {
+                continue;
+            };
 
-        let total_size: u64 = self
+            // Directory states are derived from their children
+            if tree.nodes[index].children.is_empty() {
+                tree.set_state(index, entry.state());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// File fingerprints recorded in a saved session, keyed by relative path
+    pub fn session_fingerprints(path: &Path) -> Result<BTreeMap<PathBuf, FileFingerprint>> {
+        let content = fs::read_to_string(path)?;
+        let session: BTreeMap<String, SessionEntry> = serde_json::from_str(&content)?;
+        Ok(session
+            .into_iter()
+            .filter_map(|(relative_path, entry)| match entry {
+                SessionEntry::File { fingerprint, .. } => {
+                    Some((PathBuf::from(relative_path), fingerprint))
+                }
+                SessionEntry::Directory(_) => None,
+            })
+            .collect())
+    }
+
+    pub fn get_stats(&self) -> AppStats {
+        let total_files = self
             .tree
-            .get_all_included_files()
+            .nodes
             .iter()
-            .filter_map(|node| node.size)
-            .sum();
+            .filter(|node| !node.is_directory && node.is_text_file)
+            .count();
 
         AppStats {
             total_files,
-            included_files,
-            total_size,
+            included_files: self.totals.counted.len(),
+            total_size: self.totals.size,
+            estimated_tokens: self.totals.tokens,
+            total_line_count: self.totals.lines,
+            counting_lines: !self.line_counter.is_idle(),
             filtered_count: self.filtered_results.len(),
         }
     }
@@ -262,28 +1379,729 @@ pub struct AppStats {
     pub total_files: usize,
     pub included_files: usize,
     pub total_size: u64,
+    pub estimated_tokens: usize,
+    /// Lines of the included text files counted so far, read once per file while it is unchanged
+    pub total_line_count: usize,
+    /// Whether files are still being counted, so `total_line_count` may grow
+    pub counting_lines: bool,
     pub filtered_count: usize,
 }
 
+/// Running totals of the included files
+#[derive(Debug, Default)]
+struct SelectionTotals {
+    /// Estimate each file was counted with, so excluding it subtracts the same amounts
+    counted: HashMap<usize, FileEstimate>,
+    size: u64,
+    tokens: usize,
+    lines: usize,
+}
+
+impl SelectionTotals {
+    fn add(&mut self, index: usize, estimate: FileEstimate) {
+        self.remove(index);
+        self.size += estimate.size;
+        self.tokens += estimate.tokens;
+        self.lines += estimate.lines.unwrap_or(0);
+        self.counted.insert(index, estimate);
+    }
+
+    fn remove(&mut self, index: usize) {
+        if let Some(estimate) = self.counted.remove(&index) {
+            self.size -= estimate.size;
+            self.tokens -= estimate.tokens;
+            self.lines -= estimate.lines.unwrap_or(0);
+        }
+    }
+}
+
+/// How close the estimated tokens are to `token_warn_threshold`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenLevel {
+    Normal,
+    /// At least 80% of the threshold
+    Near,
+    Over,
+}
+
 impl AppStats {
     pub fn format_size(&self) -> String {
         format_file_size(self.total_size)
     }
+
+    /// `Lines: 1,234`, or `Lines: 1,234…` while files are still being counted
+    pub fn format_lines(&self) -> String {
+        let counting = if self.counting_lines { "…" } else { "" };
+        format!("Lines: {}{}", format_count(self.total_line_count), counting)
+    }
+
+    /// `~42.3k tokens`
+    pub fn format_tokens(&self) -> String {
+        format!("~{} tokens", format_token_count(self.estimated_tokens))
+    }
+
+    pub fn token_level(&self, threshold: Option<usize>) -> TokenLevel {
+        match threshold {
+            Some(threshold) if self.estimated_tokens > threshold => TokenLevel::Over,
+            Some(threshold) if self.estimated_tokens * 5 >= threshold * 4 => TokenLevel::Near,
+            _ => TokenLevel::Normal,
+        }
+    }
 }
 
-fn format_file_size(size: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
-    let mut size_f = size as f64;
-    let mut unit_index = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::SELECTION_FILENAME;
+    use crate::directory::builder::load_selection;
+    use std::fs;
+    use tempfile::TempDir;
 
-    while size_f >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size_f /= 1024.0;
-        unit_index += 1;
+    fn create_test_app(temp_dir: &TempDir) -> App {
+        let root_path = temp_dir.path();
+        fs::write(root_path.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(root_path.join("lib.rs"), "pub fn lib() {}").unwrap();
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("main.rs"), false, root_path);
+        tree.add_node(root_path.join("lib.rs"), false, root_path);
+        App::new(tree)
+    }
+
+    /// Wait for the background line counts, which the run loop polls before every frame
+    fn wait_for_line_counts(app: &mut App) {
+        while app.get_stats().counting_lines {
+            app.poll_line_counts();
+            std::thread::yield_now();
+        }
     }
 
-    if unit_index == 0 {
-        format!("{} {}", size, UNITS[unit_index])
-    } else {
-        format!("{:.1} {}", size_f, UNITS[unit_index])
+    #[test]
+    fn test_undo_redo_selection() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+
+        app.select_all();
+        assert_eq!(app.tree.get_all_included_files().len(), 2);
+        assert!(app.get_stats().format_lines().ends_with('…'));
+        wait_for_line_counts(&mut app);
+        assert_eq!(app.get_stats().total_line_count, 2);
+
+        app.undo();
+        assert_eq!(app.tree.get_all_included_files().len(), 0);
+        assert_eq!(app.get_stats().included_files, 0);
+        assert_eq!(app.get_stats().total_line_count, 0);
+
+        app.redo();
+        assert_eq!(app.tree.get_all_included_files().len(), 2);
+        assert!(app.redo_stack.is_empty());
+
+        // A new change clears the redo history
+        app.undo();
+        app.move_down();
+        app.toggle_selection();
+        assert!(app.redo_stack.is_empty());
+        assert_eq!(app.tree.get_all_included_files().len(), 1);
+        assert_eq!(app.get_stats().included_files, 1);
+    }
+
+    #[test]
+    fn test_replace_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+        let mut app = create_test_app(&temp_dir);
+        fs::write(root_path.join("build.rs"), "fn main() {}").unwrap();
+
+        // Include the second file and leave the cursor on it
+        app.move_down();
+        app.toggle_selection();
+        let included_path = app.tree.get_all_included_files()[0].path.clone();
+
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        tree.add_node(root_path.join("build.rs"), false, root_path);
+        tree.add_node(root_path.join("main.rs"), false, root_path);
+        tree.add_node(root_path.join("lib.rs"), false, root_path);
+        assert_eq!(app.replace_tree(tree), (1, 0));
+
+        let included = app.tree.get_all_included_files();
+        assert_eq!(included.len(), 1);
+        assert_eq!(included[0].path, included_path);
+        // The new file is listed too
+        assert_eq!(app.filtered_results.len(), 4);
+        let selected = app.get_selected_tree_index().unwrap();
+        assert_eq!(app.tree.nodes[selected].path, included_path);
+        assert!(app.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn test_cycle_sort_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        let main_rs = app.tree.path_to_index[&temp_dir.path().join("main.rs")];
+        let lib_rs = app.tree.path_to_index[&temp_dir.path().join("lib.rs")];
+        app.tree.nodes[main_rs].size = Some(100);
+        app.tree.nodes[lib_rs].size = Some(10);
+        app.update_filtered_results();
+        let rows = |app: &App| app.filtered_results.visible_items.clone();
+        assert_eq!(rows(&app), vec![app.tree.root_index, main_rs, lib_rs]);
+        app.selected_index = 1;
+
+        app.cycle_sort_order();
+        assert_eq!(app.sort_order, SortOrder::Path);
+        assert_eq!(rows(&app), vec![app.tree.root_index, lib_rs, main_rs]);
+        assert_eq!(app.get_selected_tree_index(), Some(main_rs));
+
+        // The root directory has no size, so it goes last
+        app.cycle_sort_order();
+        assert_eq!(rows(&app), vec![main_rs, lib_rs, app.tree.root_index]);
+        assert_eq!(app.get_selected_tree_index(), Some(main_rs));
+    }
+
+    #[test]
+    fn test_state_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        let main_rs = app.tree.path_to_index[&temp_dir.path().join("main.rs")];
+        let lib_rs = app.tree.path_to_index[&temp_dir.path().join("lib.rs")];
+        app.tree.set_state(main_rs, SelectionState::Included);
+        app.selected_index = 2;
+
+        app.cycle_state_filter();
+        assert_eq!(app.state_filter, StateFilter::Included);
+        // The partially included root shows in both filtered views
+        let rows = |app: &App| app.filtered_results.visible_items.clone();
+        assert_eq!(rows(&app), vec![app.tree.root_index, main_rs]);
+        assert_eq!(app.get_stats().filtered_count, 2);
+
+        app.cycle_state_filter();
+        assert_eq!(rows(&app), vec![app.tree.root_index, lib_rs]);
+        assert_eq!(app.get_selected_tree_index(), Some(lib_rs));
+
+        // Items toggled out of the view disappear, here with the now included root
+        app.toggle_selection();
+        assert!(rows(&app).is_empty());
+        assert_eq!(app.selected_index, 0);
+        app.undo();
+        assert_eq!(rows(&app), vec![app.tree.root_index, lib_rs]);
+
+        app.cycle_state_filter();
+        assert_eq!(app.filtered_results.len(), 3);
+    }
+
+    #[test]
+    fn test_reload_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut app = create_test_app(&temp_dir);
+        let main_rs = app.tree.path_to_index[&temp_dir.path().join("main.rs")];
+        app.select_all();
+        app.file_preview(main_rs);
+        app.get_stats();
+
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {\n    run();\n}\n")?;
+        app.reload_file(main_rs);
+        wait_for_line_counts(&mut app);
+        assert_eq!(app.tree.nodes[main_rs].size, Some(25));
+        assert_eq!(app.get_stats().total_size, 25);
+        assert_eq!(app.get_stats().total_line_count, 4);
+        assert!(matches!(
+            app.file_preview(main_rs),
+            FilePreview::Text { lines, .. } if lines.len() == 3
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_path_list() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("paths.txt");
+        let mut app = create_test_app(&temp_dir).with_export_paths(Some(path.clone()));
+        assert_eq!(app.path_list_path(), path);
+        assert!(app.export_path_list(&path).is_err());
+
+        app.select_all();
+        app.export_path_list(&path)?;
+        assert_eq!(fs::read_to_string(&path)?, "main.rs\nlib.rs\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_and_load_selection() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join(SELECTION_FILENAME);
+        let mut app = create_test_app(&temp_dir);
+        assert!(app.save_selection(&path).is_err());
+
+        app.move_down();
+        app.toggle_selection();
+        app.save_selection(&path)?;
+
+        let mut tree = DirectoryTree::new(temp_dir.path().to_path_buf());
+        tree.add_node(temp_dir.path().join("main.rs"), false, temp_dir.path());
+        tree.add_node(temp_dir.path().join("lib.rs"), false, temp_dir.path());
+        load_selection(&mut tree, &path)?;
+        let included = |tree: &DirectoryTree| -> Vec<PathBuf> {
+            tree.get_all_included_files()
+                .iter()
+                .map(|node| node.path.clone())
+                .collect()
+        };
+        assert_eq!(included(&tree), included(&app.tree));
+        assert_eq!(included(&tree).len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_and_load_session() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut app = create_test_app(&temp_dir);
+        let session_path = temp_dir.path().join("session").join("session.json");
+
+        app.move_down();
+        app.toggle_selection();
+        app.save_session(&session_path)?;
+
+        let mut tree = DirectoryTree::new(temp_dir.path().to_path_buf());
+        tree.add_node(temp_dir.path().join("main.rs"), false, temp_dir.path());
+        App::load_session(&session_path, &mut tree)?;
+
+        let included = tree.get_all_included_files();
+        assert_eq!(included.len(), 1);
+        assert_eq!(included[0].path, app.tree.get_all_included_files()[0].path);
+        assert_eq!(tree.nodes[tree.root_index].state, SelectionState::Included);
+
+        let fingerprints = App::session_fingerprints(&session_path)?;
+        assert_eq!(fingerprints, app.tree.fingerprints());
+        assert_eq!(fingerprints[Path::new("main.rs")].size, 12);
+
+        // Sessions without fingerprints still load
+        fs::write(&session_path, r#"{"": "excluded", "main.rs": "excluded"}"#)?;
+        App::load_session(&session_path, &mut tree)?;
+        assert!(tree.get_all_included_files().is_empty());
+        assert!(App::session_fingerprints(&session_path)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_stack_is_capped() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+
+        for _ in 0..MAX_UNDO_HISTORY + 10 {
+            app.toggle_selection();
+        }
+
+        assert_eq!(app.undo_stack.len(), MAX_UNDO_HISTORY);
+    }
+
+    #[test]
+    fn test_bulk_selection_affects_visible_items() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+
+        for c in "main".chars() {
+            app.add_search_char(c);
+        }
+        app.select_all();
+
+        let included = app.tree.get_all_included_files();
+        assert_eq!(included.len(), 1);
+        assert_eq!(included[0].name, "main.rs");
+        assert_eq!(
+            app.status_message.as_ref().map(|(message, _)| message.as_str()),
+            Some("Selected 1 file")
+        );
+
+        app.clear_search();
+        app.invert_selection();
+        assert_eq!(app.tree.get_all_included_files()[0].name, "lib.rs");
+
+        app.select_none();
+        assert!(app.tree.get_all_included_files().is_empty());
+        assert_eq!(
+            app.status_message.as_ref().map(|(message, _)| message.as_str()),
+            Some("Deselected 1 file")
+        );
+    }
+
+    #[test]
+    fn test_bulk_selection_keys_follow_search() {
+        use crate::ui::events::{AppAction, handle_key_event};
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        for name in ["tests", "src"] {
+            fs::create_dir(root_path.join(name)).unwrap();
+            tree.add_node(root_path.join(name), true, root_path);
+            let file = root_path.join(name).join("cli.rs");
+            fs::write(&file, "fn cli() {}").unwrap();
+            tree.add_node(file, false, &root_path.join(name));
+        }
+        let mut app = App::new(tree);
+
+        let press_ctrl = |app: &mut App, c| {
+            let key_event = KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+            match handle_key_event(key_event, &app.mode, app.focus) {
+                Some(AppAction::SelectAll) => app.select_all(),
+                Some(AppAction::SelectNone) => app.select_none(),
+                Some(AppAction::InvertSelection) => app.invert_selection(),
+                _ => panic!("Ctrl+{} is not a bulk selection key", c),
+            }
+        };
+
+        for c in "tests".chars() {
+            app.add_search_char(c);
+        }
+        press_ctrl(&mut app, 'a');
+        let included: Vec<PathBuf> = app
+            .tree
+            .get_all_included_files()
+            .iter()
+            .map(|node| app.tree.relative_path(&node.path))
+            .collect();
+        assert_eq!(included, vec![Path::new("tests").join("cli.rs")]);
+
+        press_ctrl(&mut app, 't');
+        assert!(app.tree.get_all_included_files().is_empty());
+
+        app.clear_search();
+        press_ctrl(&mut app, 'a');
+        press_ctrl(&mut app, 'd');
+        assert!(app.tree.get_all_included_files().is_empty());
+
+        // A visible directory does not include the file the exclusion hides
+        for c in "tests !cli".chars() {
+            app.add_search_char(c);
+        }
+        let tests_index = app.tree.path_to_index[&root_path.join("tests")];
+        assert_eq!(app.filtered_results.visible_items, vec![tests_index]);
+        press_ctrl(&mut app, 'a');
+        assert!(app.tree.get_all_included_files().is_empty());
+        assert_eq!(app.tree.nodes[tests_index].state, SelectionState::Excluded);
+    }
+
+    #[test]
+    fn test_output_path_prefills_file_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("test.md");
+        fs::write(&output_path, "previous export").unwrap();
+        let mut app = create_test_app(&temp_dir).with_output_path(Some(output_path.clone()));
+
+        // Declining to replace the -o file offers it for editing in the save dialog
+        app.pending_content = Some("# Export".to_string());
+        app.confirm_overwrite(output_path.clone());
+        app.cancel_overwrite();
+        assert_eq!(app.mode, AppMode::FileSave);
+        assert_eq!(app.file_save_path(), output_path);
+
+        for _ in 0.."test.md".len() {
+            app.file_save_backspace();
+        }
+        for c in "renamed.md".chars() {
+            app.add_file_save_char(c);
+        }
+        let path = app.file_save_path();
+        OutputWriter::write_content(&path, app.pending_content.as_ref().unwrap(), false).unwrap();
+        assert_eq!(fs::read_to_string(temp_dir.path().join("renamed.md")).unwrap(), "# Export");
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "previous export");
+    }
+
+    #[test]
+    fn test_file_preview_is_cached() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        let index = app
+            .tree
+            .nodes
+            .iter()
+            .position(|node| node.name == "main.rs")
+            .unwrap();
+
+        let expected = FilePreview::Text {
+            lines: vec!["fn main() {}".to_string()],
+            truncated: false,
+        };
+        assert_eq!(app.file_preview(index), &expected);
+
+        // Later lookups reuse the first read
+        fs::write(temp_dir.path().join("main.rs"), "changed").unwrap();
+        assert_eq!(app.file_preview(index), &expected);
+    }
+
+    #[test]
+    fn test_tree_view() {
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+        fs::create_dir(root_path.join("src")).unwrap();
+        fs::write(root_path.join("src").join("cli.rs"), "fn cli() {}").unwrap();
+        fs::write(root_path.join("README.md"), "# Readme").unwrap();
+        let mut tree = DirectoryTree::new(root_path.to_path_buf());
+        let src = tree.add_node(root_path.join("src"), true, root_path).unwrap();
+        let cli = tree
+            .add_node(root_path.join("src").join("cli.rs"), false, &root_path.join("src"))
+            .unwrap();
+        tree.add_node(root_path.join("README.md"), false, root_path).unwrap();
+        let mut app = App::new(tree);
+
+        // Switching keeps the cursor on its item, expanding the directories above it
+        app.selected_index = app
+            .filtered_results
+            .visible_items
+            .iter()
+            .position(|&index| index == cli)
+            .unwrap();
+        app.toggle_tree_view();
+        assert!(app.is_tree_view_active());
+        assert_eq!(app.get_selected_tree_index(), Some(cli));
+        assert_eq!(app.filtered_results.len(), 4);
+
+        // Left goes to the parent, then collapses it
+        app.collapse();
+        assert_eq!(app.get_selected_tree_index(), Some(src));
+        app.collapse();
+        assert_eq!(app.filtered_results.len(), 3);
+        assert_eq!(app.get_selected_tree_index(), Some(src));
+
+        // Toggling selects the same subtree as in the flat list
+        app.toggle_selection();
+        assert!(app.tree.nodes[cli].state.is_included());
+        app.expand();
+        assert_eq!(app.filtered_results.len(), 4);
+
+        // Typing a search shows flat results until it is cleared
+        app.add_search_char('c');
+        assert!(!app.is_tree_view_active());
+        app.clear_search();
+        assert!(app.is_tree_view_active());
+        assert_eq!(app.filtered_results.len(), 4);
+    }
+
+    #[test]
+    fn test_toggle_no_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        assert_eq!(app.export_show_tree(None), None);
+        assert_eq!(app.export_show_tree(Some(true)), Some(true));
+
+        app.toggle_no_tree();
+        assert_eq!(app.export_show_tree(None), Some(false));
+        assert_eq!(app.export_show_tree(Some(true)), Some(false));
+
+        // Toggling back after starting with --no-tree brings the tree in
+        let mut app = create_test_app(&temp_dir).with_no_tree(true);
+        app.toggle_no_tree();
+        assert_eq!(app.export_show_tree(Some(false)), Some(true));
+    }
+
+    #[test]
+    fn test_list_focus() {
+        use crate::ui::events::{AppAction, handle_key_event};
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert!(matches!(
+            handle_key_event(key(KeyCode::Char('j')), &app.mode, app.focus),
+            Some(AppAction::SearchChar('j'))
+        ));
+        assert!(matches!(
+            handle_key_event(key(KeyCode::Tab), &app.mode, app.focus),
+            Some(AppAction::ToggleFocus)
+        ));
+        app.toggle_focus();
+
+        let action = |app: &App, code| handle_key_event(key(code), &app.mode, app.focus);
+        assert!(matches!(action(&app, KeyCode::Char('j')), Some(AppAction::MoveDown)));
+        assert!(matches!(action(&app, KeyCode::Char('G')), Some(AppAction::MoveToBottom)));
+        assert!(matches!(action(&app, KeyCode::Char(' ')), Some(AppAction::ToggleSelection)));
+        assert!(action(&app, KeyCode::Char('x')).is_none());
+        let ctrl_d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert!(matches!(
+            handle_key_event(ctrl_d, &app.mode, app.focus),
+            Some(AppAction::PageDown)
+        ));
+
+        // Esc returns to the search before quitting
+        app.handle_escape();
+        assert_eq!(app.focus, Focus::Search);
+        assert!(!app.should_quit);
+        app.handle_escape();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_command_palette() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        app.open_command_palette();
+        assert_eq!(app.mode, AppMode::CommandPalette);
+        "redo".chars().for_each(|c| app.command_palette_char(c));
+
+        assert!(matches!(app.run_command(), Some(AppAction::Redo)));
+        assert_eq!(app.mode, AppMode::Main);
+        assert!(app.command_palette.is_none());
+
+        // Esc closes without running anything
+        app.open_command_palette();
+        app.handle_escape();
+        assert_eq!(app.mode, AppMode::Main);
+        assert!(app.run_command().is_none());
+    }
+
+    #[test]
+    fn test_selection_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        app.select_all();
+        app.search_query = "zzz".to_string();
+        app.update_filtered_results();
+
+        app.open_selection_summary();
+        assert_eq!(app.mode, AppMode::SelectionSummary);
+        assert_eq!(app.selection_summary.as_ref().unwrap().files.len(), 2);
+
+        // Deselecting drops the file from the summary and can be undone
+        app.summary_deselect();
+        assert_eq!(app.tree.get_all_included_files().len(), 1);
+        assert_eq!(app.selection_summary.as_ref().unwrap().files.len(), 1);
+
+        // Jumping clears the search hiding the file
+        let remaining = app.selection_summary.as_ref().unwrap().selected().unwrap();
+        app.summary_jump();
+        assert_eq!(app.mode, AppMode::Main);
+        assert!(app.search_query.is_empty());
+        assert_eq!(app.get_selected_tree_index(), Some(remaining));
+
+        app.undo();
+        assert_eq!(app.tree.get_all_included_files().len(), 2);
+    }
+
+    #[test]
+    fn test_settings_panel() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".gthr.toml");
+        let mut app =
+            create_test_app(&temp_dir).with_settings(Settings::default(), config_path.clone());
+
+        app.open_settings();
+        assert_eq!(app.mode, AppMode::Settings);
+        while app.settings_panel.as_ref().unwrap().selected_key() != "token_warn_threshold" {
+            app.move_down();
+        }
+        app.settings_activate();
+        assert_eq!(app.mode, AppMode::SettingsEdit);
+        "1000".chars().for_each(|c| app.settings_char(c));
+        app.settings_activate();
+        assert_eq!(app.mode, AppMode::Settings);
+
+        // Nothing applies until the panel is closed
+        assert_eq!(app.token_warn_threshold, None);
+        app.save_settings();
+        assert!(fs::read_to_string(&config_path).unwrap().contains("token_warn_threshold = 1000"));
+
+        app.handle_escape();
+        assert_eq!(app.mode, AppMode::Main);
+        assert!(app.settings_panel.is_none());
+        assert_eq!(app.settings.token_warn_threshold, Some(1000));
+        assert_eq!(app.token_warn_threshold, Some(1000));
+        assert!(app.take_settings_changed());
+        assert!(!app.take_settings_changed());
+    }
+
+    #[test]
+    fn test_notify_copied() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        app.notify_copied(39_117);
+        assert!(!app.should_quit);
+        assert_eq!(
+            app.status_message.as_ref().map(|(message, _)| message.as_str()),
+            Some("✓ Copied 38.2 KB to clipboard")
+        );
+    }
+
+    #[test]
+    fn test_token_level() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        for node in app.tree.nodes.iter_mut().filter(|node| !node.is_directory) {
+            node.size = Some(if node.name == "main.rs" { 12 } else { 15 });
+        }
+        app.set_state(app.tree.root_index, SelectionState::Included);
+        // 27 bytes round up to 7 tokens
+        let stats = app.get_stats();
+        assert_eq!(stats.estimated_tokens, 7);
+        assert_eq!(stats.format_tokens(), "~7 tokens");
+
+        assert_eq!(stats.token_level(None), TokenLevel::Normal);
+        assert_eq!(stats.token_level(Some(100)), TokenLevel::Normal);
+        assert_eq!(stats.token_level(Some(8)), TokenLevel::Near);
+        assert_eq!(stats.token_level(Some(6)), TokenLevel::Over);
+    }
+
+    #[test]
+    fn test_mouse_click_and_scroll() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        // Rows 1 to 3 inside the borders show the root, main.rs and lib.rs
+        app.file_list_area = Rect::new(0, 0, 40, 10);
+        let main_rs = app.filtered_results.visible_items[1];
+
+        app.click(20, 2);
+        assert_eq!(app.selected_index, 1);
+        assert!(!app.tree.nodes[main_rs].state.is_included());
+
+        // A second click on the same row toggles it
+        app.click(20, 2);
+        assert!(app.tree.nodes[main_rs].state.is_included());
+
+        // So does a single click on the ✓/✗ marker
+        app.click(3, 2);
+        assert!(!app.tree.nodes[main_rs].state.is_included());
+
+        // Borders, rows past the end and other places are ignored
+        app.click(0, 2);
+        app.click(20, 6);
+        app.click(20, 12);
+        assert_eq!(app.selected_index, 1);
+
+        app.viewport_height = 2;
+        app.scroll(true);
+        assert_eq!(app.scroll_offset, 1);
+        assert_eq!(app.selected_index, 1);
+        app.scroll(false);
+        assert_eq!(app.scroll_offset, 0);
+    }
+
+    #[test]
+    fn test_seeded_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = create_test_app(&temp_dir);
+        // Patterns preselect, the query only filters what is visible
+        let lib_index = app.tree.path_to_index[&temp_dir.path().join("lib.rs")];
+        app.tree.set_state(lib_index, SelectionState::Included);
+        let mut app = app.with_query("main");
+
+        assert_eq!(app.search_query, "main");
+        let visible: Vec<&str> = app
+            .filtered_results
+            .visible_items
+            .iter()
+            .map(|&index| app.tree.nodes[index].name.as_str())
+            .collect();
+        assert_eq!(visible, vec!["main.rs"]);
+        assert_eq!(app.tree.get_all_included_files()[0].name, "lib.rs");
+
+        app.handle_escape();
+        assert!(app.search_query.is_empty());
+        assert!(!app.should_quit);
+        assert_eq!(app.filtered_results.len(), 3);
     }
 }
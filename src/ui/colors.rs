@@ -11,6 +11,8 @@ pub struct ColorScheme {
     pub border: Style,
     pub text: Style,
     pub help_text: Style,
+    pub warning: Style,
+    pub caution: Style,
 }
 
 impl Default for ColorScheme {
@@ -25,6 +27,8 @@ impl Default for ColorScheme {
             border: Style::default().fg(Color::White),
             text: Style::default().fg(Color::White),
             help_text: Style::default().fg(Color::Gray),
+            warning: Style::default().bg(Color::Red).fg(Color::White),
+            caution: Style::default().fg(Color::Yellow),
         }
     }
 }
@@ -0,0 +1,284 @@
+use crate::fuzzy::matcher::FuzzySearch;
+use crate::ui::events::AppAction;
+
+/// An action offered in the command palette
+#[derive(Debug, Clone)]
+pub struct CommandEntry {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub keybind: &'static str,
+    pub action: AppAction,
+}
+
+/// Every action the palette can run, in the order shown before anything is typed
+pub const COMMANDS: &[CommandEntry] = &[
+    CommandEntry {
+        name: "Export",
+        description: "Copy the output to the clipboard (or save it) and keep going",
+        keybind: "Ctrl+E",
+        action: AppAction::Export,
+    },
+    CommandEntry {
+        name: "Export and quit",
+        description: "Export the output, then leave gthr",
+        keybind: "Ctrl+Q",
+        action: AppAction::ExportAndQuit,
+    },
+    CommandEntry {
+        name: "Toggle selection",
+        description: "Include or exclude the item under the cursor",
+        keybind: "Enter",
+        action: AppAction::ToggleSelection,
+    },
+    CommandEntry {
+        name: "Include all visible",
+        description: "Include every file matching the search",
+        keybind: "Ctrl+A",
+        action: AppAction::SelectAll,
+    },
+    CommandEntry {
+        name: "Exclude all visible",
+        description: "Exclude every file matching the search",
+        keybind: "Ctrl+N",
+        action: AppAction::SelectNone,
+    },
+    CommandEntry {
+        name: "Invert visible",
+        description: "Flip the selection of every item matching the search",
+        keybind: "Ctrl+T",
+        action: AppAction::InvertSelection,
+    },
+    CommandEntry {
+        name: "Selection summary",
+        description: "List the included files with their sizes and the token estimate",
+        keybind: "Ctrl+V",
+        action: AppAction::OpenSelectionSummary,
+    },
+    CommandEntry {
+        name: "Undo",
+        description: "Undo the last selection change",
+        keybind: "Ctrl+Z",
+        action: AppAction::Undo,
+    },
+    CommandEntry {
+        name: "Redo",
+        description: "Redo the last undone selection change",
+        keybind: "Ctrl+Y",
+        action: AppAction::Redo,
+    },
+    CommandEntry {
+        name: "Save session",
+        description: "Save the selection to the session file",
+        keybind: "Ctrl+S",
+        action: AppAction::SaveSession,
+    },
+    CommandEntry {
+        name: "Copy paths",
+        description: "Copy the list of included paths to the clipboard",
+        keybind: "Ctrl+L",
+        action: AppAction::CopyPaths,
+    },
+    CommandEntry {
+        name: "Open in editor",
+        description: "Edit the file under the cursor in $EDITOR",
+        keybind: "e (list)",
+        action: AppAction::OpenInEditor,
+    },
+    CommandEntry {
+        name: "Save paths",
+        description: "Write the list of included paths to a text file",
+        keybind: "Alt+P",
+        action: AppAction::ExportPaths,
+    },
+    CommandEntry {
+        name: "Save selection",
+        description: "Write the included files to .gthr-selection for --load-selection",
+        keybind: "Alt+S",
+        action: AppAction::SaveSelection,
+    },
+    CommandEntry {
+        name: "Cycle search mode",
+        description: "Switch between fuzzy, substring, regex and exact search",
+        keybind: "Ctrl+R",
+        action: AppAction::CycleSearchMode,
+    },
+    CommandEntry {
+        name: "Cycle sort order",
+        description: "Sort the list by relevance, path, size (largest first) or modification time",
+        keybind: "s (list)",
+        action: AppAction::CycleSortOrder,
+    },
+    CommandEntry {
+        name: "Cycle view filter",
+        description: "Show all items, only included ones or only excluded ones",
+        keybind: "f (list)",
+        action: AppAction::CycleStateFilter,
+    },
+    CommandEntry {
+        name: "Search file contents",
+        description: "Search inside files instead of their paths",
+        keybind: "Ctrl+G",
+        action: AppAction::ToggleContentSearch,
+    },
+    CommandEntry {
+        name: "Toggle tree view",
+        description: "Show the hierarchy instead of the flat list",
+        keybind: "Ctrl+B",
+        action: AppAction::ToggleTreeView,
+    },
+    CommandEntry {
+        name: "Toggle preview",
+        description: "Show the item under the cursor next to the list",
+        keybind: "Ctrl+P",
+        action: AppAction::TogglePreview,
+    },
+    CommandEntry {
+        name: "Toggle file tree in output",
+        description: "Leave the file tree out of the export, or bring it back",
+        keybind: "Ctrl+O",
+        action: AppAction::ToggleNoTree,
+    },
+    CommandEntry {
+        name: "Rescan",
+        description: "Pick up files created or deleted since gthr started, keeping the selection",
+        keybind: "F5",
+        action: AppAction::Rescan,
+    },
+    CommandEntry {
+        name: "Toggle gitignore",
+        description: "Rescan with .gitignore rules turned off or back on, keeping the selection",
+        keybind: "Ctrl+F",
+        action: AppAction::ToggleGitignore,
+    },
+    CommandEntry {
+        name: "Toggle list focus",
+        description: "Switch plain keys between the search and vim-style list navigation",
+        keybind: "Tab",
+        action: AppAction::ToggleFocus,
+    },
+    CommandEntry {
+        name: "Collapse directory",
+        description: "Collapse the directory under the cursor in the tree view",
+        keybind: "←",
+        action: AppAction::Collapse,
+    },
+    CommandEntry {
+        name: "Expand directory",
+        description: "Expand the directory under the cursor in the tree view",
+        keybind: "→",
+        action: AppAction::Expand,
+    },
+    CommandEntry {
+        name: "Go to top",
+        description: "Move the cursor to the first item",
+        keybind: "Home",
+        action: AppAction::MoveToTop,
+    },
+    CommandEntry {
+        name: "Go to bottom",
+        description: "Move the cursor to the last item",
+        keybind: "End",
+        action: AppAction::MoveToBottom,
+    },
+    CommandEntry {
+        name: "Settings",
+        description: "Edit settings for this session or the project config",
+        keybind: "Ctrl+,",
+        action: AppAction::OpenSettings,
+    },
+    CommandEntry {
+        name: "Help",
+        description: "Show every keybinding",
+        keybind: "Ctrl+H",
+        action: AppAction::ShowHelp,
+    },
+];
+
+/// Query and matching commands of the open command palette
+#[derive(Debug, Clone)]
+pub struct CommandPalette {
+    pub query: String,
+    /// Commands matching the query, best match first
+    pub results: Vec<&'static CommandEntry>,
+    pub selected_index: usize,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            results: COMMANDS.iter().collect(),
+            selected_index: 0,
+        }
+    }
+
+    pub fn add_char(&mut self, c: char, fuzzy_search: &FuzzySearch) {
+        self.query.push(c);
+        self.update_results(fuzzy_search);
+    }
+
+    pub fn backspace(&mut self, fuzzy_search: &FuzzySearch) {
+        self.query.pop();
+        self.update_results(fuzzy_search);
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected_index + 1 < self.results.len() {
+            self.selected_index += 1;
+        }
+    }
+
+    pub fn selected(&self) -> Option<&'static CommandEntry> {
+        self.results.get(self.selected_index).copied()
+    }
+
+    fn update_results(&mut self, fuzzy_search: &FuzzySearch) {
+        self.results = fuzzy_search
+            .search_items(COMMANDS, &self.query, |command| command.name)
+            .into_iter()
+            .map(|result| &COMMANDS[result.item_index])
+            .collect();
+        self.selected_index = 0;
+    }
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_commands() {
+        let fuzzy_search = FuzzySearch::new();
+        let mut palette = CommandPalette::new();
+        assert_eq!(palette.results.len(), COMMANDS.len());
+
+        "tree"
+            .chars()
+            .for_each(|c| palette.add_char(c, &fuzzy_search));
+        let names: Vec<&str> = palette.results.iter().map(|command| command.name).collect();
+        assert!(names.contains(&"Toggle tree view"));
+        assert!(names.contains(&"Toggle file tree in output"));
+        assert!(!names.contains(&"Undo"));
+
+        palette.move_down();
+        assert_eq!(palette.selected_index, 1);
+        palette.backspace(&fuzzy_search);
+        assert_eq!(palette.selected_index, 0);
+        assert_eq!(palette.query, "tre");
+
+        "xyz"
+            .chars()
+            .for_each(|c| palette.add_char(c, &fuzzy_search));
+        assert!(palette.selected().is_none());
+    }
+}
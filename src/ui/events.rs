@@ -1,10 +1,15 @@
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
 use std::time::Duration;
 use anyhow::Result;
+use crate::ui::app::Focus;
 
 #[derive(Debug, Clone)]
 pub enum AppEvent {
     Key(KeyEvent),
+    Mouse(MouseEvent),
     Tick,
     Quit,
 }
@@ -26,6 +31,7 @@ impl EventHandler {
                         Ok(None)
                     }
                 }
+                Event::Mouse(mouse_event) => Ok(Some(AppEvent::Mouse(mouse_event))),
                 _ => Ok(None),
             }
         } else {
@@ -34,7 +40,11 @@ impl EventHandler {
     }
 }
 
-pub fn handle_key_event(key_event: KeyEvent, mode: &crate::ui::app::AppMode) -> Option<AppAction> {
+pub fn handle_key_event(
+    key_event: KeyEvent,
+    mode: &crate::ui::app::AppMode,
+    focus: Focus,
+) -> Option<AppAction> {
     use crate::ui::app::AppMode;
 
     // Handle file save mode differently
@@ -49,13 +59,123 @@ pub fn handle_key_event(key_event: KeyEvent, mode: &crate::ui::app::AppMode) ->
             _ => return None,
         }
     }
+    if *mode == AppMode::CommandPalette {
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        return match key_event.code {
+            KeyCode::Esc => Some(AppAction::Escape),
+            KeyCode::Enter => Some(AppAction::RunCommand),
+            KeyCode::Up => Some(AppAction::MoveUp),
+            KeyCode::Down => Some(AppAction::MoveDown),
+            KeyCode::Char('k') if control => Some(AppAction::MoveUp),
+            KeyCode::Char('j') if control => Some(AppAction::MoveDown),
+            KeyCode::Backspace => Some(AppAction::CommandPaletteBackspace),
+            KeyCode::Char(c) if !control => Some(AppAction::CommandPaletteChar(c)),
+            _ => None,
+        };
+    }
+    if *mode == AppMode::SelectionSummary {
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        return match key_event.code {
+            KeyCode::Esc => Some(AppAction::Escape),
+            KeyCode::Enter => Some(AppAction::SummaryJump),
+            KeyCode::Up => Some(AppAction::MoveUp),
+            KeyCode::Down => Some(AppAction::MoveDown),
+            KeyCode::Char('k') if control => Some(AppAction::MoveUp),
+            KeyCode::Char('j') if control => Some(AppAction::MoveDown),
+            KeyCode::Char('d' | 'D' | 'x') => Some(AppAction::SummaryDeselect),
+            _ => None,
+        };
+    }
+    if *mode == AppMode::SettingsEdit {
+        return match key_event.code {
+            KeyCode::Esc => Some(AppAction::Escape),
+            KeyCode::Enter => Some(AppAction::SettingsActivate),
+            KeyCode::Backspace => Some(AppAction::SettingsBackspace),
+            KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(AppAction::SettingsChar(c))
+            }
+            _ => None,
+        };
+    }
+    if *mode == AppMode::Settings {
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        return match key_event.code {
+            KeyCode::Esc => Some(AppAction::Escape),
+            KeyCode::Enter => Some(AppAction::SettingsActivate),
+            KeyCode::Up => Some(AppAction::MoveUp),
+            KeyCode::Down => Some(AppAction::MoveDown),
+            KeyCode::Char('k') if control => Some(AppAction::MoveUp),
+            KeyCode::Char('j') if control => Some(AppAction::MoveDown),
+            KeyCode::Char('s') | KeyCode::Char('S') => Some(AppAction::SettingsSave),
+            _ => None,
+        };
+    }
+    if *mode == AppMode::ConfirmOverwrite {
+        return match key_event.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => Some(AppAction::OverwriteConfirm),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                Some(AppAction::OverwriteCancel)
+            }
+            _ => None,
+        };
+    }
+    if key_event.modifiers.contains(KeyModifiers::ALT) && key_event.code == KeyCode::Char('p') {
+        return Some(AppAction::ExportPaths); // Alt+P for saving the included paths
+    }
+    if key_event.modifiers.contains(KeyModifiers::ALT) && key_event.code == KeyCode::Char('s') {
+        return Some(AppAction::SaveSelection); // Alt+S for saving the selection file
+    }
+    // In list focus plain characters navigate instead of searching
+    if focus == Focus::List {
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Char('d') if control => return Some(AppAction::PageDown),
+            KeyCode::Char('u') if control => return Some(AppAction::PageUp),
+            KeyCode::Char(c) if !control => return list_focus_action(c),
+            KeyCode::Backspace => return None,
+            _ => {}
+        }
+    }
+
     // Check for Ctrl combinations first
     if key_event.modifiers.contains(KeyModifiers::CONTROL) {
         match key_event.code {
             KeyCode::Char('e') => return Some(AppAction::Export),  // Ctrl+E for export output
+            KeyCode::Char('q') => return Some(AppAction::ExportAndQuit),  // Ctrl+Q for export output and quit
             KeyCode::Char('h') => return Some(AppAction::ShowHelp),  // Ctrl+H for help
+            KeyCode::Char(',') => return Some(AppAction::OpenSettings),  // Ctrl+, for the settings panel
+            KeyCode::Char('s') => return Some(AppAction::SaveSession),  // Ctrl+S for saving the session
+            KeyCode::Char('r') => return Some(AppAction::CycleSearchMode),  // Ctrl+R for fuzzy/regex/exact search
+            KeyCode::Char('g') => return Some(AppAction::ToggleContentSearch),  // Ctrl+G for searching file contents
+            KeyCode::Char('b') => return Some(AppAction::ToggleTreeView),  // Ctrl+B for the hierarchical tree view
+            KeyCode::Char('o') => return Some(AppAction::ToggleNoTree),  // Ctrl+O for leaving the file tree out of the output
+            KeyCode::Char('f') => return Some(AppAction::ToggleGitignore),  // Ctrl+F for rescanning with or without .gitignore
+            KeyCode::Char('p') | KeyCode::Char('P')
+                if key_event.modifiers.contains(KeyModifiers::SHIFT) =>
+            {
+                return Some(AppAction::OpenCommandPalette); // Ctrl+Shift+P for the command palette
+            }
+            KeyCode::Char('p') => return Some(AppAction::TogglePreview),  // Ctrl+P for the file preview pane
+            KeyCode::Char('l') => return Some(AppAction::CopyPaths),  // Ctrl+L for copying the list of included paths
+            KeyCode::Char('v') => return Some(AppAction::OpenSelectionSummary),  // Ctrl+V for viewing the included files
             KeyCode::Char('j') => return Some(AppAction::MoveDown),  // Ctrl+J for moving down
             KeyCode::Char('k') => return Some(AppAction::MoveUp),  // Ctrl+K for moving up
+            KeyCode::Char('z') | KeyCode::Char('Z')
+                if key_event.modifiers.contains(KeyModifiers::SHIFT) =>
+            {
+                return Some(AppAction::Redo); // Ctrl+Shift+Z for redo
+            }
+            KeyCode::Char('a') | KeyCode::Char('A')
+                if key_event.modifiers.contains(KeyModifiers::SHIFT) =>
+            {
+                return Some(AppAction::SelectNone); // Ctrl+Shift+A for select none
+            }
+            KeyCode::Char('a') => return Some(AppAction::SelectAll),  // Ctrl+A for selecting all visible items
+            KeyCode::Char('n') | KeyCode::Char('d') => return Some(AppAction::SelectNone),  // Ctrl+N/Ctrl+D for deselecting all visible items
+            // Most terminals send Ctrl+I as Tab, so Ctrl+T is offered as well
+            KeyCode::Char('i') | KeyCode::Char('t') => return Some(AppAction::InvertSelection),  // Ctrl+I/Ctrl+T for inverting visible items
+            KeyCode::Char('z') => return Some(AppAction::Undo),  // Ctrl+Z for undo
+            KeyCode::Char('y') => return Some(AppAction::Redo),  // Ctrl+Y for redo
             _ => return None,  // Ignore other Ctrl combinations
         }
     }
@@ -65,12 +185,17 @@ pub fn handle_key_event(key_event: KeyEvent, mode: &crate::ui::app::AppMode) ->
         KeyCode::Esc => Some(AppAction::Escape),
         KeyCode::Enter => Some(AppAction::ToggleSelection),
         KeyCode::Backspace => Some(AppAction::SearchBackspace),
+        KeyCode::Tab => Some(AppAction::ToggleFocus),
+        // Many terminals report neither Ctrl+Shift+P nor Ctrl+,
+        KeyCode::F(1) => Some(AppAction::OpenCommandPalette),
+        KeyCode::F(2) => Some(AppAction::OpenSettings),
+        KeyCode::F(5) => Some(AppAction::Rescan),
 
         // Arrow keys for navigation
         KeyCode::Up => Some(AppAction::MoveUp),
         KeyCode::Down => Some(AppAction::MoveDown),
-        KeyCode::Left => Some(AppAction::MoveUp),
-        KeyCode::Right => Some(AppAction::MoveDown),
+        KeyCode::Left => Some(AppAction::Collapse),
+        KeyCode::Right => Some(AppAction::Expand),
         KeyCode::PageUp => Some(AppAction::PageUp),
         KeyCode::PageDown => Some(AppAction::PageDown),
         KeyCode::Home => Some(AppAction::MoveToTop),
@@ -83,6 +208,44 @@ pub fn handle_key_event(key_event: KeyEvent, mode: &crate::ui::app::AppMode) ->
     }
 }
 
+/// Vim-style keys for the list focus; other characters do nothing
+fn list_focus_action(c: char) -> Option<AppAction> {
+    match c {
+        'j' => Some(AppAction::MoveDown),
+        'k' => Some(AppAction::MoveUp),
+        'h' => Some(AppAction::Collapse),
+        'l' => Some(AppAction::Expand),
+        'g' => Some(AppAction::MoveToTop),
+        'G' => Some(AppAction::MoveToBottom),
+        ' ' => Some(AppAction::ToggleSelection),
+        'e' => Some(AppAction::OpenInEditor),
+        's' => Some(AppAction::CycleSortOrder),
+        'f' => Some(AppAction::CycleStateFilter),
+        '/' => Some(AppAction::ToggleFocus),
+        _ => None,
+    }
+}
+
+/// Wheel scrolling and left clicks on the main screen; dialogs ignore the mouse
+pub fn handle_mouse_event(
+    mouse_event: MouseEvent,
+    mode: &crate::ui::app::AppMode,
+) -> Option<AppAction> {
+    use crate::ui::app::AppMode;
+
+    if !matches!(mode, AppMode::Main | AppMode::ContentSearch) {
+        return None;
+    }
+    match mouse_event.kind {
+        MouseEventKind::ScrollUp => Some(AppAction::ScrollUp),
+        MouseEventKind::ScrollDown => Some(AppAction::ScrollDown),
+        MouseEventKind::Down(MouseButton::Left) => {
+            Some(AppAction::Click(mouse_event.column, mouse_event.row))
+        }
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AppAction {
     Escape,
@@ -93,13 +256,65 @@ pub enum AppAction {
     PageDown,
     MoveToTop,
     MoveToBottom,
+    ScrollUp,
+    ScrollDown,
+    /// Left click at a terminal column and row
+    Click(u16, u16),
     Export,
+    ExportAndQuit,
     ShowHelp,
+    OpenSettings,
+    OpenCommandPalette,
+    /// Enter in the command palette: run the selected command
+    RunCommand,
+    CommandPaletteChar(char),
+    CommandPaletteBackspace,
+    OpenSelectionSummary,
+    /// Enter in the selection summary: show the file in the main list
+    SummaryJump,
+    SummaryDeselect,
+    /// Enter in the settings panel
+    SettingsActivate,
+    SettingsSave,
+    SettingsChar(char),
+    SettingsBackspace,
+    SelectAll,
+    SelectNone,
+    InvertSelection,
+    Undo,
+    Redo,
+    SaveSession,
+    CopyPaths,
+    /// Write the included paths to the `--export-paths` file
+    ExportPaths,
+    /// Write the included files to `.gthr-selection` for `--load-selection`
+    SaveSelection,
+    /// Edit the file under the cursor in `$EDITOR`
+    OpenInEditor,
+    /// Cycle the order of the flat list: relevance, path, size, modified
+    CycleSortOrder,
+    /// Cycle the list between all items, only included ones and only excluded ones
+    CycleStateFilter,
+    TogglePreview,
+    /// Switch plain keys between the search and the list
+    ToggleFocus,
+    ToggleNoTree,
+    ToggleTreeView,
+    /// Rescan the tree to pick up files changed on disk
+    Rescan,
+    /// Rescan the tree with `.gitignore` handling flipped
+    ToggleGitignore,
+    Collapse,
+    Expand,
+    CycleSearchMode,
+    ToggleContentSearch,
     SearchChar(char),
     SearchBackspace,
     FileSaveChar(char),
     FileSaveBackspace,
     FileSaveConfirm,
+    OverwriteConfirm,
+    OverwriteCancel,
 }
 
 impl Default for EventHandler {
@@ -1,21 +1,34 @@
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
     text::{Line, Span},
     widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
 };
 
 use crate::directory::state::SelectionState;
-use crate::fuzzy::filter::get_node_display_path;
-use crate::ui::app::{App, AppMode};
+use crate::fuzzy::filter::{SearchMode, get_node_display_path, node_depth};
+use crate::output::formatter::{estimate_tokens, format_file_size, format_token_count};
+use crate::ui::app::{App, AppMode, Focus, TokenLevel};
+use crate::ui::preview::{FilePreview, scroll_offset};
 
 pub fn draw_ui(f: &mut Frame, app: &mut App) {
     let size = f.size();
 
     match app.mode {
-        AppMode::Main => draw_main_interface(f, app, size),
+        AppMode::Main | AppMode::ContentSearch => draw_main_interface(f, app, size),
         AppMode::Help => draw_help_interface(f, app, size),
         AppMode::FileSave => draw_file_save_dialog(f, app, size),
+        AppMode::ConfirmOverwrite => draw_overwrite_dialog(f, app, size),
+        AppMode::Settings | AppMode::SettingsEdit => draw_settings_panel(f, app, size),
+        AppMode::CommandPalette => {
+            draw_main_interface(f, app, size);
+            draw_command_palette(f, app, size);
+        }
+        AppMode::SelectionSummary => {
+            draw_main_interface(f, app, size);
+            draw_selection_summary(f, app, size);
+        }
     }
 }
 
@@ -33,16 +46,150 @@ fn draw_main_interface(f: &mut Frame, app: &mut App, area: Rect) {
         .split(area);
 
     draw_search_bar(f, app, chunks[0]);
-    draw_file_list(f, app, chunks[1]);
+    if app.show_preview {
+        let list_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+        draw_file_list(f, app, list_chunks[0]);
+        draw_preview(f, app, list_chunks[1]);
+    } else {
+        draw_file_list(f, app, chunks[1]);
+    }
     draw_status_bar(f, app, chunks[2]);
 }
 
+/// Show the file under the cursor, or the children of a directory with their states
+fn draw_preview(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(tree_index) = app.get_selected_tree_index() else {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Preview")
+            .border_style(app.color_scheme.border);
+        f.render_widget(block, area);
+        return;
+    };
+
+    // A content search match is scrolled into view and highlighted
+    let match_line = app
+        .selected_content_match()
+        .filter(|content_match| content_match.node_index == tree_index)
+        .map(|content_match| content_match.line_number.saturating_sub(1) as usize);
+    let height = area.height.saturating_sub(2) as usize;
+    let mut scroll = 0;
+
+    let node = &app.tree.nodes[tree_index];
+    let (title, lines) = if node.is_directory {
+        let lines: Vec<Line> = node
+            .children
+            .iter()
+            .filter_map(|&child_index| app.tree.get_node(child_index))
+            .map(|child| {
+                let (state_indicator, file_type_indicator) = (
+                    state_indicator(child.state),
+                    if child.is_directory { "📁" } else { "📄" },
+                );
+                Line::from(vec![
+                    Span::styled(
+                        format!("{} ", state_indicator),
+                        app.color_scheme.get_state_style(child.state),
+                    ),
+                    Span::styled(
+                        format!("{} {}", file_type_indicator, child.name),
+                        app.color_scheme.text,
+                    ),
+                ])
+            })
+            .collect();
+        let title = format!("{}/ ({} entries)", node.name, node.children.len());
+        (title, lines)
+    } else {
+        let language = match node.language() {
+            "" => "text",
+            language => language,
+        };
+        let title = match node.size {
+            Some(size) => format!("{} · {} · {}", node.name, format_file_size(size), language),
+            None => format!("{} · {}", node.name, language),
+        };
+        let (text_style, note_style) = (app.color_scheme.text, app.color_scheme.help_text);
+        let match_style = app.color_scheme.search_match;
+        let lines = match app.file_preview(tree_index) {
+            FilePreview::Text { lines, truncated } => {
+                let line_count = lines.len();
+                let mut lines: Vec<Line> = lines
+                    .iter()
+                    .enumerate()
+                    .map(|(index, line)| {
+                        let style = if match_line == Some(index) {
+                            match_style
+                        } else {
+                            text_style
+                        };
+                        Line::styled(line.clone(), style)
+                    })
+                    .collect();
+                if *truncated {
+                    lines.push(Line::styled("…", note_style));
+                }
+                match match_line {
+                    Some(line) if line < line_count => {
+                        scroll = scroll_offset(line, line_count, height);
+                    }
+                    Some(line) => lines.push(Line::styled(
+                        format!("(match on line {} is past the preview)", line + 1),
+                        note_style,
+                    )),
+                    None => {}
+                }
+                lines
+            }
+            FilePreview::Binary => vec![Line::styled("(binary file)", note_style)],
+            FilePreview::Unreadable(reason) => {
+                vec![Line::styled(format!("(unreadable: {})", reason), note_style)]
+            }
+        };
+        (title, lines)
+    };
+
+    let preview = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(app.color_scheme.border),
+        )
+        .scroll((scroll as u16, 0));
+    f.render_widget(preview, area);
+}
+
+/// Frames of the spinner shown while a content search is running
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
 fn draw_search_bar(f: &mut Frame, app: &App, area: Rect) {
-    let search_text = if app.search_query.is_empty() {
-        "Type to search files and directories..."
+    if app.mode == AppMode::ContentSearch {
+        draw_content_search_bar(f, app, area);
+        return;
+    }
+
+    let placeholder = match app.search_mode {
+        SearchMode::Fuzzy => "Type to search files and directories...",
+        SearchMode::Substring => "Type text to match relative paths, ignoring case...",
+        SearchMode::Regex => "Type a regex to match relative paths...",
+        SearchMode::Exact => "Type text to match relative paths exactly...",
+    };
+    let query = if app.search_query.is_empty() {
+        placeholder
     } else {
         &app.search_query
     };
+    let mut search_text = match app.search_mode.label() {
+        Some(label) => format!("{} {}", label, query),
+        None => query.to_string(),
+    };
+    for exclusion in &app.filtered_results.exclusions {
+        search_text.push_str(&format!("  [−{}]", exclusion));
+    }
 
     let style = if app.search_query.is_empty() {
         app.color_scheme.help_text
@@ -50,13 +197,60 @@ fn draw_search_bar(f: &mut Frame, app: &App, area: Rect) {
         app.color_scheme.text
     };
 
+    // Surface invalid regexes in the border while all items stay visible
+    let title = match &app.filtered_results.error {
+        Some(error) => format!("Search (invalid regex: {})", error),
+        None => "Search".to_string(),
+    };
+
+    let (title, border_style) = search_focus_title(app, title);
     let search_paragraph = Paragraph::new(search_text)
         .style(style)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Search")
-                .border_style(app.color_scheme.border),
+                .title(title)
+                .border_style(border_style),
+        )
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(search_paragraph, area);
+}
+
+/// Dim the search bar and point back to it while keys drive the list
+fn search_focus_title(app: &App, title: String) -> (String, Style) {
+    match app.focus {
+        Focus::Search => (title, app.color_scheme.border),
+        Focus::List => (format!("{} (Tab: type)", title), app.color_scheme.help_text),
+    }
+}
+
+fn draw_content_search_bar(f: &mut Frame, app: &App, area: Rect) {
+    let (search_text, style) = if app.content_query.is_empty() {
+        ("Type to search inside files...", app.color_scheme.help_text)
+    } else {
+        (app.content_query.as_str(), app.color_scheme.text)
+    };
+
+    let title = if app.is_content_search_running() {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as usize;
+        let frame = SPINNER_FRAMES[(millis / 100) % SPINNER_FRAMES.len()];
+        format!("Content Search {} (Ctrl+G: back to paths)", frame)
+    } else {
+        "Content Search (Ctrl+G: back to paths)".to_string()
+    };
+
+    let (title, border_style) = search_focus_title(app, title);
+    let search_paragraph = Paragraph::new(search_text)
+        .style(style)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(border_style),
         )
         .wrap(Wrap { trim: true });
 
@@ -70,6 +264,7 @@ fn draw_file_list(f: &mut Frame, app: &mut App, area: Rect) {
 
     // Update the app's viewport height to match the actual visible area
     app.viewport_height = actual_viewport_height;
+    app.file_list_area = area;
 
     let items: Vec<ListItem> = app
         .filtered_results
@@ -82,15 +277,30 @@ fn draw_file_list(f: &mut Frame, app: &mut App, area: Rect) {
             // viewport_index is now 0-based index within the visible viewport
             // The actual index in the filtered results is scroll_offset + viewport_index
             let actual_index = app.scroll_offset + viewport_index;
-            create_list_item(app, tree_index, actual_index == app.selected_index)
+            create_list_item(app, tree_index, actual_index)
         })
         .collect();
 
+    let title = match (app.is_tree_view_active(), app.focus) {
+        (true, Focus::Search) => "Tree (Enter = toggle ✓/✗, ←/→ = collapse/expand)",
+        (true, Focus::List) => "Tree [list: j/k move, Space toggle, h/l collapse/expand]",
+        (false, Focus::Search) => "Files and Directories (Enter = toggle ✓/✗)",
+        (false, Focus::List) => {
+            "Files and Directories [list: j/k move, g/G top/bottom, Space toggle]"
+        }
+    };
+    let mut title = match app.sort_order.label() {
+        Some(label) if !app.is_tree_view_active() => format!("{} {}", title, label),
+        _ => title.to_string(),
+    };
+    if let Some(label) = app.state_filter.label() {
+        title = format!("{} {}", title, label);
+    }
     let list = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Files and Directories (Enter = toggle ✓/✗)")
+                .title(title)
                 .border_style(app.color_scheme.border),
         )
         .style(app.color_scheme.background);
@@ -98,15 +308,32 @@ fn draw_file_list(f: &mut Frame, app: &mut App, area: Rect) {
     f.render_widget(list, area);
 }
 
-fn create_list_item(app: &App, tree_index: usize, is_selected: bool) -> ListItem {
+fn create_list_item(app: &App, tree_index: usize, row: usize) -> ListItem<'_> {
     if let Some(node) = app.tree.get_node(tree_index) {
-        let display_path = get_node_display_path(&app.tree, tree_index);
-
-        let state_indicator = match node.state {
-            SelectionState::Included => "✓",
-            SelectionState::Excluded => "✗",
-            SelectionState::Partial => "◐",
+        let is_selected = row == app.selected_index;
+        let display_path = if app.is_tree_view_active() {
+            let expander = match (node.is_directory, app.expanded.contains(&tree_index)) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                (false, _) => "",
+            };
+            let indent = "  ".repeat(node_depth(&app.tree, tree_index));
+            format!("{}{}{}", indent, expander, node.name)
+        } else {
+            get_node_display_path(&app.tree, tree_index)
         };
+        // Content search rows show the matching line
+        let display_path = match app.filtered_results.content_matches.get(row) {
+            Some(content_match) => format!(
+                "{}:{}: {}",
+                display_path,
+                content_match.line_number,
+                content_match.line.trim()
+            ),
+            None => display_path,
+        };
+
+        let state_indicator = state_indicator(node.state);
 
         let file_type_indicator = if node.is_directory { "📁" } else { "📄" };
 
@@ -115,12 +342,17 @@ fn create_list_item(app: &App, tree_index: usize, is_selected: bool) -> ListItem
         // Get base style for the state, not influenced by selection
         let base_style = app.color_scheme.get_state_style(node.state);
 
-        let spans = vec![
+        let mut spans = vec![
             Span::styled(cursor_indicator, app.color_scheme.text),
             Span::styled(format!("{} ", state_indicator), base_style),
             Span::styled(format!("{} ", file_type_indicator), app.color_scheme.text),
-            Span::styled(display_path, base_style),
         ];
+        spans.extend(highlight_spans(
+            &display_path,
+            &app.filtered_results.highlighted_spans(row),
+            base_style,
+            app.color_scheme.search_match,
+        ));
 
         if let Some(size) = node.size {
             let size_str = format_file_size(size);
@@ -142,23 +374,107 @@ fn create_list_item(app: &App, tree_index: usize, is_selected: bool) -> ListItem
     }
 }
 
+fn state_indicator(state: SelectionState) -> &'static str {
+    match state {
+        SelectionState::Included => "✓",
+        SelectionState::Excluded => "✗",
+        SelectionState::Partial => "◐",
+    }
+}
+
+/// Split `text` into spans, styling the character ranges in `ranges` with `highlight_style`
+fn highlight_spans(
+    text: &str,
+    ranges: &[(usize, usize)],
+    base_style: Style,
+    highlight_style: Style,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut current_highlighted = false;
+
+    for (char_index, c) in text.chars().enumerate() {
+        let highlighted = ranges
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&char_index));
+        if highlighted != current_highlighted && !current.is_empty() {
+            let style = if current_highlighted {
+                highlight_style
+            } else {
+                base_style
+            };
+            spans.push(Span::styled(std::mem::take(&mut current), style));
+        }
+        current_highlighted = highlighted;
+        current.push(c);
+    }
+
+    if !current.is_empty() {
+        let style = if current_highlighted {
+            highlight_style
+        } else {
+            base_style
+        };
+        spans.push(Span::styled(current, style));
+    }
+    spans
+}
+
 fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
     let stats = app.get_stats();
 
-    let left_text = format!(
-        "Files: {}/{} | Size: {} | Filtered: {}",
+    let mut left_text = format!(
+        "Files: {}/{} | Size: {} | {} | Filtered: {}",
         stats.included_files,
         stats.total_files,
         stats.format_size(),
+        stats.format_lines(),
         stats.filtered_count
     );
 
+    if let Some(max_depth) = app.max_depth {
+        left_text.push_str(&format!(" | depth: {}", max_depth));
+    }
+    if app.no_tree {
+        left_text.push_str(" | [no tree]");
+    }
+    if let Some(profile) = &app.profile {
+        left_text.push_str(&format!(" | profile: {}", profile));
+    }
+    left_text.push_str(if app.respect_gitignore {
+        " | gitignore: on"
+    } else {
+        " | gitignore: off"
+    });
+
+    let tokens_text = format!(" | {}", stats.format_tokens());
+    let tokens_style = match stats.token_level(app.token_warn_threshold) {
+        TokenLevel::Normal => app.color_scheme.text,
+        TokenLevel::Near => app.color_scheme.caution,
+        TokenLevel::Over => app.color_scheme.excluded,
+    };
+
+    let size_warning = app
+        .max_total_size_warning
+        .filter(|&threshold| stats.total_size > threshold)
+        .map(|threshold| format!(" ⚠ over {} ", format_file_size(threshold)));
+
+    let mut status_text = String::new();
+    if let Some((message, _)) = &app.status_message {
+        status_text = format!(" | {}", message);
+    }
+
     // Adjust help text based on available width
     let available_width = area.width.saturating_sub(4) as usize; // Account for borders
-    let left_text_len = left_text.len();
+    let left_text_len = left_text.len()
+        + tokens_text.len()
+        + size_warning.as_ref().map_or(0, |warning| warning.len() + 1)
+        + status_text.len();
     let remaining_width = available_width.saturating_sub(left_text_len);
 
-    let right_text = if remaining_width > 80 {
+    let right_text = if remaining_width > 110 {
+        "↑/↓: Move | Enter: Toggle ✓/✗ | Ctrl+A/N/T: All/None/Invert | Ctrl+E: Export | Ctrl+H: Help"
+    } else if remaining_width > 80 {
         "↑/↓: Move | Enter: Toggle ✓/✗ | Ctrl+E: Export | Ctrl+H: Help"
     } else if remaining_width > 60 {
         "↑/↓: Move | Enter: Toggle | Ctrl+E: Export | Ctrl+H: Help"
@@ -175,7 +491,14 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(area);
 
-    let left_paragraph = Paragraph::new(left_text)
+    let mut left_spans = vec![Span::raw(left_text), Span::styled(tokens_text, tokens_style)];
+    if let Some(warning) = size_warning {
+        left_spans.push(Span::raw(" "));
+        left_spans.push(Span::styled(warning, app.color_scheme.warning));
+    }
+    left_spans.push(Span::raw(status_text));
+
+    let left_paragraph = Paragraph::new(Line::from(left_spans))
         .style(app.color_scheme.text)
         .block(
             Block::default()
@@ -203,17 +526,42 @@ fn draw_help_interface(f: &mut Frame, app: &App, area: Rect) {
         Line::from("Search:"),
         Line::from("  Type       Add any character to search (letters, numbers, symbols)"),
         Line::from("  Backspace  Delete search character"),
+        Line::from("  Ctrl+R     Cycle fuzzy / [substring] / [regex] / [exact] search"),
+        Line::from("  Ctrl+G     Search inside file contents (again to go back)"),
         Line::from("  Esc        Clear search text (or quit if empty)"),
         Line::from(""),
         Line::from("Navigation:"),
         Line::from("  ↑/↓        Move up/down"),
-        Line::from("  ←/→        Move up/down (alternative)"),
+        Line::from("  ←/→        Move up/down (collapse/expand in the tree view)"),
+        Line::from("  Ctrl+B     Toggle the tree view (a search shows the flat list)"),
+        Line::from("  Ctrl+P     Toggle the preview of the item under the cursor"),
+        Line::from("  Tab        Switch between typing a search and vim keys for the list"),
+        Line::from("             (j/k, g/G, Ctrl+D/U, h/l, Space to toggle, Esc or / to search)"),
+        Line::from("             (e edits the file under the cursor in $EDITOR)"),
+        Line::from("             (s sorts by relevance, path, size or modification time)"),
+        Line::from("             (f shows all items, only included or only excluded ones)"),
         Line::from(""),
         Line::from("Selection:"),
         Line::from("  Enter      Toggle ✓ included / ✗ excluded"),
+        Line::from("  Ctrl+A     Include all visible files"),
+        Line::from("  Ctrl+N     Exclude all visible files (also Ctrl+D, Ctrl+Shift+A)"),
+        Line::from("  Ctrl+T     Invert visible items (Ctrl+I where supported)"),
+        Line::from("  Ctrl+Z     Undo last selection change"),
+        Line::from("  Ctrl+Y     Redo selection change"),
         Line::from(""),
         Line::from("Actions:"),
-        Line::from("  Ctrl+E     Export output and quit"),
+        Line::from("  Ctrl+E     Export output and keep going (clipboard copies stay open)"),
+        Line::from("  Ctrl+Q     Export output and quit"),
+        Line::from("  Ctrl+S     Save selections to the session file"),
+        Line::from("  Ctrl+O     Toggle the file tree in the output ([no tree] when left out)"),
+        Line::from("  F5         Rescan to pick up created and deleted files"),
+        Line::from("  Ctrl+F     Rescan with .gitignore rules off/on, keeping the selection"),
+        Line::from("  Ctrl+L     Copy the list of included paths to the clipboard"),
+        Line::from("  Alt+P      Save the list of included paths to a text file"),
+        Line::from("  Alt+S      Save the selection to .gthr-selection (--load-selection)"),
+        Line::from("  Ctrl+V     List the included files (Enter: jump to one, D/x: deselect it)"),
+        Line::from("  Ctrl+,     Edit settings (F2 where Ctrl+, is not reported)"),
+        Line::from("  F1         Search and run any action (also Ctrl+Shift+P where reported)"),
         Line::from("  Ctrl+H     Show this help"),
         Line::from("  Esc        Clear search (or quit if search empty)"),
         Line::from(""),
@@ -296,7 +644,11 @@ fn draw_file_save_dialog(f: &mut Frame, app: &App, area: Rect) {
 
     // Content size info
     let content_size = if let Some(content) = &app.pending_content {
-        format_file_size(content.len() as u64)
+        format!(
+            "{}, ~{} tokens",
+            format_file_size(content.len() as u64),
+            format_token_count(estimate_tokens(content.len() as u64))
+        )
     } else {
         "Unknown".to_string()
     };
@@ -310,7 +662,10 @@ fn draw_file_save_dialog(f: &mut Frame, app: &App, area: Rect) {
 
     // Input field
     let input_text = if app.file_save_input.is_empty() {
-        "📁 Enter file path (or press Enter for default)".to_string()
+        format!(
+            "📁 Enter file path (or press Enter for {})",
+            app.default_save_path.display()
+        )
     } else {
         app.file_save_input.clone()
     };
@@ -347,19 +702,288 @@ fn draw_file_save_dialog(f: &mut Frame, app: &App, area: Rect) {
     }
 }
 
-fn format_file_size(size: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
-    let mut size_f = size as f64;
-    let mut unit_index = 0;
+fn draw_overwrite_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let popup_area = centered_rect(60, 20, area);
+    f.render_widget(Clear, popup_area);
 
-    while size_f >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size_f /= 1024.0;
-        unit_index += 1;
-    }
+    let block = Block::default()
+        .title("File Exists")
+        .borders(Borders::ALL)
+        .border_style(app.color_scheme.border)
+        .style(app.color_scheme.background);
+
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(2), // Question
+            Constraint::Length(1), // Help text
+        ])
+        .split(popup_area);
+
+    let path = app
+        .overwrite_path
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    let tokens = app.pending_content.as_ref().map_or(0, |content| {
+        estimate_tokens(content.len() as u64)
+    });
+    let question = Paragraph::new(format!(
+        "{} exists — overwrite with ~{} tokens of output? (y/n)",
+        path,
+        format_token_count(tokens)
+    ))
+        .style(app.color_scheme.text)
+        .wrap(Wrap { trim: true });
+
+    let help_text = Paragraph::new("y: Overwrite | n/Esc: Cancel")
+        .style(app.color_scheme.help_text)
+        .alignment(Alignment::Center);
+
+    f.render_widget(block, popup_area);
+    f.render_widget(question, popup_chunks[0]);
+    f.render_widget(help_text, popup_chunks[1]);
+}
+
+fn draw_command_palette(f: &mut Frame, app: &App, area: Rect) {
+    let Some(palette) = &app.command_palette else {
+        return;
+    };
+    let popup_area = centered_rect(70, 60, area);
+    f.render_widget(Clear, popup_area);
 
-    if unit_index == 0 {
-        format!("{} {}", size, UNITS[unit_index])
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Query
+            Constraint::Min(0),    // Commands
+        ])
+        .split(popup_area);
+
+    let (query, query_style) = if palette.query.is_empty() {
+        ("Type to search actions...", app.color_scheme.help_text)
     } else {
-        format!("{:.1} {}", size_f, UNITS[unit_index])
+        (palette.query.as_str(), app.color_scheme.text)
+    };
+    let input = Paragraph::new(query).style(query_style).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(app.color_scheme.border)
+            .title("Command Palette (Enter: Run | Esc: Close)"),
+    );
+
+    // Keep the selected command in view
+    let height = popup_chunks[1].height.saturating_sub(2) as usize;
+    let first = palette.selected_index.saturating_sub(height.saturating_sub(1));
+    let name_width = palette
+        .results
+        .iter()
+        .map(|command| command.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = palette
+        .results
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(height)
+        .map(|(index, command)| {
+            let (name_style, detail_style) = if index == palette.selected_index {
+                (app.color_scheme.selected, app.color_scheme.selected)
+            } else {
+                (app.color_scheme.text, app.color_scheme.help_text)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<width$}", command.name, width = name_width), name_style),
+                Span::styled(format!("  {:<8}", command.keybind), detail_style),
+                Span::styled(format!("  {}", command.description), detail_style),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(app.color_scheme.border)
+            .title(format!("{} actions", palette.results.len())),
+    );
+
+    f.render_widget(input, popup_chunks[0]);
+    f.render_widget(list, popup_chunks[1]);
+}
+
+/// Included files with their sizes, and the totals of the selection in the title
+fn draw_selection_summary(f: &mut Frame, app: &App, area: Rect) {
+    let Some(summary) = &app.selection_summary else {
+        return;
+    };
+    let popup_area = centered_rect(70, 70, area);
+    f.render_widget(Clear, popup_area);
+
+    let stats = app.get_stats();
+    let noun = if stats.included_files == 1 { "file" } else { "files" };
+    let title = format!(
+        "Selected: {} {} | {} | {} (Enter: Jump | D/x: Deselect | Esc: Close)",
+        stats.included_files,
+        noun,
+        stats.format_size(),
+        stats.format_tokens()
+    );
+
+    // Keep the highlighted file in view
+    let height = popup_area.height.saturating_sub(2) as usize;
+    let first = summary.selected_index.saturating_sub(height.saturating_sub(1));
+    let items: Vec<ListItem> = if summary.files.is_empty() {
+        vec![ListItem::new(Line::styled(
+            "No files included",
+            app.color_scheme.help_text,
+        ))]
+    } else {
+        let paths: Vec<String> = summary
+            .files
+            .iter()
+            .map(|&index| get_node_display_path(&app.tree, index))
+            .collect();
+        let path_width = paths.iter().map(|path| path.chars().count()).max().unwrap_or(0);
+        summary
+            .files
+            .iter()
+            .zip(&paths)
+            .enumerate()
+            .skip(first)
+            .take(height)
+            .map(|(row, (&index, path))| {
+                let (path_style, size_style) = if row == summary.selected_index {
+                    (app.color_scheme.selected, app.color_scheme.selected)
+                } else {
+                    (app.color_scheme.text, app.color_scheme.help_text)
+                };
+                let size = app.tree.nodes[index].size.unwrap_or(0);
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<width$}", path, width = path_width), path_style),
+                    Span::styled(format!("  {:>9}", format_file_size(size)), size_style),
+                ]))
+            })
+            .collect()
+    };
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(app.color_scheme.border)
+            .title(title),
+    );
+    f.render_widget(list, popup_area);
+}
+
+fn draw_settings_panel(f: &mut Frame, app: &App, area: Rect) {
+    let Some(panel) = &app.settings_panel else {
+        return;
+    };
+    let popup_area = centered_rect(80, 80, area);
+    f.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title("Settings")
+        .borders(Borders::ALL)
+        .border_style(app.color_scheme.border)
+        .style(app.color_scheme.background);
+
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Min(0),    // Settings
+            Constraint::Length(3), // Description or value input
+            Constraint::Length(1), // Help text or status message
+        ])
+        .split(popup_area);
+
+    let rows = panel.rows();
+    let key_width = rows.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    // Keep the selected row in view
+    let height = popup_chunks[0].height as usize;
+    let first = panel.selected_index.saturating_sub(height.saturating_sub(1));
+    let items: Vec<ListItem> = rows
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(height)
+        .map(|(index, (key, value))| {
+            let marker = if panel.changed.contains(key) { "*" } else { " " };
+            let value = match value {
+                Some(toml::Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+                None => "(unset)".to_string(),
+            };
+            let style = if index == panel.selected_index {
+                app.color_scheme.selected
+            } else {
+                app.color_scheme.text
+            };
+            ListItem::new(format!("{} {:<width$}  {}", marker, key, value, width = key_width))
+                .style(style)
+        })
+        .collect();
+
+    let bottom = match &panel.input {
+        Some(input) => Paragraph::new(format!("{}▏", input))
+            .style(app.color_scheme.text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(app.color_scheme.border)
+                    .title(format!("{} (empty resets to the default)", panel.selected_key())),
+            ),
+        None => Paragraph::new(panel.selected_description())
+            .style(app.color_scheme.help_text)
+            .wrap(Wrap { trim: true }),
+    };
+
+    let help_text = match &app.status_message {
+        Some((message, _)) => Paragraph::new(message.as_str()).style(app.color_scheme.text),
+        None if panel.input.is_some() => {
+            Paragraph::new("Enter: Apply | Esc: Cancel").style(app.color_scheme.help_text)
+        }
+        None => Paragraph::new(
+            "↑/↓: Move | Enter: Toggle/Edit | S: Save to .gthr.toml | Esc: Apply and close",
+        )
+        .style(app.color_scheme.help_text),
+    }
+    .alignment(Alignment::Center);
+
+    f.render_widget(block, popup_area);
+    f.render_widget(List::new(items), popup_chunks[0]);
+    f.render_widget(bottom, popup_chunks[1]);
+    f.render_widget(help_text, popup_chunks[2]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    #[test]
+    fn test_highlight_spans() {
+        let base = Style::default().fg(Color::Green);
+        let highlight = Style::default().fg(Color::Cyan);
+
+        let spans = highlight_spans("src/mirror.rs", &[(4, 6), (11, 13)], base, highlight);
+        let parts: Vec<(&str, Style)> = spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("src/", base),
+                ("mi", highlight),
+                ("rror.", base),
+                ("rs", highlight),
+            ]
+        );
+
+        let spans = highlight_spans("README.md", &[], base, highlight);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].style, base);
     }
 }
@@ -2,4 +2,7 @@ pub mod app;
 pub mod interface;
 pub mod events;
 pub mod colors;
-
+pub mod command_palette;
+pub mod preview;
+pub mod selection_summary;
+pub mod settings_panel;
@@ -0,0 +1,110 @@
+use crate::constants::{PREVIEW_MAX_BYTES, PREVIEW_MAX_LINES};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Contents shown in the preview pane for a file
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilePreview {
+    /// Leading lines of a text file; `truncated` if the file goes on past them
+    Text { lines: Vec<String>, truncated: bool },
+    /// Not shown, as the file is not text
+    Binary,
+    /// The file could not be read, with the reason
+    Unreadable(String),
+}
+
+impl FilePreview {
+    /// Read at most `PREVIEW_MAX_BYTES` of `path` and keep its first `PREVIEW_MAX_LINES` lines
+    pub fn load(path: &Path) -> Self {
+        let mut bytes = Vec::new();
+        let read = fs::File::open(path)
+            .and_then(|file| file.take(PREVIEW_MAX_BYTES + 1).read_to_end(&mut bytes));
+        if let Err(e) = read {
+            return FilePreview::Unreadable(e.to_string());
+        }
+        if bytes.contains(&0) {
+            return FilePreview::Binary;
+        }
+
+        let mut truncated = bytes.len() as u64 > PREVIEW_MAX_BYTES;
+        bytes.truncate(PREVIEW_MAX_BYTES as usize);
+        let text = String::from_utf8_lossy(&bytes);
+        let mut lines: Vec<String> = text
+            .lines()
+            .take(PREVIEW_MAX_LINES + 1)
+            .map(|line| line.replace('\t', "    "))
+            .collect();
+        if lines.len() > PREVIEW_MAX_LINES {
+            lines.truncate(PREVIEW_MAX_LINES);
+            truncated = true;
+        }
+        FilePreview::Text { lines, truncated }
+    }
+}
+
+/// First row shown when the preview scrolls to `line` in a pane `height` rows tall
+///
+/// The line is placed a third of the way down the pane, without scrolling past the last line.
+pub fn scroll_offset(line: usize, line_count: usize, height: usize) -> usize {
+    line.saturating_sub(height / 3)
+        .min(line_count.saturating_sub(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_preview() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let short = temp_dir.path().join("short.rs");
+        fs::write(&short, "fn main() {\n\tprintln!();\n}\n").unwrap();
+        assert_eq!(
+            FilePreview::load(&short),
+            FilePreview::Text {
+                lines: vec![
+                    "fn main() {".to_string(),
+                    "    println!();".to_string(),
+                    "}".to_string()
+                ],
+                truncated: false,
+            }
+        );
+
+        let long = temp_dir.path().join("long.txt");
+        let content: String = (0..PREVIEW_MAX_LINES + 50)
+            .map(|i| format!("line {}\n", i))
+            .collect();
+        fs::write(&long, content).unwrap();
+        match FilePreview::load(&long) {
+            FilePreview::Text { lines, truncated } => {
+                assert_eq!(lines.len(), PREVIEW_MAX_LINES);
+                assert!(truncated);
+            }
+            other => panic!("expected text preview, got {:?}", other),
+        }
+
+        let binary = temp_dir.path().join("image.bin");
+        fs::write(&binary, [0x89, b'P', b'N', b'G', 0, 0, 1]).unwrap();
+        assert_eq!(FilePreview::load(&binary), FilePreview::Binary);
+
+        assert!(matches!(
+            FilePreview::load(&temp_dir.path().join("missing")),
+            FilePreview::Unreadable(_)
+        ));
+    }
+
+    #[test]
+    fn test_scroll_offset() {
+        // Lines near the top need no scrolling
+        assert_eq!(scroll_offset(5, 100, 30), 0);
+        assert_eq!(scroll_offset(40, 100, 30), 30);
+        // The last page stays full
+        assert_eq!(scroll_offset(95, 100, 30), 70);
+        // Files shorter than the pane never scroll
+        assert_eq!(scroll_offset(8, 10, 30), 0);
+    }
+}
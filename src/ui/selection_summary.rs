@@ -0,0 +1,83 @@
+use crate::directory::tree::DirectoryTree;
+
+/// Included files listed by the selection summary, opened with Ctrl+V
+#[derive(Debug, Clone, Default)]
+pub struct SelectionSummary {
+    /// Tree indices of the included files, sorted by relative path
+    pub files: Vec<usize>,
+    pub selected_index: usize,
+}
+
+impl SelectionSummary {
+    pub fn new(tree: &DirectoryTree) -> Self {
+        let mut summary = Self::default();
+        summary.refresh(tree);
+        summary
+    }
+
+    /// Re-read the included files after the selection changed, keeping the cursor in range
+    pub fn refresh(&mut self, tree: &DirectoryTree) {
+        // The files `get_all_included_files` returns, with their indices
+        let mut files: Vec<_> = tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.state.is_included() && !node.is_directory && node.is_text_file)
+            .map(|(index, node)| (tree.relative_path(&node.path), index))
+            .collect();
+        files.sort();
+        self.files = files.into_iter().map(|(_, index)| index).collect();
+        self.selected_index = self.selected_index.min(self.files.len().saturating_sub(1));
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected_index + 1 < self.files.len() {
+            self.selected_index += 1;
+        }
+    }
+
+    /// Tree index of the highlighted file
+    pub fn selected(&self) -> Option<usize> {
+        self.files.get(self.selected_index).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::state::SelectionState;
+    use std::path::Path;
+
+    #[test]
+    fn test_summary_files() {
+        let root = Path::new("/project");
+        let mut tree = DirectoryTree::new(root.to_path_buf());
+        tree.add_node(root.join("src"), true, root);
+        let main_rs = tree
+            .add_node(root.join("src/main.rs"), false, &root.join("src"))
+            .unwrap();
+        tree.add_node(root.join("README.md"), false, root);
+        let build_rs = tree.add_node(root.join("build.rs"), false, root).unwrap();
+        for index in [main_rs, build_rs] {
+            tree.set_state(index, SelectionState::Included);
+        }
+
+        let mut summary = SelectionSummary::new(&tree);
+        assert_eq!(summary.files, vec![build_rs, main_rs]);
+        summary.move_down();
+        summary.move_down();
+        assert_eq!(summary.selected(), Some(main_rs));
+
+        // The cursor stays on the last file once the one under it is gone
+        tree.set_state(main_rs, SelectionState::Excluded);
+        summary.refresh(&tree);
+        assert_eq!(summary.selected(), Some(build_rs));
+        tree.set_state(build_rs, SelectionState::Excluded);
+        summary.refresh(&tree);
+        assert_eq!(summary.selected(), None);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::config::settings::Settings;
+use anyhow::Result;
+
+/// Settings read while the tree is traversed, which only apply from the next run
+const NEXT_RUN_KEYS: &[&str] = &[
+    "max_file_size",
+    "min_file_size",
+    "respect_gitignore",
+    "show_hidden",
+    "max_depth",
+    "ignore_files",
+    "cache_path",
+];
+
+/// Settings being edited in the interactive settings panel
+#[derive(Debug, Clone)]
+pub struct SettingsPanel {
+    /// Copy of the settings the edits go to, applied when the panel is closed
+    pub draft: Settings,
+    pub selected_index: usize,
+    /// Text of the value being edited, `None` while browsing
+    pub input: Option<String>,
+    /// Keys edited since the panel was opened, in the order they were first changed
+    pub changed: Vec<&'static str>,
+}
+
+impl SettingsPanel {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            draft: settings.clone(),
+            selected_index: 0,
+            input: None,
+            changed: Vec::new(),
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected_index + 1 < Settings::setting_docs().len() {
+            self.selected_index += 1;
+        }
+    }
+
+    pub fn selected_key(&self) -> &'static str {
+        Settings::setting_docs()[self.selected_index].0
+    }
+
+    /// Description of the selected setting, noting when it only applies from the next run
+    pub fn selected_description(&self) -> String {
+        let (key, doc) = Settings::setting_docs()[self.selected_index];
+        let doc = doc.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+        if NEXT_RUN_KEYS.contains(&key) {
+            format!("{} (takes effect the next time gthr starts)", doc)
+        } else {
+            doc
+        }
+    }
+
+    /// Every setting with its current value, `None` for unset options
+    pub fn rows(&self) -> Vec<(&'static str, Option<toml::Value>)> {
+        Settings::setting_docs()
+            .iter()
+            .map(|(key, _)| (*key, self.draft.value(key).ok().flatten()))
+            .collect()
+    }
+
+    /// Enter on the selected setting: toggle a boolean, otherwise start editing its value
+    pub fn activate(&mut self) -> Result<()> {
+        let key = self.selected_key();
+        match self.draft.value(key)? {
+            Some(toml::Value::Boolean(value)) => {
+                self.set(key, Some(toml::Value::Boolean(!value)))?;
+            }
+            // Strings are edited without their quotes
+            Some(toml::Value::String(value)) => self.input = Some(value),
+            Some(value) => self.input = Some(value.to_string()),
+            None => self.input = Some(String::new()),
+        }
+        Ok(())
+    }
+
+    /// Apply the edited value; an empty input resets the setting to its default
+    ///
+    /// On error the input stays open so the value can be corrected.
+    pub fn confirm_edit(&mut self) -> Result<()> {
+        let Some(input) = &self.input else {
+            return Ok(());
+        };
+        let key = self.selected_key();
+        let value = if input.trim().is_empty() {
+            None
+        } else if let Some(toml::Value::String(_)) = self.draft.value(key)? {
+            Some(toml::Value::String(input.clone()))
+        } else {
+            Some(Settings::parse_value(input.trim()))
+        };
+        self.set(key, value)?;
+        self.input = None;
+        Ok(())
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.input = None;
+    }
+
+    pub fn add_char(&mut self, c: char) {
+        if let Some(input) = &mut self.input {
+            input.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(input) = &mut self.input {
+            input.pop();
+        }
+    }
+
+    fn set(&mut self, key: &'static str, value: Option<toml::Value>) -> Result<()> {
+        self.draft.set_value(key, value)?;
+        if !self.changed.contains(&key) {
+            self.changed.push(key);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(panel: &mut SettingsPanel, key: &str) {
+        panel.selected_index = 0;
+        while panel.selected_key() != key {
+            panel.move_down();
+        }
+    }
+
+    #[test]
+    fn test_edit_settings() {
+        let mut panel = SettingsPanel::new(&Settings::default());
+
+        select(&mut panel, "max_clipboard_size");
+        panel.activate().unwrap();
+        assert_eq!(panel.input.as_deref(), Some("2097152"));
+        panel.input = Some("big".to_string());
+        assert!(panel.confirm_edit().is_err());
+        assert!(panel.input.is_some());
+        panel.input = Some("1024".to_string());
+        panel.confirm_edit().unwrap();
+        assert_eq!(panel.draft.max_clipboard_size, 1024);
+
+        select(&mut panel, "show_hidden");
+        panel.activate().unwrap();
+        assert!(panel.draft.show_hidden);
+        assert!(panel.input.is_none());
+        assert!(
+            panel
+                .selected_description()
+                .contains("next time gthr starts")
+        );
+
+        // Unset options start empty, and clearing the input unsets them again
+        select(&mut panel, "template_path");
+        panel.activate().unwrap();
+        "gthr.hbs".chars().for_each(|c| panel.add_char(c));
+        panel.confirm_edit().unwrap();
+        assert_eq!(
+            panel.draft.template_path.as_deref(),
+            Some("gthr.hbs".as_ref())
+        );
+        panel.activate().unwrap();
+        panel.input = Some(String::new());
+        panel.confirm_edit().unwrap();
+        assert_eq!(panel.draft.template_path, None);
+
+        assert_eq!(
+            panel.changed,
+            vec!["max_clipboard_size", "show_hidden", "template_path"]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+/// Parse a byte size like `512`, `100KB` or `1.5MB` (units are powers of 1024, case-insensitive)
+pub fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(unit_start);
+
+    let amount: f64 = amount
+        .parse()
+        .map_err(|_| format!("expected a size like 512, 100KB or 2MB, got '{}'", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" | "K" => 1024,
+        "MB" | "M" => 1024 * 1024,
+        "GB" | "G" => 1024 * 1024 * 1024,
+        _ => {
+            return Err(format!(
+                "unknown size unit '{}', expected B, KB, MB or GB",
+                unit.trim()
+            ));
+        }
+    };
+
+    let bytes = amount * multiplier as f64;
+    if bytes > u64::MAX as f64 {
+        return Err(format!("size '{}' is too large", value));
+    }
+    Ok(bytes.round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("512"), Ok(512));
+        assert_eq!(parse_byte_size("512B"), Ok(512));
+        assert_eq!(parse_byte_size("100KB"), Ok(100 * 1024));
+        assert_eq!(parse_byte_size("100kb"), Ok(100 * 1024));
+        assert_eq!(parse_byte_size("1.5MB"), Ok(1536 * 1024));
+        assert_eq!(parse_byte_size("2 GB"), Ok(2 * 1024 * 1024 * 1024));
+
+        assert!(
+            parse_byte_size("10TB")
+                .unwrap_err()
+                .contains("unknown size unit")
+        );
+        assert!(
+            parse_byte_size("KB")
+                .unwrap_err()
+                .contains("expected a size")
+        );
+        assert!(parse_byte_size("").is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much gthr reports on stderr; stdout only ever carries output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Verbosity {
+    /// `--quiet`: errors only
+    Quiet,
+    /// Warnings and success messages
+    #[default]
+    Normal,
+    /// `-v`: also every skipped, ignored or unreadable entry
+    Verbose,
+    /// `-vv`: also whether each file ended up included
+    Trace,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+impl Verbosity {
+    /// Level for `-v` repeated `count` times, or `Quiet` with `--quiet`
+    pub fn from_flags(count: u8, quiet: bool) -> Self {
+        match (quiet, count) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Trace,
+        }
+    }
+
+    /// Level used by `warn!` for the rest of the process
+    pub fn set_global(self) {
+        VERBOSITY.store(self as u8, Ordering::Relaxed);
+    }
+
+    pub fn global() -> Self {
+        match VERBOSITY.load(Ordering::Relaxed) {
+            0 => Verbosity::Quiet,
+            1 => Verbosity::Normal,
+            2 => Verbosity::Verbose,
+            _ => Verbosity::Trace,
+        }
+    }
+}
+
+/// Print a `⚠` warning to stderr unless `--quiet` is set
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        if $crate::verbosity::Verbosity::global() >= $crate::verbosity::Verbosity::Normal {
+            eprintln!("⚠ {}", format_args!($($arg)*));
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_flags() {
+        assert_eq!(Verbosity::from_flags(0, false), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(1, false), Verbosity::Verbose);
+        assert_eq!(Verbosity::from_flags(3, false), Verbosity::Trace);
+        // --quiet wins over -v
+        assert_eq!(Verbosity::from_flags(2, true), Verbosity::Quiet);
+    }
+}
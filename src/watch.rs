@@ -0,0 +1,147 @@
+use crate::constants::WATCH_DEBOUNCE_MS;
+use anyhow::{Context, Result, bail};
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How often a blocked `wait_for_change` checks its stop flag
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Recursive file watcher that reports bursts of changes once they settle
+pub struct ChangeWatcher {
+    // Dropping the watcher stops the events, so it lives as long as the receiver
+    _watcher: Box<dyn Watcher + Send>,
+    events: Receiver<notify::Result<Event>>,
+    ignored: Vec<PathBuf>,
+    debounce: Duration,
+}
+
+impl ChangeWatcher {
+    /// Watch every root recursively, polling every `poll_interval` instead of using
+    /// OS notifications when it is given
+    pub fn new(roots: &[PathBuf], poll_interval: Option<Duration>) -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher: Box<dyn Watcher + Send> = match poll_interval {
+            Some(interval) => Box::new(PollWatcher::new(
+                sender,
+                Config::default().with_poll_interval(interval),
+            )?),
+            None => Box::new(RecommendedWatcher::new(sender, Config::default())?),
+        };
+
+        for root in roots {
+            // Canonical roots make event paths comparable with the ignored paths
+            let root = root
+                .canonicalize()
+                .with_context(|| format!("Failed to watch {}", root.display()))?;
+            watcher
+                .watch(&root, RecursiveMode::Recursive)
+                .with_context(|| format!("Failed to watch {}", root.display()))?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            ignored: Vec::new(),
+            debounce: Duration::from_millis(WATCH_DEBOUNCE_MS),
+        })
+    }
+
+    /// Ignore changes to `path`, such as the output file being regenerated
+    pub fn with_ignored_path(mut self, path: &Path) -> Self {
+        self.ignored.push(canonical_path(path));
+        self
+    }
+
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Block until a change settles, returning `false` once `stop` is set
+    pub fn wait_for_change(&self, stop: &AtomicBool) -> Result<bool> {
+        loop {
+            if stop.load(Ordering::SeqCst) {
+                return Ok(false);
+            }
+            match self.events.recv_timeout(STOP_CHECK_INTERVAL) {
+                Ok(Ok(event)) if self.is_relevant(&event) => break,
+                Ok(Err(e)) => return Err(e).context("File watcher failed"),
+                Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => bail!("File watcher stopped unexpectedly"),
+            }
+        }
+
+        // Wait for the burst to end so a save touching several files triggers one rebuild
+        loop {
+            match self.events.recv_timeout(self.debounce) {
+                Ok(_) if !stop.load(Ordering::SeqCst) => {}
+                Ok(_) => return Ok(false),
+                Err(RecvTimeoutError::Timeout) => return Ok(true),
+                Err(RecvTimeoutError::Disconnected) => bail!("File watcher stopped unexpectedly"),
+            }
+        }
+    }
+
+    /// Whether an event changes something other than the ignored paths; reads are not changes
+    fn is_relevant(&self, event: &Event) -> bool {
+        !matches!(event.kind, EventKind::Access(_))
+            && event.paths.iter().any(|path| !self.ignored.contains(path))
+    }
+}
+
+/// Canonical form of a path that may not exist yet, resolved through its parent
+fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            parent
+                .canonicalize()
+                .map(|parent| parent.join(name))
+                .unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_watcher_ignores_output_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let output_path = temp_dir.path().join("context.md");
+        let source_path = temp_dir.path().join("main.rs");
+        fs::write(&source_path, "fn main() {}")?;
+
+        let stop = AtomicBool::new(false);
+        let watcher = ChangeWatcher::new(&[temp_dir.path().to_path_buf()], None)?
+            .with_ignored_path(&output_path)
+            .with_debounce(Duration::from_millis(100));
+
+        let output_only = Event::new(EventKind::Any).add_path(canonical_path(&output_path));
+        assert!(!watcher.is_relevant(&output_only));
+        let source_changed = output_only.add_path(canonical_path(&source_path));
+        assert!(watcher.is_relevant(&source_changed));
+
+        fs::write(&source_path, "fn main() { run(); }")?;
+        assert!(watcher.wait_for_change(&stop)?);
+
+        stop.store(true, Ordering::SeqCst);
+        assert!(!watcher.wait_for_change(&stop)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,599 @@
+use anyhow::Result;
+use clap::Parser;
+use gthr::cli::Cli;
+use gthr::output::formatter::format_file_list;
+use gthr::{OutputFormatter, SelectionState, Settings, build_directory_tree};
+use std::fs;
+use tempfile::TempDir;
+
+fn create_fixture() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let root_path = temp_dir.path();
+    fs::create_dir_all(root_path.join("src").join("ui"))?;
+    fs::create_dir(root_path.join("docs"))?;
+    fs::write(root_path.join("src").join("main.rs"), "fn main() {}")?;
+    fs::write(
+        root_path.join("src").join("ui").join("app.rs"),
+        "pub struct App;",
+    )?;
+    fs::write(root_path.join("docs").join("guide.md"), "# Guide")?;
+    fs::write(root_path.join("README.md"), "# Test Project")?;
+    Ok(temp_dir)
+}
+
+#[test]
+fn test_exclude_all_with_include_pattern() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root = temp_dir.path().to_string_lossy().to_string();
+    let cli = Cli::try_parse_from(["gthr", "-r", &root, "-E", "-i", "src/**", "direct"])?;
+
+    let tree = build_directory_tree(&cli, &Settings::default())?;
+    let root_path = temp_dir.path();
+    let state_of = |relative: &str| tree.nodes[tree.path_to_index[&root_path.join(relative)]].state;
+
+    assert_eq!(state_of("src"), SelectionState::Included);
+    assert_eq!(state_of("src/main.rs"), SelectionState::Included);
+    assert_eq!(state_of("src/ui/app.rs"), SelectionState::Included);
+    assert_eq!(state_of("docs"), SelectionState::Excluded);
+    assert_eq!(state_of("docs/guide.md"), SelectionState::Excluded);
+    assert_eq!(state_of("README.md"), SelectionState::Excluded);
+    assert_eq!(tree.get_all_included_files().len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_exclude_all_without_patterns() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root = temp_dir.path().to_string_lossy().to_string();
+    let cli = Cli::try_parse_from(["gthr", "-r", &root, "-E", "direct"])?;
+
+    let tree = build_directory_tree(&cli, &Settings::default())?;
+    assert!(
+        tree.nodes
+            .iter()
+            .all(|node| node.state == SelectionState::Excluded)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_include_all_conflicts_with_exclude_all() {
+    let result = Cli::try_parse_from(["gthr", "-I", "-E", "direct"]);
+    assert_eq!(
+        result.err().map(|e| e.kind()),
+        Some(clap::error::ErrorKind::ArgumentConflict)
+    );
+}
+
+#[test]
+fn test_multiple_roots() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let frontend = temp_dir.path().join("frontend");
+    let backend = temp_dir.path().join("backend");
+    fs::create_dir_all(frontend.join("src"))?;
+    fs::create_dir_all(backend.join("src"))?;
+    fs::write(frontend.join("src").join("app.ts"), "export {}")?;
+    fs::write(frontend.join("package.json"), "{}")?;
+    fs::write(backend.join("src").join("main.rs"), "fn main() {}")?;
+
+    let frontend_root = frontend.to_string_lossy().to_string();
+    let backend_root = backend.to_string_lossy().to_string();
+    let cli = Cli::try_parse_from([
+        "gthr",
+        "-r",
+        &frontend_root,
+        "-r",
+        &backend_root,
+        "-i",
+        "src/**",
+        "direct",
+    ])?;
+    let tree = build_directory_tree(&cli, &Settings::default())?;
+
+    let included: Vec<String> = tree
+        .get_all_included_files()
+        .iter()
+        .map(|node| tree.relative_path(&node.path).to_string_lossy().to_string())
+        .collect();
+    assert_eq!(included, vec!["frontend/src/app.ts", "backend/src/main.rs"]);
+
+    let output = OutputFormatter::new().format_output(&tree)?;
+    assert!(output.contains("# frontend/src/app.ts"));
+    assert!(output.contains("# backend/src/main.rs"));
+    assert!(!output.contains("package.json"));
+
+    Ok(())
+}
+
+#[test]
+fn test_git_diff_with_exclude_pattern() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root_path = temp_dir.path();
+    let git = |args: &[&str]| -> Result<()> {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(root_path)
+            .args(["-c", "user.name=gthr", "-c", "user.email=gthr@example.com"])
+            .args(args)
+            .status()?;
+        assert!(status.success(), "git {:?} failed", args);
+        Ok(())
+    };
+    git(&["init", "-q"])?;
+    git(&["add", "."])?;
+    git(&["commit", "-q", "-m", "initial"])?;
+    fs::write(
+        root_path.join("src").join("main.rs"),
+        "fn main() { run(); }",
+    )?;
+    fs::write(root_path.join("docs").join("guide.md"), "# Updated guide")?;
+
+    let root = root_path.to_string_lossy().to_string();
+    let cli = Cli::try_parse_from([
+        "gthr",
+        "-r",
+        &root,
+        "-I",
+        "--git-diff",
+        "HEAD",
+        "-e",
+        "*.md",
+        "direct",
+    ])?;
+    let tree = build_directory_tree(&cli, &Settings::default())?;
+
+    let included: Vec<String> = tree
+        .get_all_included_files()
+        .iter()
+        .map(|node| tree.relative_path(&node.path).to_string_lossy().to_string())
+        .collect();
+    assert_eq!(included, vec!["src/main.rs"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_git_selection_modes_conflict() {
+    let result = Cli::try_parse_from(["gthr", "--staged", "--untracked", "direct"]);
+    assert_eq!(
+        result.err().map(|e| e.kind()),
+        Some(clap::error::ErrorKind::ArgumentConflict)
+    );
+
+    let result = Cli::try_parse_from(["gthr", "--git-staged", "--git-diff", "main", "direct"]);
+    assert_eq!(
+        result.err().map(|e| e.kind()),
+        Some(clap::error::ErrorKind::ArgumentConflict)
+    );
+    let cli = Cli::try_parse_from(["gthr", "--git-staged", "direct"]).unwrap();
+    assert_eq!(
+        cli.git_selection(),
+        Some(gthr::directory::git::GitSelection::Staged)
+    );
+
+    let cli = Cli::try_parse_from(["gthr", "--untracked", "-e", "*.md", "direct"]).unwrap();
+    assert_eq!(
+        cli.git_selection(),
+        Some(gthr::directory::git::GitSelection::Untracked)
+    );
+}
+
+#[test]
+fn test_multiple_roots_with_same_name() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let first = temp_dir.path().join("first").join("app");
+    let second = temp_dir.path().join("second").join("app");
+    fs::create_dir_all(&first)?;
+    fs::create_dir_all(&second)?;
+    fs::write(first.join("main.rs"), "fn main() {}")?;
+    fs::write(second.join("main.rs"), "fn main() {}")?;
+
+    let first_root = first.to_string_lossy().to_string();
+    let second_root = second.to_string_lossy().to_string();
+    let cli = Cli::try_parse_from([
+        "gthr",
+        "-r",
+        &first_root,
+        "-r",
+        &second_root,
+        "-I",
+        "direct",
+    ])?;
+    let tree = build_directory_tree(&cli, &Settings::default())?;
+
+    let included: Vec<String> = tree
+        .get_all_included_files()
+        .iter()
+        .map(|node| tree.relative_path(&node.path).to_string_lossy().to_string())
+        .collect();
+    assert_eq!(included, vec!["app/main.rs", "app-2/main.rs"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_extension_filters() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root_path = temp_dir.path();
+    fs::write(root_path.join("Makefile"), "all:")?;
+    fs::write(root_path.join("Cargo.TOML"), "[package]")?;
+
+    let root = root_path.to_string_lossy().to_string();
+    let included_with = |args: &[&str]| -> Result<Vec<String>> {
+        let cli = Cli::try_parse_from(["gthr", "-r", &root].iter().chain(args))?;
+        let tree = build_directory_tree(&cli, &Settings::default())?;
+        let mut included: Vec<String> = tree
+            .get_all_included_files()
+            .iter()
+            .map(|node| tree.relative_path(&node.path).to_string_lossy().to_string())
+            .collect();
+        included.sort();
+        Ok(included)
+    };
+
+    assert_eq!(
+        included_with(&["--ext", "toml,makefile", "direct"])?,
+        vec!["Cargo.TOML", "Makefile"]
+    );
+    // Extensions and include patterns are combined
+    assert_eq!(
+        included_with(&["--ext", "md", "-i", "src/ui/**", "direct"])?,
+        vec!["README.md", "docs/guide.md", "src/ui/app.rs"]
+    );
+    assert_eq!(
+        included_with(&["-I", "--not-ext", "MD,rs,toml", "direct"])?,
+        vec!["Makefile"]
+    );
+    // Long aliases and short flags, with or without a leading dot
+    assert_eq!(
+        included_with(&["--include-extensions", ".toml", "-x", "rs", "direct"])?,
+        vec!["Cargo.TOML", "src/main.rs", "src/ui/app.rs"]
+    );
+    assert_eq!(
+        included_with(&[
+            "-I",
+            "-X",
+            ".md,.rs",
+            "--exclude-extensions",
+            "toml",
+            "direct"
+        ])?,
+        vec!["Makefile"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_directory_patterns_cascade_to_contents() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root_path = temp_dir.path();
+    fs::create_dir_all(root_path.join("src").join("tests"))?;
+    fs::create_dir_all(root_path.join("target").join("debug"))?;
+    fs::write(
+        root_path.join("src").join("tests").join("it.rs"),
+        "#[test] fn it() {}",
+    )?;
+    fs::write(
+        root_path.join("target").join("debug").join("gthr.d"),
+        "deps",
+    )?;
+    let root = root_path.to_string_lossy().to_string();
+
+    let included_files = |args: &[&str]| -> Result<Vec<String>> {
+        let cli = Cli::try_parse_from(["gthr", "-r", &root].iter().chain(args).chain(&["direct"]))?;
+        let tree = build_directory_tree(&cli, &Settings::default())?;
+        let mut files: Vec<String> = tree
+            .get_all_included_files()
+            .iter()
+            .map(|node| tree.relative_path(&node.path).to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        Ok(files)
+    };
+
+    // A pattern naming a directory selects everything below it
+    assert_eq!(
+        included_files(&["-i", "src"])?,
+        vec!["src/main.rs", "src/tests/it.rs", "src/ui/app.rs"]
+    );
+    assert_eq!(
+        included_files(&["-i", "src/**", "-e", "src/tests/**"])?,
+        vec!["src/main.rs", "src/ui/app.rs"]
+    );
+    assert_eq!(
+        included_files(&["-I", "-e", "target"])?,
+        vec![
+            "README.md",
+            "docs/guide.md",
+            "src/main.rs",
+            "src/tests/it.rs",
+            "src/ui/app.rs"
+        ]
+    );
+
+    // Directory states follow from the files below them
+    let cli = Cli::try_parse_from([
+        "gthr",
+        "-r",
+        &root,
+        "-i",
+        "src/**",
+        "-e",
+        "src/tests/**",
+        "direct",
+    ])?;
+    let tree = build_directory_tree(&cli, &Settings::default())?;
+    let state_of = |relative: &str| tree.nodes[tree.path_to_index[&root_path.join(relative)]].state;
+    assert_eq!(state_of("src"), SelectionState::Partial);
+    assert_eq!(state_of("src/ui"), SelectionState::Included);
+    assert_eq!(state_of("src/tests"), SelectionState::Excluded);
+    assert_eq!(state_of("target"), SelectionState::Excluded);
+
+    Ok(())
+}
+
+#[test]
+fn test_list_included_files() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root_path = temp_dir.path();
+    fs::write(root_path.join("docs").join("my notes.md"), "# Notes")?;
+    let root = root_path.to_string_lossy().to_string();
+    let list = |args: &[&str]| -> Result<String> {
+        let cli = Cli::try_parse_from(["gthr", "-r", &root].iter().chain(args))?;
+        let tree = build_directory_tree(&cli, &Settings::default())?;
+        let gthr::cli::Commands::List { long, null } = cli.command.unwrap() else {
+            panic!("expected the list subcommand");
+        };
+        Ok(format_file_list(&tree, long, null))
+    };
+
+    let mut paths: Vec<String> = list(&["-I", "-e", "src/ui/**", "list"])?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            "README.md",
+            "docs/guide.md",
+            "docs/my notes.md",
+            "src/main.rs"
+        ]
+    );
+
+    let null_separated = list(&["-i", "docs/**", "list", "-0"])?;
+    let mut paths: Vec<&str> = null_separated.split_terminator('\0').collect();
+    paths.sort();
+    assert_eq!(paths, vec!["docs/guide.md", "docs/my notes.md"]);
+
+    assert_eq!(
+        list(&["-i", "src/main.rs", "list", "--long"])?,
+        "        12  rust          src/main.rs\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_save_and_load_selection() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root_path = temp_dir.path();
+    let root = root_path.to_string_lossy().to_string();
+    let selection_path = root_path.join(".gthr-selection");
+
+    let cli = Cli::try_parse_from(["gthr", "-r", &root, "-i", "src/**", "direct"])?;
+    let tree = build_directory_tree(&cli, &Settings::default())?;
+    fs::write(
+        &selection_path,
+        format!(
+            "# saved\n{}docs/removed.md\n",
+            format_file_list(&tree, false, false)
+        ),
+    )?;
+
+    let selection = selection_path.to_string_lossy().to_string();
+    let cli = Cli::try_parse_from([
+        "gthr",
+        "-r",
+        &root,
+        "--load-selection",
+        &selection,
+        "-i",
+        "README.md",
+        "-e",
+        "src/ui/**",
+        "direct",
+    ])?;
+    let tree = build_directory_tree(&cli, &Settings::default())?;
+    let mut included: Vec<String> = format_file_list(&tree, false, false)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    included.sort();
+    assert_eq!(included, vec!["README.md", "src/main.rs"]);
+    let state_of = |relative: &str| tree.nodes[tree.path_to_index[&root_path.join(relative)]].state;
+    assert_eq!(state_of("src"), SelectionState::Partial);
+
+    Ok(())
+}
+
+#[test]
+fn test_print0_keeps_stdout_machine_readable() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root_path = temp_dir.path();
+    fs::write(root_path.join("docs").join("my notes.md"), "# Notes")?;
+    let root = root_path.to_string_lossy().to_string();
+    let run = |args: &[&str]| -> Result<Vec<String>> {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_gthr"))
+            .args(["-r", &root])
+            .args(args)
+            .output()?;
+        assert!(output.status.success(), "gthr {:?} failed", args);
+        let mut paths: Vec<String> = output
+            .stdout
+            .split(|&byte| byte == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| String::from_utf8_lossy(path).to_string())
+            .collect();
+        paths.sort();
+        Ok(paths)
+    };
+
+    let expected = vec!["docs/guide.md", "docs/my notes.md"];
+    assert_eq!(run(&["-i", "docs/**", "--print0", "list"])?, expected);
+    assert_eq!(
+        run(&[
+            "-i",
+            "docs/**",
+            "--copy-path",
+            "--relative",
+            "--print0",
+            "direct"
+        ])?,
+        expected
+    );
+    // Log lines go to stderr, so stdout holds only the paths
+    assert_eq!(run(&["-i", "docs/**", "-v", "--print0", "list"])?, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_exit_codes() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root_path = temp_dir.path();
+    let root = root_path.to_string_lossy().to_string();
+    let run = |args: &[&str]| -> Result<Option<i32>> {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_gthr"))
+            .args(["-r", &root])
+            .args(args)
+            .env("RUST_BACKTRACE", "0")
+            .output()?;
+        Ok(output.status.code())
+    };
+
+    // Nothing selected: the default pre-excludes everything
+    assert_eq!(run(&["-o", "out.md", "direct"])?, Some(2));
+    assert_eq!(run(&["-i", "*.nothing", "--copy-path", "direct"])?, Some(2));
+    assert!(!root_path.join("out.md").exists());
+    assert_eq!(run(&["list"])?, Some(0));
+    assert_eq!(run(&["--fail-on-empty", "list"])?, Some(2));
+    assert_eq!(run(&["--fail-on-empty", "-I", "list"])?, Some(0));
+    assert_eq!(run(&["--fail-on-empty", "tokens"])?, Some(2));
+
+    let blocked = root_path.join("src").join("main.rs").join("out.md");
+    assert_eq!(
+        run(&["-I", "-o", &blocked.to_string_lossy(), "direct"])?,
+        Some(3)
+    );
+
+    let output = root_path.join("context.md");
+    assert_eq!(
+        run(&["-i", "docs/**", "-o", &output.to_string_lossy(), "direct"])?,
+        Some(0)
+    );
+
+    // Usage errors are generic errors, never mistaken for an empty selection
+    assert_eq!(run(&["--no-such-flag", "direct"])?, Some(1));
+
+    Ok(())
+}
+
+#[test]
+fn test_verbose_counts_small_files() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    let root = temp_dir.path().to_string_lossy().to_string();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_gthr"))
+        .args(["-r", &root, "-I", "--min-file-size", "10", "-v", "list"])
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("SKIP docs/guide.md (too small, 7 B)"), "{}", stderr);
+    assert!(stderr.contains("Skipped 1 file smaller than 10 B"), "{}", stderr);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("guide.md"));
+
+    Ok(())
+}
+
+/// Export the fixture with everything included and `config` as its `.gthr.toml`
+fn export_with_config(temp_dir: &TempDir, config: &str) -> Result<String> {
+    let root_path = temp_dir.path();
+    fs::write(root_path.join(".gthr.toml"), config)?;
+    // Written outside the root so the next export does not pick it up
+    let output_dir = TempDir::new()?;
+    let output_path = output_dir.path().join("out.md");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_gthr"))
+        .args(["-r", &root_path.to_string_lossy(), "-I", "--quiet", "--force", "-o"])
+        .arg(&output_path)
+        .arg("direct")
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(fs::read_to_string(output_path)?)
+}
+
+#[test]
+fn test_hash_files_setting() -> Result<()> {
+    let temp_dir = create_fixture()?;
+
+    // Hashes do not turn on the report header
+    let output = export_with_config(&temp_dir, "hash_files = true\n")?;
+    assert!(!output.contains("# Text Ingest Report"), "{}", output);
+    assert!(output.contains("# src/main.rs\n\n**SHA256:** "), "{}", output);
+
+    let output = export_with_config(&temp_dir, "")?;
+    assert!(!output.contains("# Text Ingest Report"), "{}", output);
+    assert!(!output.contains("**SHA256:**"), "{}", output);
+
+    let output = export_with_config(&temp_dir, "hash_files = true\ninclude_metadata = true\n")?;
+    assert!(output.contains("# Text Ingest Report"), "{}", output);
+    assert!(output.contains("**SHA256:** "), "{}", output);
+
+    Ok(())
+}
+
+#[test]
+fn test_skipped_empty_files_in_header() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    fs::write(temp_dir.path().join("docs").join("empty.md"), "")?;
+
+    let config = "skip_empty_files = true\ninclude_metadata = true\n";
+    let output = export_with_config(&temp_dir, config)?;
+    assert!(output.contains("**Empty Files Skipped:** 1"), "{}", output);
+    assert!(!output.contains("# docs/empty.md"), "{}", output);
+
+    Ok(())
+}
+
+#[test]
+fn test_total_lines_in_header() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    fs::write(temp_dir.path().join("docs").join("guide.md"), "# Guide\n\nSteps\n")?;
+
+    let output = export_with_config(&temp_dir, "include_metadata = true\n")?;
+    assert!(output.contains("**Total Lines:** 6\n"), "{}", output);
+
+    Ok(())
+}
+
+#[test]
+fn test_transcoded_encoding_in_output() -> Result<()> {
+    let temp_dir = create_fixture()?;
+    fs::write(temp_dir.path().join("docs").join("latin.txt"), b"caf\xe9\n")?;
+
+    let output = export_with_config(&temp_dir, "include_metadata = true\n")?;
+    assert!(
+        output.contains("**Encoding:** windows-1252 (transcoded to UTF-8)"),
+        "{}",
+        output
+    );
+    assert!(output.contains("café"), "{}", output);
+
+    Ok(())
+}